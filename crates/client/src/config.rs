@@ -24,6 +24,7 @@ pub struct ClientConfig {
     pub user_agent: String,
     pub out_file: OutFile,
     pub http_type: HttpType,
+    pub compressed: bool,
 }
 
 impl ClientConfig {
@@ -56,6 +57,7 @@ impl ClientConfig {
                 "--license" => license(),
                 "-O" => conf.out_file = OutFile::GetFromUrl,
                 "-o" => conf.out_file = OutFile::Filename(parse_next!()),
+                "--compressed" => conf.compressed = true,
                 _ => conf.url = arg,
             }
         }
@@ -102,12 +104,14 @@ fn help() -> ! {
         "\
 USAGE: http-client [--method <HTTP Method>] [--host <hostname>]
                    [--user-agent <User Agent>] [-O] [-o <output-file>]
+                   [--compressed]
 PARAMETERS:
     -m, --method    Set HTTP method
     --host  Set target hostname
     -a, --user-agent Set user agent string
     -O  Get output filename from the url
     -o  Set output filename
+    --compressed Request a compressed response, and decompress it
     -h, --help Display this help guide
     --license Display the license for this program"
     );
@@ -149,6 +153,7 @@ impl Default for ClientConfig {
             user_agent: "http-client".to_string(),
             out_file: OutFile::Stdout,
             http_type: HttpType::Http,
+            compressed: false,
         }
     }
 }
@@ -172,4 +177,12 @@ mod test {
         let conf = parse_from_vec(&conf).unwrap();
         assert_eq!(conf.host, "?unknown");
     }
+
+    #[test]
+    fn compressed() {
+        let conf = parse_from_vec(&["localhost/"]).unwrap();
+        assert!(!conf.compressed);
+        let conf = parse_from_vec(&["--compressed", "localhost/"]).unwrap();
+        assert!(conf.compressed);
+    }
 }
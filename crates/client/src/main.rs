@@ -8,7 +8,7 @@ use std::{
     time::Instant,
 };
 
-use http::{HttpMethod, HttpRequest, HttpResponse};
+use http::{HttpMethod, HttpRequest, HttpResponse, HttpVersion};
 mod config;
 use config::ClientConfig;
 
@@ -147,16 +147,19 @@ pub fn main() -> http::Result<()> {
     let addr = format!("{}:{}", conf.host, conf.port);
     let addrs = addr.to_socket_addrs().unwrap().next().unwrap();
 
-    let req = HttpRequest::builder()
+    let mut req = HttpRequest::builder()
         .method(conf.method)
         .url(conf.url.clone().into_boxed_str())
-        .version(1.1)
+        .version(HttpVersion::Http11)
         .header("Host", conf.host.clone().into_boxed_str())
         .header("Accept", "*/*")
         .header("User-Agent", "http-client")
         .header("Connection", "close")
-        .header("Accept-Encoding", "identity")
-        .build();
+        .header("Accept-Encoding", "identity");
+    if conf.compressed {
+        req = req.compressed();
+    }
+    let req = req.build();
 
     let tcp = match TcpStream::connect(addrs) {
         Ok(tcp) => tcp,
@@ -172,7 +175,12 @@ pub fn main() -> http::Result<()> {
             process::exit(1);
         });
 
-    let len = result.content_length();
+    /* The Content-Length is the size of the encoded body */
+    let len = if conf.compressed && result.header("Content-Encoding").is_some() {
+        0
+    } else {
+        result.content_length()
+    };
     let mut out: Box<dyn Write> = match conf.out_file {
         config::OutFile::Stdout => Box::new(stdout()),
         config::OutFile::Filename(s) => open_file(&s, len),
@@ -192,7 +200,6 @@ pub fn main() -> http::Result<()> {
         for (k, v) in result.headers() {
             println!("{k}: {v}");
         }
-        return Ok(());
     }
 
     match result.write_to(&mut out) {
@@ -0,0 +1,318 @@
+//! HTTP Cookies
+//!
+//! See [RFC 6265](https://www.rfc-editor.org/rfc/rfc6265)
+
+use core::fmt;
+use std::time::SystemTime;
+
+use crate::{Result, date::format_http_date, err, headers::is_token};
+
+/// Parses the value of a *Cookie* header into (name, value) pairs
+///
+/// Malformed pairs are skipped. Quoted values are returned without the quotes.
+///
+/// # Example
+/// ```
+/// use http::cookie::parse_cookies;
+///
+/// let cookies: Vec<_> = parse_cookies("id=a3fWa; theme=\"dark\"").collect();
+/// assert_eq!(cookies, [("id", "a3fWa"), ("theme", "dark")]);
+/// ```
+pub fn parse_cookies(header: &str) -> impl Iterator<Item = (&str, &str)> {
+    header.split(';').filter_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        let name = name.trim();
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        is_token(name).then_some((name, value))
+    })
+}
+
+/// Returns true if the value only contains cookie-octets
+fn is_cookie_value(value: &str) -> bool {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    value
+        .bytes()
+        .all(|b| matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E))
+}
+
+/// Returns true if the path is a valid `path-value`:
+/// any CHAR except CTLs or ';'
+fn is_path_value(path: &str) -> bool {
+    path.bytes()
+        .all(|b| b.is_ascii() && !b.is_ascii_control() && b != b';')
+}
+
+/// Returns true if the domain is a valid `domain-value`: letters,
+/// digits, hyphens and dots. A leading dot is allowed, and ignored by clients
+fn is_domain_value(domain: &str) -> bool {
+    let domain = domain.strip_prefix('.').unwrap_or(domain);
+    !domain.is_empty()
+        && domain
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+}
+
+/// Value of the `SameSite` attribute of a [`SetCookie`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        })
+    }
+}
+
+/// Builder for the value of a *Set-Cookie* header
+///
+/// # Example
+/// ```
+/// use http::cookie::{SameSite, SetCookie};
+///
+/// let cookie = SetCookie::new("id", "a3fWa")
+///     .unwrap()
+///     .path("/")
+///     .unwrap()
+///     .max_age(3600)
+///     .http_only(true)
+///     .same_site(SameSite::Lax);
+/// assert_eq!(
+///     cookie.to_string(),
+///     "id=a3fWa; Max-Age=3600; Path=/; HttpOnly; SameSite=Lax"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetCookie {
+    name: Box<str>,
+    value: Box<str>,
+    expires: Option<SystemTime>,
+    max_age: Option<i64>,
+    domain: Option<Box<str>>,
+    path: Option<Box<str>>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    partitioned: bool,
+}
+
+impl SetCookie {
+    /// Creates a new cookie
+    ///
+    /// # Errors
+    /// - If the name is not a valid token
+    /// - If the value contains characters not allowed in a cookie
+    ///   (whitespace, '"', ',', ';' or '\\')
+    pub fn new(name: impl Into<Box<str>>, value: impl Into<Box<str>>) -> Result<Self> {
+        let name = name.into();
+        let value = value.into();
+        if !is_token(&name) {
+            return err!(kind = Parse, "Invalid cookie name: \"{name}\"");
+        }
+        if !is_cookie_value(&value) {
+            return err!(kind = Parse, "Invalid cookie value: \"{value}\"");
+        }
+        Ok(Self {
+            name,
+            value,
+            expires: None,
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            partitioned: false,
+        })
+    }
+
+    /// Date at which the cookie expires
+    #[must_use]
+    pub fn expires(mut self, time: SystemTime) -> Self {
+        self.expires = Some(time);
+        self
+    }
+
+    /// Number of seconds until the cookie expires.
+    ///
+    /// A zero or negative number expires the cookie immediately.
+    #[must_use]
+    pub fn max_age(mut self, secs: i64) -> Self {
+        self.max_age = Some(secs);
+        self
+    }
+
+    /// Host to which the cookie will be sent
+    ///
+    /// # Errors
+    /// If the domain contains characters other than letters,
+    /// digits, '-' or '.'
+    pub fn domain(mut self, domain: impl Into<Box<str>>) -> Result<Self> {
+        let domain = domain.into();
+        if !is_domain_value(&domain) {
+            return err!(kind = Parse, "Invalid cookie domain: {domain:?}");
+        }
+        self.domain = Some(domain);
+        Ok(self)
+    }
+
+    /// Path that must exist in the requested url for the cookie to be sent
+    ///
+    /// # Errors
+    /// If the path contains control characters or ';'
+    pub fn path(mut self, path: impl Into<Box<str>>) -> Result<Self> {
+        let path = path.into();
+        if !is_path_value(&path) {
+            return err!(kind = Parse, "Invalid cookie path: {path:?}");
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// Only send the cookie over secure connections
+    #[must_use]
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Forbid scripts from accessing the cookie
+    #[must_use]
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Controls if the cookie is sent with cross-site requests
+    ///
+    /// # NOTE
+    /// [`SameSite::None`] implies [Secure](Self::secure)
+    #[must_use]
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Store the cookie using partitioned storage
+    ///
+    /// # NOTE
+    /// Partitioned implies [Secure](Self::secure)
+    #[must_use]
+    pub fn partitioned(mut self, partitioned: bool) -> Self {
+        self.partitioned = partitioned;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", format_http_date(expires))?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        /* Browsers reject these cookies if they're not Secure */
+        if self.secure || self.partitioned || self.same_site == Some(SameSite::None) {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={same_site}")?;
+        }
+        if self.partitioned {
+            f.write_str("; Partitioned")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{SameSite, SetCookie, parse_cookies};
+
+    #[test]
+    fn parse() {
+        let cookies: Vec<_> = parse_cookies("a=1;b=2 ;  c=; =bad; d").collect();
+        assert_eq!(cookies, [("a", "1"), ("b", "2"), ("c", "")]);
+    }
+
+    #[test]
+    fn all_attributes() {
+        let cookie = SetCookie::new("session", "\"abc\"")
+            .unwrap()
+            .expires(UNIX_EPOCH + Duration::from_secs(1_445_412_480))
+            .max_age(-1)
+            .domain("example.com")
+            .unwrap()
+            .path("/docs")
+            .unwrap()
+            .http_only(true)
+            .same_site(SameSite::None)
+            .partitioned(true);
+        assert_eq!(
+            cookie.to_string(),
+            "session=\"abc\"; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=-1; \
+             Domain=example.com; Path=/docs; Secure; HttpOnly; SameSite=None; Partitioned"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(SetCookie::new("a b", "1").is_err());
+        assert!(SetCookie::new("a", "1;2").is_err());
+        assert!(SetCookie::new("a", "x\r\nInjected: 1").is_err());
+    }
+
+    #[test]
+    fn invalid_attributes() {
+        let cookie = SetCookie::new("a", "1").unwrap();
+        assert!(cookie.clone().path("/x; Domain=evil.com").is_err());
+        assert!(cookie.clone().path("/x\r\nInjected: 1").is_err());
+        assert!(cookie.clone().path("/caf\u{e9}").is_err());
+        assert!(cookie.clone().domain("evil.com; Secure").is_err());
+        assert!(cookie.clone().domain("a b.com").is_err());
+        assert!(cookie.clone().domain("").is_err());
+
+        let cookie = cookie.domain(".example.com").unwrap().path("/a b").unwrap();
+        assert_eq!(cookie.to_string(), "a=1; Domain=.example.com; Path=/a b");
+    }
+}
@@ -0,0 +1,259 @@
+//! HTTP dates
+//!
+//! See [RFC 9110, section 5.6.7](https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7)
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const LONG_DAYS: [&str; 7] = [
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats the time as an HTTP date (IMF-fixdate)
+///
+/// Times before the UNIX epoch are formatted as the epoch.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use http::date::format_http_date;
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+/// assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+#[must_use]
+pub fn format_http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let days = secs / 86400;
+    let secs = secs % 86400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1],
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Parses an HTTP date
+///
+/// Besides the preferred IMF-fixdate format, it accepts the
+/// obsolete RFC 850 and ANSI C's asctime() formats, as recipients must.
+/// Returns None if the date is invalid, or before the UNIX epoch.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use http::date::parse_http_date;
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+/// assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(time));
+/// assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(time));
+/// assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), Some(time));
+/// ```
+#[must_use]
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let mut parts = date.split_whitespace();
+    let day_name = parts.next()?;
+    let (year, month, day, time) = match day_name.strip_suffix(',') {
+        Some(day_name) if DAYS.contains(&day_name) => {
+            /* IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT */
+            let day = parse_digits(parts.next()?, 2)?;
+            let month = parse_month(parts.next()?)?;
+            let year = parse_digits(parts.next()?, 4)?;
+            (year, month, day, gmt_time(&mut parts)?)
+        }
+        Some(day_name) if LONG_DAYS.contains(&day_name) => {
+            /* RFC 850: Sunday, 06-Nov-94 08:49:37 GMT */
+            let mut date = parts.next()?.split('-');
+            let day = parse_digits(date.next()?, 2)?;
+            let month = parse_month(date.next()?)?;
+            let year = full_year(parse_digits(date.next()?, 2)?);
+            if date.next().is_some() {
+                return None;
+            }
+            (year, month, day, gmt_time(&mut parts)?)
+        }
+        None if DAYS.contains(&day_name) => {
+            /* asctime: Sun Nov  6 08:49:37 1994 */
+            let month = parse_month(parts.next()?)?;
+            let day = parts.next()?;
+            let day = parse_digits(day, day.len().clamp(1, 2))?;
+            let time = parts.next()?;
+            let year = parse_digits(parts.next()?, 4)?;
+            (year, month, day, time)
+        }
+        _ => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    let mut time = time.split(':');
+    let hour = parse_digits(time.next()?, 2)?;
+    let min = parse_digits(time.next()?, 2)?;
+    let sec = parse_digits(time.next()?, 2)?;
+    /* 60 allows for leap seconds */
+    if time.next().is_some() || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+    if year < 1970 || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + min * 60 + sec;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Returns the time of day, which must be followed by the GMT zone
+fn gmt_time<'a>(parts: &mut impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let time = parts.next()?;
+    (parts.next()? == "GMT").then_some(time)
+}
+
+/// Parses a number of exactly `len` ASCII digits
+fn parse_digits(s: &str, len: usize) -> Option<u64> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parses a month name, returning it's number (1-12)
+fn parse_month(s: &str) -> Option<u64> {
+    let i = MONTHS.iter().position(|m| *m == s)?;
+    Some(i as u64 + 1)
+}
+
+/// Interprets the two digit year of an RFC 850 date
+///
+/// As RFC 9110 says, a year that appears to be more than 50 years
+/// in the future is the most recent year in the past with those digits.
+fn full_year(year: u64) -> u64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (current, ..) = civil_from_days(secs / 86400);
+    let year = current - current % 100 + year;
+    if year > current + 50 {
+        year - 100
+    } else {
+        year
+    }
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a (year, month, day) date into a number of days since the UNIX epoch
+///
+/// The inverse of [`civil_from_days`]. The date must not be before the epoch.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Converts a number of days since the UNIX epoch into a (year, month, day) date
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{format_http_date, parse_http_date};
+
+    #[test]
+    fn format() {
+        let cases = [
+            (0, "Thu, 01 Jan 1970 00:00:00 GMT"),
+            (951_782_400, "Tue, 29 Feb 2000 00:00:00 GMT"),
+            (1_445_412_480, "Wed, 21 Oct 2015 07:28:00 GMT"),
+            (4_133_980_799, "Fri, 31 Dec 2100 23:59:59 GMT"),
+        ];
+        for (secs, date) in cases {
+            assert_eq!(
+                format_http_date(UNIX_EPOCH + Duration::from_secs(secs)),
+                date
+            );
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let cases = [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun Nov 06 08:49:37 1994",
+        ];
+        for date in cases {
+            assert_eq!(parse_http_date(date), Some(time), "{date}");
+        }
+        for secs in [0, 951_782_400, 1_445_412_480, 4_133_980_799] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_http_date(&format_http_date(time)), Some(time));
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let cases = [
+            "",
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 6 Nov 1994 08:49:37 GMT",
+            "Sun, 06 nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Sun, 06 Nov 1994 08:49:37 GMT extra",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Tue, 29 Feb 2100 00:00:00 GMT",
+            "Wed, 31 Dec 1969 23:59:59 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994 GMT",
+            "Someday, 06 Nov 1994 08:49:37 GMT",
+        ];
+        for date in cases {
+            assert_eq!(parse_http_date(date), None, "{date}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::io::{BufRead, Read, Result, Take};
+
+use super::ChunkedDecoder;
+use crate::{HeaderMap, ParserLimits, Status, err};
+
+/// How the length of a message body is determined
+///
+/// See [RFC 9112, section 6.3](https://www.rfc-editor.org/rfc/rfc9112#section-6.3)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    /// The message doesn't have a body
+    Empty,
+    /// The body has a fixed length, from the *Content-Length* header
+    Length(u64),
+    /// The body is sent with chunked *Transfer-Encoding*
+    Chunked,
+    /// The body ends when the connection is closed
+    Close,
+}
+
+impl BodyKind {
+    /// Determines the framing of a request's body from it's headers
+    ///
+    /// # Errors
+    /// - If the request has a *Transfer-Encoding* that doesn't end with chunked
+    /// - If the request has both *Transfer-Encoding* and *Content-Length*
+    /// - If the *Content-Length* header is invalid
+    pub fn of_request(headers: &HeaderMap) -> crate::Result<Self> {
+        if let Some(chunked) = is_chunked(headers) {
+            if !chunked {
+                return err!(
+                    kind = Parse,
+                    "The last transfer coding of a request must be chunked"
+                );
+            }
+            /* A proxy in front of us might have used the Content-Length
+             * instead, and sent the rest of the body as another request */
+            if headers.get("Content-Length").is_some() {
+                return err!(
+                    kind = Parse,
+                    "Request has both Transfer-Encoding and Content-Length"
+                );
+            }
+            return Ok(BodyKind::Chunked);
+        }
+        Ok(match content_length(headers)? {
+            Some(len) => BodyKind::Length(len),
+            None => BodyKind::Empty,
+        })
+    }
+
+    /// Determines the framing of a response's body from it's status and headers
+    ///
+    /// # Errors
+    /// If the *Content-Length* header is invalid
+    pub fn of_response(status: Status, headers: &HeaderMap) -> crate::Result<Self> {
+        if status.is_informational()
+            || status == Status::NO_CONTENT
+            || status == Status::NOT_MODIFIED
+        {
+            return Ok(BodyKind::Empty);
+        }
+        if let Some(chunked) = is_chunked(headers) {
+            return Ok(if chunked {
+                BodyKind::Chunked
+            } else {
+                BodyKind::Close
+            });
+        }
+        Ok(match content_length(headers)? {
+            Some(len) => BodyKind::Length(len),
+            None => BodyKind::Close,
+        })
+    }
+}
+
+/// Returns whether the last transfer coding is chunked, or
+/// [None] if there's no *Transfer-Encoding* header
+pub(crate) fn is_chunked(headers: &HeaderMap) -> Option<bool> {
+    headers.get_all("Transfer-Encoding").last().map(|te| {
+        te.rsplit(',')
+            .next()
+            .is_some_and(|last| last.trim().eq_ignore_ascii_case("chunked"))
+    })
+}
+
+/// Parses the *Content-Length* header
+///
+/// It can appear multiple times, or as a list, as long as all values are the same.
+pub(crate) fn content_length(headers: &HeaderMap) -> crate::Result<Option<u64>> {
+    let mut len = None;
+    for value in headers.get_all("Content-Length").flat_map(|v| v.split(',')) {
+        let value = value.trim();
+        if !value.bytes().all(|b| b.is_ascii_digit()) {
+            return err!(kind = Parse, "Invalid Content-Length: {value}");
+        }
+        let value: u64 = value.parse()?;
+        if len.is_some_and(|len| len != value) {
+            return err!(kind = Parse, "Conflicting Content-Length values");
+        }
+        len = Some(value);
+    }
+    Ok(len)
+}
+
+enum Inner<R: BufRead> {
+    Empty(R),
+    Length(Take<R>),
+    Chunked(ChunkedDecoder<R>),
+    Close(R),
+}
+
+/// A reader for the body of an HTTP message
+///
+/// It reads exactly the bytes that belong to the body, as
+/// described by it's [`BodyKind`], and stops there. This way,
+/// the underlying reader is left at the start of the next message.
+///
+/// # Example
+/// ```
+/// use std::io::Read;
+/// use http::encoding::{BodyKind, BodyReader};
+///
+/// let stream = "Hello world!GET / HTTP/1.1";
+/// let mut body = BodyReader::new(stream.as_bytes(), BodyKind::Length(12));
+///
+/// let mut buf = String::new();
+/// body.read_to_string(&mut buf).unwrap();
+/// assert_eq!(buf, "Hello world!");
+/// ```
+pub struct BodyReader<R: BufRead> {
+    inner: Inner<R>,
+}
+
+impl<R: BufRead> BodyReader<R> {
+    pub fn new(reader: R, kind: BodyKind) -> Self {
+        let inner = match kind {
+            BodyKind::Empty => Inner::Empty(reader),
+            BodyKind::Length(len) => Inner::Length(reader.take(len)),
+            BodyKind::Chunked => Inner::Chunked(ChunkedDecoder::new(reader)),
+            BodyKind::Close => Inner::Close(reader),
+        };
+        Self { inner }
+    }
+
+    /// Sets the [limits](ParserLimits) of the trailer section, if the body is chunked.
+    ///
+    /// See [`ChunkedDecoder::with_limits`]
+    #[must_use]
+    pub fn with_limits(self, limits: ParserLimits) -> Self {
+        let inner = match self.inner {
+            Inner::Chunked(decoder) => Inner::Chunked(decoder.with_limits(limits)),
+            inner => inner,
+        };
+        Self { inner }
+    }
+
+    /// Returns the trailers of the body, if it's chunked.
+    pub fn trailers(&self) -> Option<&HeaderMap> {
+        match &self.inner {
+            Inner::Chunked(decoder) => Some(decoder.trailers()),
+            _ => None,
+        }
+    }
+
+    /// Consumes the reader, returning the trailers of
+    /// the body if it's chunked, or an empty map otherwise.
+    pub fn into_trailers(self) -> HeaderMap {
+        match self.inner {
+            Inner::Chunked(decoder) => decoder.into_trailers(),
+            _ => HeaderMap::new(),
+        }
+    }
+
+    /// Returns true if the whole body has been read
+    ///
+    /// A body that ends when the connection is closed is
+    /// never done, since it's end can't be known in advance.
+    pub fn is_done(&self) -> bool {
+        match &self.inner {
+            Inner::Empty(_) => true,
+            Inner::Length(take) => take.limit() == 0,
+            Inner::Chunked(decoder) => decoder.is_done(),
+            Inner::Close(_) => false,
+        }
+    }
+
+    /// Gets a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        match &self.inner {
+            Inner::Empty(r) | Inner::Close(r) => r,
+            Inner::Length(take) => take.get_ref(),
+            Inner::Chunked(decoder) => decoder.get_ref(),
+        }
+    }
+
+    /// Gets a mutable reference to the underlying reader
+    ///
+    /// Reading from it directly will skip the framing of the body
+    pub fn get_mut(&mut self) -> &mut R {
+        match &mut self.inner {
+            Inner::Empty(r) | Inner::Close(r) => r,
+            Inner::Length(take) => take.get_mut(),
+            Inner::Chunked(decoder) => decoder.get_mut(),
+        }
+    }
+
+    /// Consumes the body reader, returning the underlying reader
+    ///
+    /// If the body hasn't been read completely, the
+    /// reader is left in the middle of it.
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::Empty(r) | Inner::Close(r) => r,
+            Inner::Length(take) => take.into_inner(),
+            Inner::Chunked(decoder) => decoder.into_inner(),
+        }
+    }
+}
+
+impl<R: BufRead> Read for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match &mut self.inner {
+            Inner::Empty(_) => Ok(0),
+            Inner::Length(take) => {
+                let expected = take.limit();
+                let n = take.read(buf)?;
+                if n == 0 && expected > 0 && !buf.is_empty() {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(n)
+            }
+            Inner::Chunked(decoder) => decoder.read(buf),
+            Inner::Close(reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read_all(input: &str, kind: BodyKind) -> Result<(String, usize)> {
+        let mut input = input.as_bytes();
+        let mut out = String::new();
+        BodyReader::new(&mut input, kind).read_to_string(&mut out)?;
+        Ok((out, input.len()))
+    }
+
+    #[test]
+    fn length() {
+        let (body, left) = read_all("abcdefNEXT", BodyKind::Length(6)).unwrap();
+        assert_eq!(body, "abcdef");
+        assert_eq!(left, 4);
+        assert!(read_all("abc", BodyKind::Length(6)).is_err());
+    }
+
+    #[test]
+    fn chunked() {
+        let (body, left) = read_all("3\r\nabc\r\n0\r\n\r\nNEXT", BodyKind::Chunked).unwrap();
+        assert_eq!(body, "abc");
+        assert_eq!(left, 4);
+    }
+
+    #[test]
+    fn close_and_empty() {
+        let (body, left) = read_all("abcdef", BodyKind::Close).unwrap();
+        assert_eq!(body, "abcdef");
+        assert_eq!(left, 0);
+
+        let (body, left) = read_all("abcdef", BodyKind::Empty).unwrap();
+        assert_eq!(body, "");
+        assert_eq!(left, 6);
+    }
+
+    #[test]
+    fn done() {
+        let mut body = BodyReader::new("abcNEXT".as_bytes(), BodyKind::Length(3));
+        assert!(!body.is_done());
+        body.read_exact(&mut [0; 3]).unwrap();
+        assert!(body.is_done());
+        assert_eq!(body.into_inner(), b"NEXT");
+
+        let mut body = BodyReader::new("1\r\na\r\n0\r\n\r\nNEXT".as_bytes(), BodyKind::Chunked);
+        assert!(!body.is_done());
+        body.read_to_end(&mut Vec::new()).unwrap();
+        assert!(body.is_done());
+        assert_eq!(body.into_inner(), b"NEXT");
+
+        assert!(BodyReader::new("".as_bytes(), BodyKind::Empty).is_done());
+    }
+}
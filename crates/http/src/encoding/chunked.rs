@@ -1,12 +1,22 @@
-use std::io::{Read, Result, Write};
+use std::io::{BufRead, Error, ErrorKind, Read, Result, Write};
+
+use crate::{
+    HeaderMap, ParserLimits,
+    parser::{Event, Parser},
+};
 
 /// A reader for [HTTP Chunked transfer encoding]
 ///
+/// It reads from the underlying reader and outputs it's data in
+/// chunks of, at most, `CHUNK_SIZE` bytes. When the reader is exhausted,
+/// the last (zero-sized) chunk is emitted.
+///
 /// [HTTP Chunked transfer encoding]: <https://en.wikipedia.org/wiki/Chunked_transfer_encoding>
 pub struct Chunked<R: Read, const CHUNK_SIZE: usize = 1024> {
     reader: R,
     chunk: Vec<u8>,
     offset: usize,
+    finished: bool,
 }
 
 impl<R: Read> Chunked<R> {
@@ -25,16 +35,23 @@ impl<R: Read, const CHUNK_SIZE: usize> Chunked<R, CHUNK_SIZE> {
             reader,
             chunk: Vec::with_capacity(CHUNK_SIZE + 8),
             offset: 0,
+            finished: false,
         }
     }
     fn next_chunk(&mut self) -> Result<bool> {
         self.chunk.clear();
         self.offset = 0;
 
+        if self.finished {
+            return Ok(false);
+        }
+
         let mut tmpbuf: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
         let n = self.reader.read(&mut tmpbuf)?;
         if n == 0 {
-            return Ok(false);
+            self.finished = true;
+            self.chunk.write_all(b"0\r\n\r\n")?;
+            return Ok(true);
         }
         self.chunk.write_all(format!("{n:X}\r\n").as_bytes())?;
         self.chunk.write_all(&tmpbuf[0..n])?;
@@ -81,6 +98,137 @@ impl<R: Read + Default> Default for Chunked<R> {
     }
 }
 
+/// A reader that decodes a body sent with [HTTP Chunked transfer encoding]
+///
+/// It reads the chunks from the underlying [`BufRead`], and outputs
+/// just their data, without the chunk framing. When the last chunk is read,
+/// the trailer section is parsed, and can be retrieved with
+/// [trailers](Self::trailers).
+///
+/// The chunks are decoded by a [`Parser`], just like the
+/// bodies of the messages it parses.
+///
+/// # Example
+/// ```
+/// use std::io::Read;
+/// use http::encoding::ChunkedDecoder;
+///
+/// let body = "5\r\nHello\r\n7;lang=en\r\n, world\r\n0\r\nExpires: never\r\n\r\n";
+/// let mut decoder = ChunkedDecoder::new(body.as_bytes());
+/// let mut out = String::new();
+/// decoder.read_to_string(&mut out).unwrap();
+///
+/// assert_eq!(out, "Hello, world");
+/// assert_eq!(decoder.trailers().get("Expires"), Some("never"));
+/// ```
+///
+/// [HTTP Chunked transfer encoding]: <https://www.rfc-editor.org/rfc/rfc9112#section-7.1>
+pub struct ChunkedDecoder<R: BufRead> {
+    reader: R,
+    parser: Parser,
+    trailers: HeaderMap,
+}
+
+impl<R: BufRead> ChunkedDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            parser: Parser::chunked_body(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    /// Sets the [limits](ParserLimits) of the chunk lines and the trailer section
+    ///
+    /// If the trailers exceed them, reading fails with an error
+    /// that wraps an [`HeaderFieldsTooLarge`](crate::error::ErrorKind::HeaderFieldsTooLarge)
+    /// [`HttpError`](crate::HttpError).
+    #[must_use]
+    pub fn with_limits(self, limits: ParserLimits) -> Self {
+        Self {
+            parser: self.parser.with_limits(limits),
+            ..self
+        }
+    }
+
+    /// Returns true if the whole body, including the trailers, has been read
+    pub fn is_done(&self) -> bool {
+        self.parser.is_idle()
+    }
+
+    /// Returns the extensions of the last chunk read
+    ///
+    /// Extensions without a value are returned with an empty string.
+    pub fn extensions(&self) -> &[(Box<str>, Box<str>)] {
+        self.parser.chunk_extensions()
+    }
+
+    /// Returns the trailer headers of the body.
+    ///
+    /// # NOTE
+    /// The trailers are only available once the whole body
+    /// has been read. See [`is_done`](Self::is_done)
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    /// Consumes the decoder, returning it's trailers
+    pub fn into_trailers(self) -> HeaderMap {
+        self.trailers
+    }
+
+    /// Gets a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader
+    ///
+    /// Reading from it directly will corrupt the chunked body
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes the decoder, returning the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Read for ChunkedDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while !self.is_done() && !buf.is_empty() {
+            let input = self.reader.fill_buf()?;
+            if input.is_empty() {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            /* The parser can't give back the data it consumes,
+             * so it can't be fed more than what fits in buf */
+            let len = input.len().min(buf.len());
+            let (consumed, event) = self
+                .parser
+                .parse(&input[..len])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+            let n = match event {
+                Some(Event::Data(data)) => {
+                    buf[..data.len()].copy_from_slice(data);
+                    data.len()
+                }
+                Some(Event::End(trailers)) => {
+                    self.trailers = trailers;
+                    0
+                }
+                Some(Event::Head(_)) | None => 0,
+            };
+            self.reader.consume(consumed);
+            if n > 0 {
+                return Ok(n);
+            }
+        }
+        Ok(0)
+    }
+}
+
 #[cfg(test)]
 mod test {
     pub use super::*;
@@ -99,6 +247,7 @@ mod test {
             expected.extend_from_slice(chunk);
             expected.extend_from_slice(b"\r\n");
         }
+        expected.extend_from_slice(b"0\r\n\r\n");
         assert_eq!(out, expected);
     }
 
@@ -118,4 +267,82 @@ mod test {
     fn with_remaining() {
         test_chunks(&"a".repeat(SIZE + 200));
     }
+
+    fn decode(input: &str) -> Result<(String, ChunkedDecoder<&[u8]>)> {
+        let mut decoder = ChunkedDecoder::new(input.as_bytes());
+        let mut out = String::new();
+        decoder.read_to_string(&mut out)?;
+        Ok((out, decoder))
+    }
+
+    #[test]
+    fn decode_chunks() {
+        let (out, decoder) =
+            decode("4\r\nWiki\r\n7\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\n\r\n").unwrap();
+        assert_eq!(out, "Wikipedia in \r\nchunks.");
+        assert!(decoder.is_done());
+        assert!(decoder.trailers().is_empty());
+    }
+
+    #[test]
+    fn decode_extensions_and_trailers() {
+        let (out, decoder) = decode(
+            "3;name=value;flag\r\nabc\r\n0;last=\"yes\"\r\nDigest: abcd\r\nExpires: never\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(out, "abc");
+        assert_eq!(decoder.extensions(), &[("last".into(), "yes".into())]);
+        let trailers = decoder.into_trailers();
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers.get("digest"), Some("abcd"));
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let input = "a".repeat(SIZE * 3 + 17);
+        let mut encoded = Vec::new();
+        Chunked::<_, SIZE>::new(input.as_bytes())
+            .read_to_end(&mut encoded)
+            .unwrap();
+        let encoded = String::from_utf8(encoded).unwrap();
+        let (out, _) = decode(&encoded).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn decode_small_reads() {
+        let mut decoder =
+            ChunkedDecoder::new("3;a=b\r\nabc\r\n2\r\nde\r\n0\r\nX: y\r\n\r\n".as_bytes());
+        let mut out = Vec::new();
+        let mut byte = [0; 1];
+        while decoder.read(&mut byte).unwrap() > 0 {
+            out.push(byte[0]);
+        }
+        assert_eq!(out, b"abcde");
+        assert!(decoder.is_done());
+        assert_eq!(decoder.trailers().get("x"), Some("y"));
+    }
+
+    #[test]
+    fn trailer_limits() {
+        let limits = ParserLimits::default().max_headers(2);
+        let input = format!("1\r\na\r\n0\r\n{}\r\n", "X: y\r\n".repeat(3));
+        let mut decoder = ChunkedDecoder::new(input.as_bytes()).with_limits(limits);
+        let err = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(crate::HttpError::from(err).status(), 431);
+
+        let limits = ParserLimits::default().max_header_bytes(64);
+        let input = format!("0\r\n{}\r\n", "X: y\r\n".repeat(100));
+        let mut decoder = ChunkedDecoder::new(input.as_bytes()).with_limits(limits);
+        let err = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(crate::HttpError::from(err).status(), 431);
+    }
+
+    #[test]
+    fn decode_malformed() {
+        assert!(decode("zz\r\nabc\r\n0\r\n\r\n").is_err());
+        assert!(decode("3\r\nabcdef\r\n0\r\n\r\n").is_err());
+        assert!(decode("5\r\nabc").is_err());
+        assert!(decode("3;=x\r\nabc\r\n0\r\n\r\n").is_err());
+    }
 }
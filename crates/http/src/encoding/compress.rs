@@ -0,0 +1,298 @@
+//! Content codings
+//!
+//! [Read] and [Write] adapters that compress and decompress
+//! data with the gzip, deflate and brotli
+//! [content codings](https://www.rfc-editor.org/rfc/rfc9110#section-8.4.1)
+//!
+//! # Example
+//! ```
+//! use std::io::{Read, Write};
+//! use http::encoding::compress::{CompressWriter, ContentCoding, DecompressReader};
+//!
+//! let mut writer = CompressWriter::new(Vec::new(), ContentCoding::Gzip);
+//! writer.write_all(b"Hello world!").unwrap();
+//! let compressed = writer.finish().unwrap();
+//!
+//! let mut reader = DecompressReader::new(&compressed[..], ContentCoding::Gzip);
+//! let mut text = String::new();
+//! reader.read_to_string(&mut text).unwrap();
+//! assert_eq!(text, "Hello world!");
+//! ```
+
+use core::fmt;
+use std::{
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+use flate2::{
+    Compression,
+    read::{GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder},
+    write,
+};
+
+use crate::{HttpError, Result, err};
+
+/* Brotli's default quality (11) is too slow to compress on the fly */
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_LGWIN: u32 = 22;
+const BUFFER_SIZE: usize = 4096;
+
+/// A compressing content coding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentCoding {
+    Gzip,
+    /// The "zlib" format, as specified by
+    /// [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-8.4.1.2)
+    Deflate,
+    Brotli,
+}
+
+impl ContentCoding {
+    /// All the supported codings, from the most to the least efficient
+    pub const ALL: [ContentCoding; 3] = [Self::Brotli, Self::Gzip, Self::Deflate];
+
+    /// Returns the name of the coding, as used in the
+    /// *Content-Encoding* and *Accept-Encoding* headers
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Brotli => "br",
+        }
+    }
+
+    /// Returns the entity tag of a representation compressed with this coding
+    ///
+    /// The name of the coding is appended to the opaque tag, so that
+    /// the compressed and the identity representations have different
+    /// tags. `"abc"` becomes `"abc-gzip"`, and `W/"abc"` becomes `W/"abc-gzip"`
+    #[must_use]
+    pub fn etag(self, etag: &str) -> String {
+        match etag.trim_end().strip_suffix('"') {
+            Some(tag) => format!("{tag}-{self}\""),
+            None => etag.to_owned(),
+        }
+    }
+}
+
+impl FromStr for ContentCoding {
+    type Err = HttpError;
+
+    /// Parses a coding name. The comparison is case-insensitive
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("gzip") || s.eq_ignore_ascii_case("x-gzip") {
+            Ok(ContentCoding::Gzip)
+        } else if s.eq_ignore_ascii_case("deflate") {
+            Ok(ContentCoding::Deflate)
+        } else if s.eq_ignore_ascii_case("br") {
+            Ok(ContentCoding::Brotli)
+        } else {
+            err!(
+                kind = UnsupportedMediaType,
+                "Unsupported content coding: \"{s}\""
+            )
+        }
+    }
+}
+
+impl fmt::Display for ContentCoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+enum CompressReaderInner<R: Read> {
+    Gzip(GzEncoder<R>),
+    Deflate(ZlibEncoder<R>),
+    Brotli(Box<brotli::CompressorReader<R>>),
+}
+
+/// Reader that compresses the data of another reader
+pub struct CompressReader<R: Read>(CompressReaderInner<R>);
+
+impl<R: Read> CompressReader<R> {
+    pub fn new(reader: R, coding: ContentCoding) -> Self {
+        Self(match coding {
+            ContentCoding::Gzip => {
+                CompressReaderInner::Gzip(GzEncoder::new(reader, Compression::default()))
+            }
+            ContentCoding::Deflate => {
+                CompressReaderInner::Deflate(ZlibEncoder::new(reader, Compression::default()))
+            }
+            ContentCoding::Brotli => CompressReaderInner::Brotli(Box::new(
+                brotli::CompressorReader::new(reader, BUFFER_SIZE, BROTLI_QUALITY, BROTLI_LGWIN),
+            )),
+        })
+    }
+}
+
+impl<R: Read> Read for CompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            CompressReaderInner::Gzip(r) => r.read(buf),
+            CompressReaderInner::Deflate(r) => r.read(buf),
+            CompressReaderInner::Brotli(r) => r.read(buf),
+        }
+    }
+}
+
+enum CompressWriterInner<W: Write> {
+    Gzip(write::GzEncoder<W>),
+    Deflate(write::ZlibEncoder<W>),
+    Brotli(Box<brotli::CompressorWriter<W>>),
+}
+
+/// Writer that compresses the data before writing it to another writer
+///
+/// # NOTE
+/// The compressed stream is only complete after calling [finish](Self::finish)
+pub struct CompressWriter<W: Write>(CompressWriterInner<W>);
+
+impl<W: Write> CompressWriter<W> {
+    pub fn new(writer: W, coding: ContentCoding) -> Self {
+        Self(match coding {
+            ContentCoding::Gzip => {
+                CompressWriterInner::Gzip(write::GzEncoder::new(writer, Compression::default()))
+            }
+            ContentCoding::Deflate => CompressWriterInner::Deflate(write::ZlibEncoder::new(
+                writer,
+                Compression::default(),
+            )),
+            ContentCoding::Brotli => CompressWriterInner::Brotli(Box::new(
+                brotli::CompressorWriter::new(writer, BUFFER_SIZE, BROTLI_QUALITY, BROTLI_LGWIN),
+            )),
+        })
+    }
+
+    /// Writes the end of the compressed stream, and returns the inner writer
+    ///
+    /// # Errors
+    /// If the inner writer fails
+    pub fn finish(self) -> io::Result<W> {
+        match self.0 {
+            CompressWriterInner::Gzip(w) => w.finish(),
+            CompressWriterInner::Deflate(w) => w.finish(),
+            CompressWriterInner::Brotli(mut w) => {
+                w.flush()?;
+                Ok(w.into_inner())
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            CompressWriterInner::Gzip(w) => w.write(buf),
+            CompressWriterInner::Deflate(w) => w.write(buf),
+            CompressWriterInner::Brotli(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            CompressWriterInner::Gzip(w) => w.flush(),
+            CompressWriterInner::Deflate(w) => w.flush(),
+            CompressWriterInner::Brotli(w) => w.flush(),
+        }
+    }
+}
+
+enum DecompressReaderInner<R: Read> {
+    Gzip(GzDecoder<R>),
+    Deflate(ZlibDecoder<R>),
+    Brotli(Box<brotli::Decompressor<R>>),
+}
+
+/// Reader that decompresses the data of another reader
+pub struct DecompressReader<R: Read>(DecompressReaderInner<R>);
+
+impl<R: Read> DecompressReader<R> {
+    pub fn new(reader: R, coding: ContentCoding) -> Self {
+        Self(match coding {
+            ContentCoding::Gzip => DecompressReaderInner::Gzip(GzDecoder::new(reader)),
+            ContentCoding::Deflate => DecompressReaderInner::Deflate(ZlibDecoder::new(reader)),
+            ContentCoding::Brotli => DecompressReaderInner::Brotli(Box::new(
+                brotli::Decompressor::new(reader, BUFFER_SIZE),
+            )),
+        })
+    }
+
+    /// Returns the inner reader
+    pub fn into_inner(self) -> R {
+        match self.0 {
+            DecompressReaderInner::Gzip(r) => r.into_inner(),
+            DecompressReaderInner::Deflate(r) => r.into_inner(),
+            DecompressReaderInner::Brotli(r) => r.into_inner(),
+        }
+    }
+}
+
+impl<R: Read> Read for DecompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            DecompressReaderInner::Gzip(r) => r.read(buf),
+            DecompressReaderInner::Deflate(r) => r.read(buf),
+            DecompressReaderInner::Brotli(r) => r.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::{Read, Write};
+
+    use super::{CompressReader, CompressWriter, ContentCoding, DecompressReader};
+
+    const TEXT: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. \
+                          Lorem ipsum dolor sit amet, consectetur adipiscing elit.";
+
+    fn decompress(data: &[u8], coding: ContentCoding) -> Vec<u8> {
+        let mut out = Vec::new();
+        DecompressReader::new(data, coding)
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn reader_round_trip() {
+        for coding in ContentCoding::ALL {
+            let mut compressed = Vec::new();
+            CompressReader::new(TEXT, coding)
+                .read_to_end(&mut compressed)
+                .unwrap();
+            assert!(compressed.len() < TEXT.len(), "{coding}");
+            assert_eq!(decompress(&compressed, coding), TEXT, "{coding}");
+        }
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        for coding in ContentCoding::ALL {
+            let mut writer = CompressWriter::new(Vec::new(), coding);
+            for chunk in TEXT.chunks(7) {
+                writer.write_all(chunk).unwrap();
+            }
+            let compressed = writer.finish().unwrap();
+            assert_eq!(decompress(&compressed, coding), TEXT, "{coding}");
+        }
+    }
+
+    #[test]
+    fn parse() {
+        for coding in ContentCoding::ALL {
+            assert_eq!(coding.as_str().parse::<ContentCoding>().unwrap(), coding);
+        }
+        assert_eq!(
+            "X-GZIP".parse::<ContentCoding>().unwrap(),
+            ContentCoding::Gzip
+        );
+        assert_eq!("zstd".parse::<ContentCoding>().unwrap_err().status(), 415);
+    }
+}
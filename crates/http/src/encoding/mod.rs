@@ -1,4 +1,8 @@
+pub mod body;
+pub use body::{BodyKind, BodyReader};
 pub mod chunked;
-pub use chunked::Chunked;
+pub use chunked::{Chunked, ChunkedDecoder};
+pub mod compress;
+pub use compress::{CompressReader, CompressWriter, ContentCoding, DecompressReader};
 pub mod stream;
 pub use stream::StreamReader;
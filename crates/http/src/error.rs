@@ -1,6 +1,7 @@
 use core::fmt;
 use std::{
     borrow::Cow,
+    error::Error,
     fmt::{Debug, Display},
     io,
     num::ParseIntError,
@@ -9,14 +10,137 @@ use std::{
     string::FromUtf8Error,
 };
 
-/// Server Error
-pub struct HttpError(Cow<'static, str>);
+use crate::Status;
+
+/// Kind of an [`HttpError`]
+///
+/// Each kind maps to the HTTP status code that a server
+/// should respond with when it finds an error of that kind.
+/// See [`ErrorKind::status`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An IO operation failed
+    Io,
+    /// Some input couldn't be parsed
+    Parse,
+    /// An operation timed out
+    Timeout,
+    /// The body of a message is too big
+    PayloadTooLarge,
+    /// The target URI of a request is too long
+    UriTooLong,
+    /// The header section of a message is too big
+    HeaderFieldsTooLarge,
+    /// The requested resource doesn't exist
+    NotFound,
+    /// The requested resource can't be accessed
+    Forbidden,
+    /// Authentication is required
+    Unauthorized,
+    /// The request's method is not supported for the resource
+    MethodNotAllowed,
+    /// None of the available representations is acceptable to the client
+    NotAcceptable,
+    /// The media type of the request's body is not supported
+    UnsupportedMediaType,
+    /// The requested functionality is not implemented
+    NotImplemented,
+    /// Any other error
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns the HTTP status code that corresponds to this kind of error
+    #[must_use]
+    pub fn status(self) -> Status {
+        match self {
+            ErrorKind::Parse => Status::BAD_REQUEST,
+            ErrorKind::Unauthorized => Status::UNAUTHORIZED,
+            ErrorKind::Forbidden => Status::FORBIDDEN,
+            ErrorKind::NotFound => Status::NOT_FOUND,
+            ErrorKind::MethodNotAllowed => Status::METHOD_NOT_ALLOWED,
+            ErrorKind::NotAcceptable => Status::NOT_ACCEPTABLE,
+            ErrorKind::Timeout => Status::REQUEST_TIMEOUT,
+            ErrorKind::PayloadTooLarge => Status::CONTENT_TOO_LARGE,
+            ErrorKind::UriTooLong => Status::URI_TOO_LONG,
+            ErrorKind::HeaderFieldsTooLarge => Status::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            ErrorKind::UnsupportedMediaType => Status::UNSUPPORTED_MEDIA_TYPE,
+            ErrorKind::NotImplemented => Status::NOT_IMPLEMENTED,
+            ErrorKind::Io | ErrorKind::Other => Status::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<io::ErrorKind> for ErrorKind {
+    fn from(value: io::ErrorKind) -> Self {
+        match value {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::Forbidden,
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorKind::Timeout,
+            io::ErrorKind::InvalidData => ErrorKind::Parse,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+/// Http Error
+///
+/// It contains a [kind](ErrorKind), a message, and optionally,
+/// the HTTP status code that it represents, and the error that caused it.
+///
+/// # Example
+/// ```
+/// use std::error::Error;
+/// use http::{HttpError, Status, error::ErrorKind};
+///
+/// let err: HttpError = "abc".parse::<u16>().unwrap_err().into();
+/// assert_eq!(err.kind(), ErrorKind::Parse);
+/// assert_eq!(err.status(), 400);
+/// assert!(err.source().is_some());
+///
+/// let err = HttpError::with_kind(ErrorKind::Forbidden, "Private file")
+///             .with_status(Status::UNAVAILABLE_FOR_LEGAL_REASONS);
+/// assert_eq!(err.status(), 451);
+/// ```
+pub struct HttpError {
+    kind: ErrorKind,
+    status: Option<Status>,
+    msg: Cow<'static, str>,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
 
 impl HttpError {
-    /// Creates a [`HttpError`] from a &'static [str]
+    /// Creates a [`HttpError`] of [kind](ErrorKind) [`Other`](ErrorKind::Other)
+    /// from a message
     #[inline]
     pub fn new(msg: impl Into<Cow<'static, str>>) -> Self {
-        Self(msg.into())
+        Self::with_kind(ErrorKind::Other, msg)
+    }
+    /// Creates a [`HttpError`] of the given [kind](ErrorKind)
+    #[inline]
+    pub fn with_kind(kind: ErrorKind, msg: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            status: None,
+            msg: msg.into(),
+            source: None,
+        }
+    }
+    /// Sets the HTTP status code of the error, overriding
+    /// the default for it's [kind](ErrorKind::status)
+    #[inline]
+    #[must_use]
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = Some(status);
+        self
+    }
+    /// Sets the error that caused this one
+    #[inline]
+    #[must_use]
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
     }
     /// Turns the [`HttpError`] into a [Result]<T,[`HttpError`]>
     #[inline]
@@ -27,12 +151,30 @@ impl HttpError {
     #[inline]
     #[must_use]
     pub fn get_message(&self) -> &str {
-        &self.0
+        &self.msg
+    }
+    /// Gets the [kind](ErrorKind) of this error
+    #[inline]
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+    /// Gets the HTTP status code that corresponds to this error
+    ///
+    /// If no status was set with [`with_status`](Self::with_status),
+    /// it returns the default status for the error's [kind](ErrorKind::status)
+    #[inline]
+    #[must_use]
+    pub fn status(&self) -> Status {
+        self.status.unwrap_or_else(|| self.kind.status())
     }
 }
 
 #[macro_export]
 macro_rules! err {
+    (kind = $kind:ident, $($e:tt)*) => {
+        $crate::HttpError::with_kind($crate::error::ErrorKind::$kind, format!($($e)*)).err()
+    };
     ($($e:tt)*) => {
         $crate::HttpError::new(format!($($e)*)).err()
     };
@@ -44,47 +186,54 @@ macro_rules! err {
     };
 }
 
+macro_rules! from_source {
+    ($t:ty => $kind:expr) => {
+        impl From<$t> for HttpError {
+            #[inline]
+            fn from(value: $t) -> Self {
+                Self::with_kind($kind, value.to_string()).with_source(value)
+            }
+        }
+    };
+}
+
 impl From<io::Error> for HttpError {
     #[inline]
     fn from(value: io::Error) -> Self {
-        Self::new(value.to_string())
-    }
-}
-impl From<FromUtf8Error> for HttpError {
-    #[inline]
-    fn from(value: FromUtf8Error) -> Self {
-        Self::new(value.to_string())
-    }
-}
-impl From<Utf8Error> for HttpError {
-    #[inline]
-    fn from(value: Utf8Error) -> Self {
-        Self::new(value.to_string())
-    }
-}
-impl From<std::path::StripPrefixError> for HttpError {
-    #[inline]
-    fn from(value: StripPrefixError) -> Self {
-        Self::new(value.to_string())
-    }
-}
-impl From<ParseIntError> for HttpError {
-    #[inline]
-    fn from(value: ParseIntError) -> Self {
-        Self::new(value.to_string())
+        /* Readers like ChunkedDecoder wrap the errors of the parser,
+         * so the kind of those must be recovered */
+        match value.downcast::<HttpError>() {
+            Ok(err) => err,
+            Err(value) => {
+                Self::with_kind(value.kind().into(), value.to_string()).with_source(value)
+            }
+        }
     }
 }
+
+from_source!(FromUtf8Error => ErrorKind::Parse);
+from_source!(Utf8Error => ErrorKind::Parse);
+from_source!(ParseIntError => ErrorKind::Parse);
+from_source!(StripPrefixError => ErrorKind::Other);
+from_source!(fmt::Error => ErrorKind::Other);
+
 impl From<Cow<'static, str>> for HttpError {
     #[inline]
     fn from(value: Cow<'static, str>) -> Self {
-        Self(value)
+        Self::new(value)
     }
 }
 
 impl Debug for HttpError {
-    #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.get_message())
+        let mut deb = f.debug_struct("HttpError");
+        deb.field("kind", &self.kind)
+            .field("status", &self.status())
+            .field("message", &self.msg);
+        if let Some(source) = &self.source {
+            deb.field("source", source);
+        }
+        deb.finish()
     }
 }
 
@@ -97,20 +246,51 @@ impl Display for HttpError {
 
 impl From<&'static str> for HttpError {
     fn from(value: &'static str) -> Self {
-        Self(value.into())
+        Self::new(value)
     }
 }
 
 impl From<String> for HttpError {
     fn from(value: String) -> Self {
-        Self(value.into())
+        Self::new(value)
     }
 }
 
-impl From<fmt::Error> for HttpError {
-    fn from(value: fmt::Error) -> Self {
-        Self(value.to_string().into())
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn Error + 'static))
     }
 }
 
-impl std::error::Error for HttpError {}
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{ErrorKind, HttpError};
+
+    #[test]
+    fn io_kinds() {
+        let kinds = [
+            (io::ErrorKind::NotFound, ErrorKind::NotFound, 404),
+            (io::ErrorKind::PermissionDenied, ErrorKind::Forbidden, 403),
+            (io::ErrorKind::TimedOut, ErrorKind::Timeout, 408),
+            (io::ErrorKind::BrokenPipe, ErrorKind::Io, 500),
+        ];
+        for (io_kind, kind, status) in kinds {
+            let err = HttpError::from(io::Error::from(io_kind));
+            assert_eq!(err.kind(), kind);
+            assert_eq!(err.status(), status);
+        }
+    }
+
+    #[test]
+    fn macro_kind() {
+        let err: Result<(), HttpError> = crate::err!(kind = PayloadTooLarge, "{} bytes", 12);
+        let err = err.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PayloadTooLarge);
+        assert_eq!(err.status(), 413);
+        assert_eq!(err.get_message(), "12 bytes");
+    }
+}
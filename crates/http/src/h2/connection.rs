@@ -0,0 +1,998 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io::{self, BufReader, Read, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use super::{
+    ErrorCode, MAX_WINDOW_SIZE, PREFACE, Settings,
+    frame::{Frame, FrameHeader, FrameType, flags, strip_padding},
+    hpack::{self, Decoder, Field, HpackError},
+    is_upgrade,
+};
+use crate::{
+    HeaderMap, HttpMethod, HttpRequest, HttpStream, HttpVersion, ParserLimits, Result, Status,
+    encoding::{BodyKind, body::content_length},
+    err,
+    parser::{Event, Head, Parser, RequestHead, parse_target},
+    request::{expects_continue, from_head},
+};
+
+/// Max number of streams that can be open at the same time.
+///
+/// Since requests are handled one at a time, this also limits the
+/// number of request bodies buffered while waiting for their turn.
+const MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// Headers that only make sense on an HTTP/1 connection
+const CONNECTION_HEADERS: [&str; 6] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "http2-settings",
+];
+
+fn is_connection_header(name: &str) -> bool {
+    CONNECTION_HEADERS
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(name))
+}
+
+enum FrameError {
+    /// Closes the connection with a GOAWAY frame
+    Connection(ErrorCode, Cow<'static, str>),
+    /// Closes a single stream with a RST_STREAM frame
+    Stream(u32, ErrorCode),
+    Io(io::Error),
+}
+
+type FrameResult<T> = std::result::Result<T, FrameError>;
+
+impl From<io::Error> for FrameError {
+    fn from(err: io::Error) -> Self {
+        FrameError::Io(err)
+    }
+}
+
+fn connection_error<T>(code: ErrorCode, msg: impl Into<Cow<'static, str>>) -> FrameResult<T> {
+    Err(FrameError::Connection(code, msg.into()))
+}
+
+/// State of a stream
+struct Stream {
+    /// Data received, but not read by the handler yet
+    recv: VecDeque<u8>,
+    /// The client won't send more data
+    recv_closed: bool,
+    /// Bytes that the client can still send
+    recv_window: i64,
+    /// Bytes read by the handler, but not yet returned
+    /// to the client with a WINDOW_UPDATE
+    recv_unacked: u32,
+    send_window: i64,
+    /// The stream was reset, by the client or by us
+    reset: bool,
+    response: Response,
+}
+
+/// Translates the HTTP/1.1 response written by the
+/// handler into HEADERS and DATA frames
+struct Response {
+    parser: Parser,
+    is_head: bool,
+    /// Header block of the final response, held until
+    /// we know if the response has a body
+    head: Option<Vec<u8>>,
+    is_final: bool,
+    /// END_STREAM has been sent
+    done: bool,
+}
+
+struct Inner {
+    io: BufReader<Box<dyn HttpStream>>,
+    decoder: Decoder,
+    local: Settings,
+    remote: Settings,
+    limits: ParserLimits,
+    streams: HashMap<u32, Stream>,
+    /// Requests waiting to be handled
+    pending: VecDeque<(u32, RequestHead, BodyKind, bool)>,
+    /// Highest stream id opened by the client
+    last_stream_id: u32,
+    recv_window: i64,
+    recv_unacked: u32,
+    send_window: i64,
+    /// A HEADERS frame waiting for it's CONTINUATION frames:
+    /// the stream id, whether it ends the stream, and the header block.
+    continuation: Option<(u32, bool, Vec<u8>)>,
+    /// The first frame must be a SETTINGS
+    expect_settings: bool,
+    /// The client sent a GOAWAY
+    goaway: bool,
+    /// The connection can't be used anymore
+    closed: bool,
+}
+
+/// An HTTP/2 connection
+///
+/// The streams are handled one at a time, in the order they're opened.
+/// While a handler runs, the frames of the other streams are still read,
+/// and their request bodies buffered, up to their flow control window.
+///
+/// See the [module docs](super) for an example.
+pub struct Connection {
+    inner: Rc<RefCell<Inner>>,
+    idle_timeout: Option<Duration>,
+}
+
+impl Connection {
+    /// Creates a connection over the given stream
+    ///
+    /// The client must start by sending the [connection preface](PREFACE).
+    /// That's the case after negotiating `h2` with ALPN, or when
+    /// [`starts_with_preface`](super::starts_with_preface) returns true.
+    #[must_use]
+    pub fn new(stream: BufReader<Box<dyn HttpStream>>, limits: ParserLimits) -> Self {
+        let local = Settings {
+            max_concurrent_streams: Some(MAX_CONCURRENT_STREAMS),
+            max_header_list_size: u32::try_from(limits.max_header_bytes).ok(),
+            ..Settings::default()
+        };
+        let remote = Settings::default();
+        let inner = Inner {
+            io: stream,
+            decoder: Decoder::new(local.header_table_size as usize),
+            local,
+            remote,
+            limits,
+            streams: HashMap::new(),
+            pending: VecDeque::new(),
+            last_stream_id: 0,
+            recv_window: i64::from(local.initial_window_size),
+            recv_unacked: 0,
+            send_window: i64::from(remote.initial_window_size),
+            continuation: None,
+            expect_settings: false,
+            goaway: false,
+            closed: false,
+        };
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+            idle_timeout: None,
+        }
+    }
+
+    /// Upgrades the connection of an HTTP/1.1 request to HTTP/2
+    ///
+    /// It sends the *101 Switching Protocols* response, and the
+    /// request becomes the first stream of the new connection.
+    ///
+    /// # Errors
+    /// - If the request is not an [upgrade request](super::is_upgrade)
+    /// - If the *HTTP2-Settings* header is invalid
+    /// - If the *101* response can't be sent
+    pub fn upgrade(mut req: HttpRequest) -> Result<Self> {
+        if !is_upgrade(&req) {
+            return err!(kind = Parse, "Not an h2c upgrade request");
+        }
+        /* The settings are encoded with the URL-safe alphabet of base64 */
+        let settings: String = req
+            .header("HTTP2-Settings")
+            .unwrap_or("")
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '-' => '+',
+                '_' => '/',
+                c => c,
+            })
+            .collect();
+        let settings = base64::decode(&settings)
+            .map_err(|err| format!("Invalid HTTP2-Settings header: {err}"))?;
+        let mut remote = Settings::default();
+        if let Err(code) = remote.apply(&settings) {
+            return err!(kind = Parse, "Invalid HTTP2-Settings header: {code}");
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Connection", "Upgrade");
+        headers.insert("Upgrade", "h2c");
+        req.send_interim(Status::SWITCHING_PROTOCOLS, &headers)?;
+
+        let (mut head, stream, limits) = req.into_parts();
+        head.version = HttpVersion::Http2;
+        head.headers = head
+            .headers
+            .into_iter()
+            .filter(|(k, _)| !is_connection_header(k))
+            .collect();
+
+        let conn = Self::new(stream, limits);
+        {
+            let mut inner = conn.inner.borrow_mut();
+            inner.send_window = i64::from(remote.initial_window_size);
+            inner.remote = remote;
+            inner.last_stream_id = 1;
+            inner.open_stream(1, head, BodyKind::Empty, false);
+        }
+        Ok(conn)
+    }
+
+    /// Closes the connection when it's been idle for the given time
+    #[must_use]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Serves the requests of this connection, until the client closes it
+    ///
+    /// Each request is passed to `handler`. An error returned by the
+    /// handler only affects it's stream: if the response wasn't complete,
+    /// the stream is reset.
+    ///
+    /// # Errors
+    /// - If the client violates the protocol. In that case, a
+    ///   GOAWAY frame is sent before returning.
+    /// - If an IO error happens
+    pub fn serve<F>(self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(&mut HttpRequest) -> Result<()>,
+    {
+        self.inner.borrow_mut().start()?;
+        while let Some((id, head, kind, expect)) = self.next_request()? {
+            let stream: Box<dyn HttpStream> = Box::new(H2Stream {
+                inner: Rc::clone(&self.inner),
+                id,
+            });
+            let limits = self.inner.borrow().limits;
+            let mut req = from_head(head, BufReader::new(stream), kind, limits, expect);
+            /* Errors are already reported by the response, or by resetting the stream */
+            let _ = handler(&mut req);
+            drop(req);
+            self.inner.borrow_mut().finish_stream(id)?;
+        }
+        self.inner.borrow_mut().close()?;
+        Ok(())
+    }
+
+    fn next_request(&self) -> Result<Option<(u32, RequestHead, BodyKind, bool)>> {
+        let mut inner = self.inner.borrow_mut();
+        loop {
+            while let Some((id, head, kind, expect)) = inner.pending.pop_front() {
+                if inner.streams.get(&id).is_some_and(|s| !s.reset) {
+                    return Ok(Some((id, head, kind, expect)));
+                }
+                inner.streams.remove(&id);
+            }
+            if inner.closed || (inner.goaway && inner.streams.is_empty()) {
+                return Ok(None);
+            }
+            if let Some(timeout) = self.idle_timeout {
+                inner.io.get_mut().set_non_blocking(timeout)?;
+            }
+            let result = inner.step();
+            inner.io.get_mut().set_blocking()?;
+            match result {
+                Ok(true) => {}
+                Ok(false) => return Ok(None),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    inner.close()?;
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Inner {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let io = self.io.get_mut();
+        frame.write_to(io)?;
+        io.flush()
+    }
+
+    /// Sends our settings, and reads the client's preface
+    fn start(&mut self) -> Result<()> {
+        let settings = Frame::new(FrameType::Settings, 0, 0, self.local.encode());
+        self.write_frame(&settings)?;
+        let mut preface = [0; PREFACE.len()];
+        self.io.read_exact(&mut preface)?;
+        if preface != PREFACE {
+            self.go_away(ErrorCode::PROTOCOL_ERROR, "Invalid connection preface")?;
+            return err!(kind = Parse, "Invalid HTTP/2 connection preface");
+        }
+        self.expect_settings = true;
+        Ok(())
+    }
+
+    /// Sends a GOAWAY frame, if it wasn't sent already
+    fn close(&mut self) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.go_away(ErrorCode::NO_ERROR, "")
+    }
+
+    fn go_away(&mut self, code: ErrorCode, msg: &str) -> io::Result<()> {
+        self.closed = true;
+        let mut payload = Vec::with_capacity(8 + msg.len());
+        payload.extend_from_slice(&self.last_stream_id.to_be_bytes());
+        payload.extend_from_slice(&code.0.to_be_bytes());
+        payload.extend_from_slice(msg.as_bytes());
+        self.write_frame(&Frame::new(FrameType::GoAway, 0, 0, payload))
+    }
+
+    fn reset(&mut self, id: u32, code: ErrorCode) -> io::Result<()> {
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.reset = true;
+        }
+        let frame = Frame::new(FrameType::RstStream, 0, id, code.0.to_be_bytes());
+        self.write_frame(&frame)
+    }
+
+    /// Reads and processes a frame
+    ///
+    /// Returns false if the client closed the connection.
+    fn step(&mut self) -> io::Result<bool> {
+        if self.closed {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+        match self.read_frame() {
+            Ok(open) => {
+                self.closed |= !open;
+                Ok(open)
+            }
+            Err(FrameError::Stream(id, code)) => {
+                self.reset(id, code)?;
+                Ok(true)
+            }
+            Err(FrameError::Connection(code, msg)) => {
+                self.go_away(code, &msg)?;
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("HTTP/2 connection error ({code}): {msg}"),
+                ))
+            }
+            Err(FrameError::Io(err)) => {
+                if !matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) {
+                    self.closed = true;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> FrameResult<bool> {
+        let Some(header) = FrameHeader::read(&mut self.io)? else {
+            return Ok(false);
+        };
+        if header.len > self.local.max_frame_size {
+            return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Frame too large");
+        }
+        let frame = Frame::read_payload(header, &mut self.io)?;
+
+        if self.expect_settings {
+            if frame.kind != FrameType::Settings || frame.has_flag(flags::ACK) {
+                return connection_error(ErrorCode::PROTOCOL_ERROR, "Expected SETTINGS");
+            }
+            self.expect_settings = false;
+        }
+        if let Some((id, ..)) = self.continuation
+            && (frame.kind != FrameType::Continuation || frame.stream_id != id)
+        {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "Expected CONTINUATION");
+        }
+
+        match frame.kind {
+            FrameType::Data => self.on_data(&frame)?,
+            FrameType::Headers => self.on_headers(frame)?,
+            FrameType::Continuation => self.on_continuation(frame)?,
+            FrameType::Priority => {
+                if frame.stream_id == 0 {
+                    return connection_error(ErrorCode::PROTOCOL_ERROR, "PRIORITY on stream 0");
+                }
+                if frame.payload.len() != 5 {
+                    return Err(FrameError::Stream(
+                        frame.stream_id,
+                        ErrorCode::FRAME_SIZE_ERROR,
+                    ));
+                }
+            }
+            FrameType::RstStream => self.on_rst_stream(&frame)?,
+            FrameType::Settings => self.on_settings(&frame)?,
+            FrameType::PushPromise => {
+                return connection_error(ErrorCode::PROTOCOL_ERROR, "Clients can't push");
+            }
+            FrameType::Ping => {
+                if frame.stream_id != 0 {
+                    return connection_error(ErrorCode::PROTOCOL_ERROR, "PING on a stream");
+                }
+                if frame.payload.len() != 8 {
+                    return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Invalid PING");
+                }
+                if !frame.has_flag(flags::ACK) {
+                    let pong = Frame::new(FrameType::Ping, flags::ACK, 0, frame.payload);
+                    self.write_frame(&pong)?;
+                }
+            }
+            FrameType::GoAway => {
+                if frame.stream_id != 0 {
+                    return connection_error(ErrorCode::PROTOCOL_ERROR, "GOAWAY on a stream");
+                }
+                self.goaway = true;
+            }
+            FrameType::WindowUpdate => self.on_window_update(&frame)?,
+            FrameType::Unknown(_) => {}
+        }
+        Ok(true)
+    }
+
+    fn on_data(&mut self, frame: &Frame) -> FrameResult<()> {
+        let id = frame.stream_id;
+        if id == 0 {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "DATA on stream 0");
+        }
+        let len = frame.payload.len();
+        if len as i64 > self.recv_window {
+            return connection_error(ErrorCode::FLOW_CONTROL_ERROR, "Connection window exceeded");
+        }
+        /* The connection window is returned right away. The memory
+         * used by the buffered data is bounded by the stream windows */
+        self.recv_window -= len as i64;
+        self.recv_unacked += len as u32;
+        if self.recv_unacked >= self.local.initial_window_size / 2 {
+            let increment = self.recv_unacked;
+            self.recv_unacked = 0;
+            self.recv_window += i64::from(increment);
+            self.window_update(0, increment)?;
+        }
+
+        let Some(data) = strip_padding(frame.flags, &frame.payload) else {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "Invalid padding");
+        };
+        let Some(stream) = self.streams.get_mut(&id) else {
+            if id > self.last_stream_id {
+                return connection_error(ErrorCode::PROTOCOL_ERROR, "DATA on an idle stream");
+            }
+            /* A stream that we already closed */
+            return Ok(());
+        };
+        if stream.recv_closed {
+            return Err(FrameError::Stream(id, ErrorCode::STREAM_CLOSED));
+        }
+        if len as i64 > stream.recv_window {
+            return Err(FrameError::Stream(id, ErrorCode::FLOW_CONTROL_ERROR));
+        }
+        stream.recv_window -= len as i64;
+        stream.recv.extend(data);
+        /* The padding is not read by the handler, so it's returned now */
+        stream.recv_unacked += (len - data.len()) as u32;
+        stream.recv_closed = frame.has_flag(flags::END_STREAM);
+        self.ack_stream(id)?;
+        Ok(())
+    }
+
+    /// Sends a WINDOW_UPDATE for the data read from a stream,
+    /// once there's enough of it
+    fn ack_stream(&mut self, id: u32) -> io::Result<()> {
+        let threshold = self.local.initial_window_size / 2;
+        let Some(stream) = self.streams.get_mut(&id) else {
+            return Ok(());
+        };
+        if stream.recv_closed || stream.reset || stream.recv_unacked < threshold {
+            return Ok(());
+        }
+        let increment = stream.recv_unacked;
+        stream.recv_unacked = 0;
+        stream.recv_window += i64::from(increment);
+        self.window_update(id, increment)
+    }
+
+    fn window_update(&mut self, id: u32, increment: u32) -> io::Result<()> {
+        let frame = Frame::new(FrameType::WindowUpdate, 0, id, increment.to_be_bytes());
+        self.write_frame(&frame)
+    }
+
+    fn on_headers(&mut self, frame: Frame) -> FrameResult<()> {
+        let id = frame.stream_id;
+        if id == 0 {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "HEADERS on stream 0");
+        }
+        let Some(mut block) = strip_padding(frame.flags, &frame.payload) else {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "Invalid padding");
+        };
+        if frame.has_flag(flags::PRIORITY) {
+            let Some(rest) = block.get(5..) else {
+                return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Invalid HEADERS");
+            };
+            block = rest;
+        }
+        let end_stream = frame.has_flag(flags::END_STREAM);
+        if frame.has_flag(flags::END_HEADERS) {
+            self.on_header_block(id, end_stream, block)
+        } else {
+            self.continuation = Some((id, end_stream, block.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn on_continuation(&mut self, frame: Frame) -> FrameResult<()> {
+        let Some((id, end_stream, mut block)) = self.continuation.take() else {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "Unexpected CONTINUATION");
+        };
+        block.extend_from_slice(&frame.payload);
+        /* The block is compressed, so it should be smaller than the
+         * decoded header list. This stops endless CONTINUATION frames */
+        if block.len() > self.limits.max_header_bytes.saturating_mul(2) {
+            return connection_error(ErrorCode::ENHANCE_YOUR_CALM, "Header block too large");
+        }
+        if frame.has_flag(flags::END_HEADERS) {
+            self.on_header_block(id, end_stream, &block)
+        } else {
+            self.continuation = Some((id, end_stream, block));
+            Ok(())
+        }
+    }
+
+    fn on_header_block(&mut self, id: u32, end_stream: bool, block: &[u8]) -> FrameResult<()> {
+        /* The block must be decoded even if the stream is refused,
+         * to keep the decoder in sync with the client's encoder */
+        let limits = self.limits;
+        let fields = match self
+            .decoder
+            .decode(block, limits.max_header_bytes, limits.max_headers)
+        {
+            Ok(fields) => Some(fields),
+            Err(HpackError::ListTooLarge) => None,
+            Err(err) => return connection_error(ErrorCode::COMPRESSION_ERROR, err.to_string()),
+        };
+        if let Some(stream) = self.streams.get_mut(&id) {
+            /* Trailers. They are discarded */
+            if stream.recv_closed || !end_stream {
+                return Err(FrameError::Stream(id, ErrorCode::PROTOCOL_ERROR));
+            }
+            stream.recv_closed = true;
+            return Ok(());
+        }
+        if id.is_multiple_of(2) {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "Invalid stream id");
+        }
+        if id <= self.last_stream_id {
+            /* A stream that we already closed */
+            return Ok(());
+        }
+        self.last_stream_id = id;
+        if self.streams.len() >= MAX_CONCURRENT_STREAMS as usize {
+            return Err(FrameError::Stream(id, ErrorCode::REFUSED_STREAM));
+        }
+        let request = match fields {
+            Some(fields) => request_head(fields, end_stream).and_then(|(head, kind)| {
+                let expect = expects_continue(&head, kind)?;
+                Ok((head, kind, expect))
+            }),
+            None => err!(kind = HeaderFieldsTooLarge, "Header list too large"),
+        };
+        match request {
+            Ok((head, kind, expect)) => self.open_stream(id, head, kind, expect),
+            Err(err) => self.reject(id, err.status(), end_stream)?,
+        }
+        Ok(())
+    }
+
+    fn open_stream(&mut self, id: u32, head: RequestHead, kind: BodyKind, expect: bool) {
+        let stream = Stream {
+            recv: VecDeque::new(),
+            recv_closed: kind == BodyKind::Empty,
+            recv_window: i64::from(self.local.initial_window_size),
+            recv_unacked: 0,
+            send_window: i64::from(self.remote.initial_window_size),
+            reset: false,
+            response: Response {
+                parser: Parser::response(),
+                is_head: head.method == HttpMethod::HEAD,
+                head: None,
+                is_final: false,
+                done: false,
+            },
+        };
+        self.streams.insert(id, stream);
+        self.pending.push_back((id, head, kind, expect));
+    }
+
+    /// Responds to a request that can't be passed to a handler
+    fn reject(&mut self, id: u32, status: Status, end_stream: bool) -> io::Result<()> {
+        let status = status.to_string();
+        let mut block = Vec::new();
+        hpack::encode([(":status", &*status), ("content-length", "0")], &mut block);
+        self.write_headers(id, &block, true)?;
+        if !end_stream {
+            self.reset(id, ErrorCode::NO_ERROR)?;
+        }
+        Ok(())
+    }
+
+    fn on_rst_stream(&mut self, frame: &Frame) -> FrameResult<()> {
+        let id = frame.stream_id;
+        if id == 0 || id > self.last_stream_id {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "RST_STREAM on an idle stream");
+        }
+        if frame.payload.len() != 4 {
+            return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Invalid RST_STREAM");
+        }
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.reset = true;
+        }
+        Ok(())
+    }
+
+    fn on_settings(&mut self, frame: &Frame) -> FrameResult<()> {
+        if frame.stream_id != 0 {
+            return connection_error(ErrorCode::PROTOCOL_ERROR, "SETTINGS on a stream");
+        }
+        if frame.has_flag(flags::ACK) {
+            if !frame.payload.is_empty() {
+                return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Invalid SETTINGS ACK");
+            }
+            return Ok(());
+        }
+        let old_window = self.remote.initial_window_size;
+        if let Err(code) = self.remote.apply(&frame.payload) {
+            return connection_error(code, "Invalid SETTINGS");
+        }
+        /* A change of the initial window affects all the open streams */
+        let delta = i64::from(self.remote.initial_window_size) - i64::from(old_window);
+        for stream in self.streams.values_mut() {
+            stream.send_window += delta;
+            if stream.send_window > i64::from(MAX_WINDOW_SIZE) {
+                return connection_error(ErrorCode::FLOW_CONTROL_ERROR, "Window too large");
+            }
+        }
+        self.write_frame(&Frame::new(FrameType::Settings, flags::ACK, 0, []))?;
+        Ok(())
+    }
+
+    fn on_window_update(&mut self, frame: &Frame) -> FrameResult<()> {
+        let id = frame.stream_id;
+        let Ok(payload) = <[u8; 4]>::try_from(&frame.payload[..]) else {
+            return connection_error(ErrorCode::FRAME_SIZE_ERROR, "Invalid WINDOW_UPDATE");
+        };
+        let increment = i64::from(u32::from_be_bytes(payload) & 0x7fff_ffff);
+        if id == 0 {
+            if increment == 0 {
+                return connection_error(ErrorCode::PROTOCOL_ERROR, "Zero WINDOW_UPDATE");
+            }
+            self.send_window += increment;
+            if self.send_window > i64::from(MAX_WINDOW_SIZE) {
+                return connection_error(ErrorCode::FLOW_CONTROL_ERROR, "Window too large");
+            }
+            return Ok(());
+        }
+        let Some(stream) = self.streams.get_mut(&id) else {
+            if id > self.last_stream_id {
+                return connection_error(ErrorCode::PROTOCOL_ERROR, "WINDOW_UPDATE on idle stream");
+            }
+            return Ok(());
+        };
+        if increment == 0 {
+            return Err(FrameError::Stream(id, ErrorCode::PROTOCOL_ERROR));
+        }
+        stream.send_window += increment;
+        if stream.send_window > i64::from(MAX_WINDOW_SIZE) {
+            return Err(FrameError::Stream(id, ErrorCode::FLOW_CONTROL_ERROR));
+        }
+        Ok(())
+    }
+
+    fn stream(&mut self, id: u32) -> io::Result<&mut Stream> {
+        match self.streams.get_mut(&id) {
+            Some(stream) if stream.reset => Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "The stream was reset",
+            )),
+            Some(stream) => Ok(stream),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Reads the request body of a stream
+    fn read_body(&mut self, id: u32, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let stream = self.stream(id)?;
+            if !stream.recv.is_empty() {
+                let n = stream.recv.read(buf)?;
+                stream.recv_unacked += n as u32;
+                self.ack_stream(id)?;
+                return Ok(n);
+            }
+            if stream.recv_closed {
+                return Ok(0);
+            }
+            if !self.step()? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Parses the response written by the handler, and sends it as frames
+    fn write_response(&mut self, id: u32, mut buf: &[u8]) -> io::Result<()> {
+        loop {
+            let response = &mut self.stream(id)?.response;
+            if response.done {
+                if buf.is_empty() {
+                    return Ok(());
+                }
+                return Err(io::Error::other("The response was already sent"));
+            }
+            let (n, event) = response.parser.parse(buf).map_err(io::Error::other)?;
+            buf = &buf[n..];
+            match event {
+                Some(event) => self.on_response_event(id, event)?,
+                None if buf.is_empty() => break,
+                None => {}
+            }
+        }
+        /* The head of a response with a body can be sent now */
+        let response = &mut self.stream(id)?.response;
+        if let Some(block) = response.head.take() {
+            self.write_headers(id, &block, false)?;
+        }
+        Ok(())
+    }
+
+    fn on_response_event(&mut self, id: u32, event: Event<'_>) -> io::Result<()> {
+        let response = &mut self.stream(id)?.response;
+        match event {
+            Event::Head(Head::Response(head)) => {
+                if head.status == Status::SWITCHING_PROTOCOLS {
+                    return Err(io::Error::other("Can't switch protocols over HTTP/2"));
+                }
+                let block = header_block(Some(head.status), &head.headers);
+                if head.status.is_informational() {
+                    return self.write_headers(id, &block, false);
+                }
+                if response.is_head {
+                    response.parser.skip_body();
+                }
+                response.is_final = true;
+                response.head = Some(block);
+            }
+            Event::Head(Head::Request(_)) => unreachable!("Response parser"),
+            Event::Data(data) => {
+                if let Some(block) = response.head.take() {
+                    self.write_headers(id, &block, false)?;
+                }
+                self.write_data(id, data, false)?;
+            }
+            Event::End(trailers) => {
+                if !response.is_final {
+                    /* End of an informational response */
+                    return Ok(());
+                }
+                response.done = true;
+                if let Some(block) = response.head.take() {
+                    self.write_headers(id, &block, true)?;
+                } else if trailers.is_empty() {
+                    self.write_data(id, &[], true)?;
+                } else {
+                    let block = header_block(None, &trailers);
+                    self.write_headers(id, &block, true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a header block, split in a HEADERS frame
+    /// and as many CONTINUATION frames as needed
+    fn write_headers(&mut self, id: u32, block: &[u8], end_stream: bool) -> io::Result<()> {
+        let max = self.remote.max_frame_size as usize;
+        let mut chunks = block.chunks(max).peekable();
+        let mut kind = FrameType::Headers;
+        let mut flags = if end_stream { flags::END_STREAM } else { 0 };
+        loop {
+            let chunk = chunks.next().unwrap_or_default();
+            if chunks.peek().is_none() {
+                flags |= flags::END_HEADERS;
+            }
+            self.write_frame(&Frame::new(kind, flags, id, chunk))?;
+            if flags & flags::END_HEADERS != 0 {
+                return Ok(());
+            }
+            kind = FrameType::Continuation;
+            flags = 0;
+        }
+    }
+
+    /// Sends DATA frames, waiting for the flow control windows when needed
+    fn write_data(&mut self, id: u32, mut data: &[u8], end_stream: bool) -> io::Result<()> {
+        loop {
+            let max_frame = i64::from(self.remote.max_frame_size);
+            let conn_window = self.send_window;
+            let stream = self.stream(id)?;
+            let window = conn_window.min(stream.send_window).min(max_frame);
+            if window <= 0 && !data.is_empty() {
+                if !self.step()? {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                continue;
+            }
+            let n = data.len().min(usize::try_from(window).unwrap_or(0));
+            let (chunk, rest) = data.split_at(n);
+            stream.send_window -= n as i64;
+            self.send_window -= n as i64;
+            data = rest;
+            let end = end_stream && data.is_empty();
+            let flags = if end { flags::END_STREAM } else { 0 };
+            self.write_frame(&Frame::new(FrameType::Data, flags, id, chunk))?;
+            if data.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Completes the response of a stream after it's handler returns
+    fn finish_stream(&mut self, id: u32) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let finished = self.complete_response(id);
+        let Some(stream) = self.streams.get(&id) else {
+            return Ok(());
+        };
+        if !stream.reset {
+            if !matches!(finished, Ok(true)) {
+                self.reset(id, ErrorCode::INTERNAL_ERROR)?;
+            } else if !stream.recv_closed {
+                /* The response doesn't need the rest of the body */
+                self.reset(id, ErrorCode::NO_ERROR)?;
+            }
+        }
+        self.streams.remove(&id);
+        Ok(())
+    }
+
+    /// Ends a response whose body ends with the connection, in HTTP/1.1 terms
+    ///
+    /// Returns true if the whole response was sent
+    fn complete_response(&mut self, id: u32) -> io::Result<bool> {
+        loop {
+            let response = &mut self.stream(id)?.response;
+            if response.done {
+                return Ok(true);
+            }
+            match response.parser.finish() {
+                Ok(Some(event)) => self.on_response_event(id, event)?,
+                Ok(None) | Err(_) => return Ok(false),
+            }
+        }
+    }
+}
+
+/// Encodes the fields of a response's head, or trailers if there's no status
+fn header_block(status: Option<Status>, headers: &HeaderMap) -> Vec<u8> {
+    let status = status.map(|s| s.to_string());
+    let fields: Vec<(String, &str)> = headers
+        .iter()
+        .filter(|(k, _)| !is_connection_header(k))
+        .map(|(k, v)| (k.to_ascii_lowercase(), v))
+        .collect();
+    let mut block = Vec::new();
+    let status = status.as_deref().map(|s| (":status", s));
+    let fields = fields.iter().map(|(k, v)| (k.as_str(), *v));
+    hpack::encode(status.into_iter().chain(fields), &mut block);
+    block
+}
+
+/// Builds the head of a request from the decoded header fields
+///
+/// The size of the list is already checked by the [`Decoder`]
+fn request_head(fields: Vec<Field>, end_stream: bool) -> Result<(RequestHead, BodyKind)> {
+    let mut method = None;
+    let mut scheme = None;
+    let mut authority = None;
+    let mut path = None;
+    let mut headers = HeaderMap::with_capacity(fields.len());
+    let mut cookies = Vec::new();
+    for (name, value) in fields {
+        if let Some(pseudo) = name.strip_prefix(':') {
+            if !headers.is_empty() || !cookies.is_empty() {
+                return err!(kind = Parse, "Pseudo-header after a regular header");
+            }
+            let slot = match pseudo {
+                "method" => &mut method,
+                "scheme" => &mut scheme,
+                "authority" => &mut authority,
+                "path" => &mut path,
+                _ => return err!(kind = Parse, "Unknown pseudo-header: {name}"),
+            };
+            if slot.replace(value).is_some() {
+                return err!(kind = Parse, "Duplicated pseudo-header: {name}");
+            }
+            continue;
+        }
+        if name.bytes().any(|b| b.is_ascii_uppercase()) {
+            return err!(kind = Parse, "Uppercase header name: {name}");
+        }
+        if is_connection_header(&name) || (&*name == "te" && &*value != "trailers") {
+            return err!(kind = Parse, "Connection-specific header: {name}");
+        }
+        /* Cookies can be split in many fields, to compress them better */
+        if &*name == "cookie" {
+            cookies.push(value);
+        } else {
+            headers.append(name, value);
+        }
+    }
+    if !cookies.is_empty() {
+        headers.append("cookie", cookies.join("; "));
+    }
+
+    let (Some(method), Some(_), Some(path)) = (method, scheme, path) else {
+        return err!(
+            kind = Parse,
+            "Missing :method, :scheme or :path pseudo-header"
+        );
+    };
+    let method = method.parse()?;
+    let (url, params) = parse_target(&path)?;
+    if let Some(authority) = authority
+        && !headers.contains("host")
+    {
+        headers.insert("host", authority);
+    }
+
+    let kind = match content_length(&headers)? {
+        _ if end_stream => BodyKind::Empty,
+        Some(len) => BodyKind::Length(len),
+        /* The body ends with the stream */
+        None => BodyKind::Close,
+    };
+    let head = RequestHead {
+        method,
+        url,
+        params,
+        version: HttpVersion::Http2,
+        headers,
+    };
+    Ok((head, kind))
+}
+
+/// The stream of a request, as seen by it's [`HttpRequest`]
+struct H2Stream {
+    inner: Rc<RefCell<Inner>>,
+    id: u32,
+}
+
+impl Read for H2Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.borrow_mut().read_body(self.id, buf)
+    }
+}
+
+impl Write for H2Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.borrow_mut().write_response(self.id, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.borrow_mut().io.get_mut().flush()
+    }
+}
+
+impl HttpStream for H2Stream {}
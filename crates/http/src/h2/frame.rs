@@ -0,0 +1,276 @@
+//! HTTP/2 frames
+//!
+//! See [RFC 9113 Section 4](https://www.rfc-editor.org/rfc/rfc9113#section-4)
+
+use std::io::{self, Read, Write};
+
+/// Size of the header of a frame
+pub const HEADER_LEN: usize = 9;
+
+/// Type of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    /// Frames of unknown types must be ignored
+    Unknown(u8),
+}
+
+impl From<u8> for FrameType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x0 => FrameType::Data,
+            0x1 => FrameType::Headers,
+            0x2 => FrameType::Priority,
+            0x3 => FrameType::RstStream,
+            0x4 => FrameType::Settings,
+            0x5 => FrameType::PushPromise,
+            0x6 => FrameType::Ping,
+            0x7 => FrameType::GoAway,
+            0x8 => FrameType::WindowUpdate,
+            0x9 => FrameType::Continuation,
+            n => FrameType::Unknown(n),
+        }
+    }
+}
+
+impl From<FrameType> for u8 {
+    fn from(value: FrameType) -> Self {
+        match value {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::GoAway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(n) => n,
+        }
+    }
+}
+
+/// Frame flags
+///
+/// The meaning of a flag depends on the type of the frame.
+pub mod flags {
+    /// DATA and HEADERS: This is the last frame of the stream
+    pub const END_STREAM: u8 = 0x1;
+    /// SETTINGS and PING: This frame acknowledges a previous one
+    pub const ACK: u8 = 0x1;
+    /// HEADERS and CONTINUATION: The header block ends in this frame
+    pub const END_HEADERS: u8 = 0x4;
+    /// DATA and HEADERS: The payload is padded
+    pub const PADDED: u8 = 0x8;
+    /// HEADERS: The payload starts with the stream's priority
+    pub const PRIORITY: u8 = 0x20;
+}
+
+/// Header of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Length of the payload
+    pub len: u32,
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+}
+
+impl FrameHeader {
+    /// Reads the header of the next frame
+    ///
+    /// Returns None if the reader is at EOF before the header starts.
+    ///
+    /// # Errors
+    /// If the reader fails, or the EOF is found inside the header
+    pub fn read(reader: &mut impl Read) -> io::Result<Option<Self>> {
+        let mut buf = [0; HEADER_LEN];
+        let mut n = 0;
+        while n < HEADER_LEN {
+            match reader.read(&mut buf[n..]) {
+                Ok(0) if n == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(m) => n += m,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Some(Self::parse(&buf)))
+    }
+
+    /// Parses a frame header
+    #[must_use]
+    pub fn parse(buf: &[u8; HEADER_LEN]) -> Self {
+        Self {
+            len: u32::from_be_bytes([0, buf[0], buf[1], buf[2]]),
+            kind: buf[3].into(),
+            flags: buf[4],
+            /* The first bit is reserved, and must be ignored */
+            stream_id: u32::from_be_bytes([buf[5], buf[6], buf[7], buf[8]]) & 0x7fff_ffff,
+        }
+    }
+
+    /// Encodes this header
+    #[must_use]
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let len = self.len.to_be_bytes();
+        let id = self.stream_id.to_be_bytes();
+        [
+            len[1],
+            len[2],
+            len[3],
+            self.kind.into(),
+            self.flags,
+            id[0],
+            id[1],
+            id[2],
+            id[3],
+        ]
+    }
+
+    #[must_use]
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+/// A frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameType, flags: u8, stream_id: u32, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kind,
+            flags,
+            stream_id,
+            payload: payload.into(),
+        }
+    }
+
+    /// Reads the payload of the frame with the given header
+    ///
+    /// # Errors
+    /// If the reader fails, or it ends before the whole payload is read
+    pub fn read_payload(header: FrameHeader, reader: &mut impl Read) -> io::Result<Self> {
+        let mut payload = vec![0; header.len as usize];
+        reader.read_exact(&mut payload)?;
+        Ok(Self {
+            kind: header.kind,
+            flags: header.flags,
+            stream_id: header.stream_id,
+            payload,
+        })
+    }
+
+    #[must_use]
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Returns the header of this frame
+    ///
+    /// The payload must be smaller than 16 MiB, which
+    /// is the maximum size of a frame.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn header(&self) -> FrameHeader {
+        debug_assert!(self.payload.len() < 1 << 24);
+        let len = self.payload.len() as u32;
+        FrameHeader {
+            len,
+            kind: self.kind,
+            flags: self.flags,
+            stream_id: self.stream_id,
+        }
+    }
+
+    /// Writes this frame
+    ///
+    /// # Errors
+    /// If the writer fails
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        buf.extend_from_slice(&self.header().encode());
+        buf.extend_from_slice(&self.payload);
+        writer.write_all(&buf)
+    }
+}
+
+/// Removes the padding of a DATA or HEADERS frame
+///
+/// Returns None if the padding is longer than the payload.
+#[must_use]
+pub fn strip_padding(flags: u8, payload: &[u8]) -> Option<&[u8]> {
+    if flags & flags::PADDED == 0 {
+        return Some(payload);
+    }
+    let (&pad, rest) = payload.split_first()?;
+    rest.len().checked_sub(pad.into()).map(|len| &rest[..len])
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::{Frame, FrameHeader, FrameType, flags, strip_padding};
+
+    #[test]
+    fn round_trip() {
+        let frame = Frame::new(
+            FrameType::Headers,
+            flags::END_HEADERS | flags::END_STREAM,
+            0x1234_5678,
+            b"payload".to_vec(),
+        );
+        let mut buf = Vec::new();
+        frame.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..9], [0, 0, 7, 1, 5, 0x12, 0x34, 0x56, 0x78]);
+
+        let mut reader = &buf[..];
+        let header = FrameHeader::read(&mut reader).unwrap().unwrap();
+        assert_eq!(header, frame.header());
+        assert_eq!(Frame::read_payload(header, &mut reader).unwrap(), frame);
+        assert!(FrameHeader::read(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn reserved_bit() {
+        let header = FrameHeader::parse(&[0, 0, 0, 0xff, 0, 0x80, 0, 0, 3]);
+        assert_eq!(header.stream_id, 3);
+        assert_eq!(header.kind, FrameType::Unknown(0xff));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(FrameHeader::read(&mut &[0, 0, 4, 0][..]).is_err());
+        let header = FrameHeader::parse(&[0, 0, 4, 0, 0, 0, 0, 0, 1]);
+        assert!(Frame::read_payload(header, &mut &b"ab"[..]).is_err());
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(strip_padding(0, b"abc"), Some(&b"abc"[..]));
+        assert_eq!(
+            strip_padding(flags::PADDED, b"\x02abc\0\0"),
+            Some(&b"abc"[..])
+        );
+        assert_eq!(strip_padding(flags::PADDED, b"\x05abc"), None);
+        assert_eq!(strip_padding(flags::PADDED, b""), None);
+    }
+}
@@ -0,0 +1,474 @@
+//! HPACK: Header compression for HTTP/2
+//!
+//! See [RFC 7541](https://www.rfc-editor.org/rfc/rfc7541)
+//!
+//! # Example
+//! ```
+//! use http::h2::hpack::{self, Decoder};
+//!
+//! let mut block = Vec::new();
+//! hpack::encode([(":status", "200"), ("content-type", "text/plain")], &mut block);
+//!
+//! let mut decoder = Decoder::new(4096);
+//! let headers = decoder.decode(&block, 16 * 1024, 100).unwrap();
+//! assert_eq!(&*headers[0].1, "200");
+//! assert_eq!(&*headers[1].0, "content-type");
+//! ```
+
+use core::fmt;
+use std::error::Error;
+
+mod huffman;
+mod table;
+use table::{DynamicTable, STATIC_TABLE};
+
+/// An error found while decoding a header block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HpackError {
+    /// The block ended in the middle of a representation
+    Truncated,
+    /// An integer doesn't fit in a usize
+    IntegerOverflow,
+    /// A reference to an entry that doesn't exist
+    InvalidIndex(usize),
+    /// A string has an invalid Huffman encoding
+    Huffman,
+    /// A dynamic table size update is bigger than the allowed
+    /// maximum, or it's not at the start of the block
+    TableSizeUpdate,
+    /// The decoded header list is bigger than the given limits
+    ListTooLarge,
+}
+
+impl fmt::Display for HpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HpackError::Truncated => write!(f, "Truncated header block"),
+            HpackError::IntegerOverflow => write!(f, "Integer overflow"),
+            HpackError::InvalidIndex(i) => write!(f, "Invalid table index: {i}"),
+            HpackError::Huffman => write!(f, "Invalid Huffman code"),
+            HpackError::TableSizeUpdate => write!(f, "Invalid dynamic table size update"),
+            HpackError::ListTooLarge => write!(f, "Header list too large"),
+        }
+    }
+}
+
+impl Error for HpackError {}
+
+type Result<T> = std::result::Result<T, HpackError>;
+
+/// A decoded header field
+pub type Field = (Box<str>, Box<str>);
+
+/// Decoder of header blocks
+///
+/// A connection has a single decoder, that must see all the
+/// header blocks in the order they're received, since they
+/// can refer to the fields indexed by the previous ones.
+pub struct Decoder {
+    table: DynamicTable,
+    max_size: usize,
+}
+
+impl Decoder {
+    /// Creates a decoder whose dynamic table can
+    /// grow up to `max_size` bytes.
+    ///
+    /// This is the size sent in the `SETTINGS_HEADER_TABLE_SIZE` setting.
+    #[must_use]
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            table: DynamicTable::new(max_size),
+            max_size,
+        }
+    }
+
+    /// Decodes a header block
+    ///
+    /// The decoded list can have up to `max_fields` fields, and up to
+    /// `max_size` bytes, counting each field as the length of it's name
+    /// and value plus 32, like `SETTINGS_MAX_HEADER_LIST_SIZE` does.
+    ///
+    /// Strings that are not valid UTF-8 are converted lossily.
+    ///
+    /// # Errors
+    /// - If the block is malformed. In that case, the state of the
+    ///   decoder is corrupted, and the connection must be closed.
+    /// - [`HpackError::ListTooLarge`] if the list exceeds the limits. No more
+    ///   fields are kept once it does, but the rest of the block is still
+    ///   processed, so the decoder can be used for the next blocks.
+    pub fn decode(
+        &mut self,
+        mut block: &[u8],
+        max_size: usize,
+        max_fields: usize,
+    ) -> Result<Vec<Field>> {
+        let mut fields = Vec::new();
+        let mut list = ListSize::new(max_size, max_fields);
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                /* Indexed Header Field */
+                let index = decode_int(&mut block, 7)?;
+                let (name, value) = self.get(index)?;
+                if list.add(name, value) {
+                    fields.push((name.into(), value.into()));
+                }
+            } else if first & 0x40 != 0 {
+                /* Literal Header Field with Incremental Indexing */
+                let (name, value) = self.literal(&mut block, 6)?;
+                self.table.insert(name.clone(), value.clone());
+                if list.add(&name, &value) {
+                    fields.push((name, value));
+                }
+            } else if first & 0x20 != 0 {
+                /* Dynamic Table Size Update */
+                let size = decode_int(&mut block, 5)?;
+                if size > self.max_size || list.count > 0 {
+                    return Err(HpackError::TableSizeUpdate);
+                }
+                self.table.set_max_size(size);
+            } else {
+                /* Literal Header Field without Indexing / Never Indexed */
+                let (name, value) = self.literal(&mut block, 4)?;
+                if list.add(&name, &value) {
+                    fields.push((name, value));
+                }
+            }
+        }
+        if list.exceeded() {
+            return Err(HpackError::ListTooLarge);
+        }
+        Ok(fields)
+    }
+
+    fn get(&self, index: usize) -> Result<(&str, &str)> {
+        match index {
+            0 => None,
+            1..=61 => STATIC_TABLE.get(index - 1).copied(),
+            _ => self.table.get(index - STATIC_TABLE.len()),
+        }
+        .ok_or(HpackError::InvalidIndex(index))
+    }
+
+    fn literal(&self, block: &mut &[u8], prefix: u8) -> Result<Field> {
+        let index = decode_int(block, prefix)?;
+        let name = match index {
+            0 => decode_str(block)?,
+            _ => self.get(index)?.0.into(),
+        };
+        let value = decode_str(block)?;
+        Ok((name, value))
+    }
+}
+
+/// Running size of a decoded header list
+struct ListSize {
+    size: usize,
+    count: usize,
+    max_size: usize,
+    max_fields: usize,
+}
+
+impl ListSize {
+    fn new(max_size: usize, max_fields: usize) -> Self {
+        Self {
+            size: 0,
+            count: 0,
+            max_size,
+            max_fields,
+        }
+    }
+
+    /// Adds a field, and returns false if the list no longer fits
+    fn add(&mut self, name: &str, value: &str) -> bool {
+        self.size = self.size.saturating_add(name.len() + value.len() + 32);
+        self.count += 1;
+        !self.exceeded()
+    }
+
+    fn exceeded(&self) -> bool {
+        self.size > self.max_size || self.count > self.max_fields
+    }
+}
+
+/// Encodes a list of header fields
+///
+/// The names must be lowercase. Fields are taken from the static table
+/// when possible, and the dynamic table is never used, so the block
+/// can be decoded regardless of the decoder's table size.
+pub fn encode<'a>(fields: impl IntoIterator<Item = (&'a str, &'a str)>, dst: &mut Vec<u8>) {
+    for (name, value) in fields {
+        let mut name_index = 0;
+        let mut exact = None;
+        for (i, &(n, v)) in STATIC_TABLE.iter().enumerate() {
+            if n != name {
+                continue;
+            }
+            if v == value {
+                exact = Some(i + 1);
+                break;
+            }
+            if name_index == 0 {
+                name_index = i + 1;
+            }
+        }
+        if let Some(index) = exact {
+            encode_int(index, 7, 0x80, dst);
+            continue;
+        }
+        encode_int(name_index, 4, 0, dst);
+        if name_index == 0 {
+            encode_str(name, dst);
+        }
+        encode_str(value, dst);
+    }
+}
+
+fn decode_int(block: &mut &[u8], prefix: u8) -> Result<usize> {
+    let max = (1 << prefix) - 1;
+    let (&first, rest) = block.split_first().ok_or(HpackError::Truncated)?;
+    *block = rest;
+    let mut value = usize::from(first) & max;
+    if value < max {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let (&b, rest) = block.split_first().ok_or(HpackError::Truncated)?;
+        *block = rest;
+        if shift > 28 {
+            return Err(HpackError::IntegerOverflow);
+        }
+        value += usize::from(b & 0x7f) << shift;
+        shift += 7;
+        if b & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn encode_int(mut value: usize, prefix: u8, flags: u8, dst: &mut Vec<u8>) {
+    let max = (1 << prefix) - 1;
+    if value < max {
+        dst.push(flags | value as u8);
+        return;
+    }
+    dst.push(flags | max as u8);
+    value -= max;
+    while value >= 0x80 {
+        dst.push(0x80 | (value & 0x7f) as u8);
+        value >>= 7;
+    }
+    dst.push(value as u8);
+}
+
+fn decode_str(block: &mut &[u8]) -> Result<Box<str>> {
+    let huffman = block.first().is_some_and(|b| b & 0x80 != 0);
+    let len = decode_int(block, 7)?;
+    if len > block.len() {
+        return Err(HpackError::Truncated);
+    }
+    let (bytes, rest) = block.split_at(len);
+    *block = rest;
+    let s = if huffman {
+        String::from_utf8_lossy(&huffman::decode(bytes)?).into()
+    } else {
+        String::from_utf8_lossy(bytes).into()
+    };
+    Ok(s)
+}
+
+fn encode_str(s: &str, dst: &mut Vec<u8>) {
+    let len = huffman::encoded_len(s.as_bytes());
+    if len < s.len() {
+        encode_int(len, 7, 0x80, dst);
+        huffman::encode(s.as_bytes(), dst);
+    } else {
+        encode_int(s.len(), 7, 0, dst);
+        dst.extend_from_slice(s.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::{Decoder, HpackError, decode_int, encode, encode_int};
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fields(decoded: &[(Box<str>, Box<str>)]) -> Vec<(&str, &str)> {
+        decoded.iter().map(|(k, v)| (&**k, &**v)).collect()
+    }
+
+    #[test]
+    fn integers() {
+        /* RFC 7541 C.1 */
+        for (value, prefix, encoded) in [(10, 5, "0a"), (1337, 5, "1f9a0a"), (42, 8, "2a")] {
+            let mut out = Vec::new();
+            encode_int(value, prefix, 0, &mut out);
+            assert_eq!(out, hex(encoded));
+            assert_eq!(decode_int(&mut &out[..], prefix).unwrap(), value);
+        }
+        let overflow = hex("1f ff ff ff ff ff ff ff ff ff 01");
+        assert_eq!(
+            decode_int(&mut &overflow[..], 5),
+            Err(HpackError::IntegerOverflow)
+        );
+    }
+
+    #[test]
+    fn requests_with_huffman() {
+        /* RFC 7541 C.4 */
+        let mut decoder = Decoder::new(4096);
+        let first = decoder
+            .decode(
+                &hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff"),
+                usize::MAX,
+                usize::MAX,
+            )
+            .unwrap();
+        assert_eq!(fields(&first), [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ]);
+        let second = decoder
+            .decode(
+                &hex("8286 84be 5886 a8eb 1064 9cbf"),
+                usize::MAX,
+                usize::MAX,
+            )
+            .unwrap();
+        assert_eq!(fields(&second), [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+            ("cache-control", "no-cache"),
+        ]);
+        let third = decoder
+            .decode(
+                &hex("8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf"),
+                usize::MAX,
+                usize::MAX,
+            )
+            .unwrap();
+        assert_eq!(fields(&third), [
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/index.html"),
+            (":authority", "www.example.com"),
+            ("custom-key", "custom-value"),
+        ]);
+        assert_eq!(decoder.table.size(), 164);
+    }
+
+    #[test]
+    fn eviction() {
+        /* RFC 7541 C.5, with a table of 256 bytes */
+        let mut decoder = Decoder::new(256);
+        decoder
+            .decode(
+                &hex("4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120
+                 4f63 7420 3230 3133 2032 303a 3133 3a32 3120 474d 546e 1768
+                 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 6c65 2e63 6f6d"),
+                usize::MAX,
+                usize::MAX,
+            )
+            .unwrap();
+        assert_eq!(decoder.table.size(), 222);
+        let second = decoder
+            .decode(&hex("4803 3330 37c1 c0bf"), usize::MAX, usize::MAX)
+            .unwrap();
+        assert_eq!(fields(&second), [
+            (":status", "307"),
+            ("cache-control", "private"),
+            ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+            ("location", "https://www.example.com"),
+        ]);
+        assert_eq!(decoder.table.size(), 222);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut decoder = Decoder::new(4096);
+        assert_eq!(
+            decoder.decode(&[0x80], usize::MAX, usize::MAX),
+            Err(HpackError::InvalidIndex(0))
+        );
+        assert_eq!(
+            decoder.decode(&[0xbe], usize::MAX, usize::MAX),
+            Err(HpackError::InvalidIndex(62))
+        );
+        assert_eq!(
+            decoder.decode(&[0x40, 0x05, b'a'], usize::MAX, usize::MAX),
+            Err(HpackError::Truncated)
+        );
+        /* Bigger than the maximum size */
+        assert_eq!(
+            decoder.decode(&hex("3fe2 1f"), usize::MAX, usize::MAX),
+            Err(HpackError::TableSizeUpdate)
+        );
+        /* After a field */
+        assert_eq!(
+            decoder.decode(&hex("82 20"), usize::MAX, usize::MAX),
+            Err(HpackError::TableSizeUpdate)
+        );
+        decoder
+            .decode(&hex("20 82"), usize::MAX, usize::MAX)
+            .unwrap();
+    }
+
+    #[test]
+    fn list_limits() {
+        /* A big entry in the dynamic table, referenced many times */
+        let mut block = vec![0x40, 0x06];
+        block.extend_from_slice(b"x-bomb");
+        block.extend([0x7f, 0xa1, 0x1e]);
+        block.extend([b'a'; 4000]);
+        block.extend([0xbe; 10_000]);
+
+        let mut decoder = Decoder::new(4096);
+        assert_eq!(
+            decoder.decode(&block, 64 * 1024, 100),
+            Err(HpackError::ListTooLarge)
+        );
+        /* The decoder is still in sync */
+        let decoded = decoder.decode(&[0xbe], 64 * 1024, 100).unwrap();
+        assert_eq!(&*decoded[0].0, "x-bomb");
+        assert_eq!(decoded[0].1.len(), 4000);
+
+        let block = [0x82; 4];
+        assert_eq!(
+            decoder.decode(&block, usize::MAX, 3),
+            Err(HpackError::ListTooLarge)
+        );
+        assert_eq!(decoder.decode(&block, usize::MAX, 4).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = [
+            (":status", "200"),
+            (":status", "418"),
+            ("content-type", "text/html; charset=utf-8"),
+            ("x-custom", "Value with UPPERCASE and símbolos"),
+            ("content-length", ""),
+        ];
+        let mut block = Vec::new();
+        encode(input, &mut block);
+        let decoded = Decoder::new(0)
+            .decode(&block, usize::MAX, usize::MAX)
+            .unwrap();
+        assert_eq!(fields(&decoded), input);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+/// Static table of HPACK
+///
+/// See [RFC 7541 Appendix A](https://www.rfc-editor.org/rfc/rfc7541#appendix-A)
+pub const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Overhead of each entry of the dynamic table
+const ENTRY_OVERHEAD: usize = 32;
+
+/// Dynamic table of HPACK
+///
+/// New entries are inserted at the front, and the
+/// oldest ones are evicted when the table is full.
+pub struct DynamicTable {
+    entries: VecDeque<(Box<str>, Box<str>)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl DynamicTable {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    /// Returns the entry with the given index, counting from 1
+    pub fn get(&self, index: usize) -> Option<(&str, &str)> {
+        let i = index.checked_sub(1)?;
+        self.entries.get(i).map(|(k, v)| (&**k, &**v))
+    }
+
+    pub fn insert(&mut self, name: Box<str>, value: Box<str>) {
+        let size = name.len() + value.len() + ENTRY_OVERHEAD;
+        /* An entry bigger than the table empties it */
+        self.size += size;
+        self.entries.push_front((name, value));
+        self.evict();
+    }
+
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            let Some((k, v)) = self.entries.pop_back() else {
+                break;
+            };
+            self.size -= k.len() + v.len() + ENTRY_OVERHEAD;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
@@ -0,0 +1,296 @@
+//! HTTP/2
+//!
+//! Server side of [RFC 9113](https://www.rfc-editor.org/rfc/rfc9113).
+//!
+//! A [`Connection`] multiplexes the requests of an HTTP/2 connection,
+//! and passes each of them to a handler as a regular [`HttpRequest`].
+//! The handler responds like it would to an HTTP/1.1 request, and
+//! the response is translated into HTTP/2 frames.
+//!
+//! A connection can be started in three ways:
+//! - Over TLS, when `h2` is negotiated with ALPN
+//! - In cleartext, when the client sends the [connection preface](PREFACE)
+//!   right away ("prior knowledge"). See [`starts_with_preface`]
+//! - In cleartext, when an HTTP/1.1 request asks to upgrade to `h2c`.
+//!   See [`is_upgrade`] and [`Connection::upgrade`]
+//!
+//! # Example
+//! ```no_run
+//! use std::{io::BufReader, net::TcpListener};
+//! use http::{HttpRequest, HttpStream, ParserLimits, h2};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+//! let (tcp, _) = listener.accept().unwrap();
+//! let stream: Box<dyn HttpStream> = Box::new(tcp);
+//! let mut stream = BufReader::new(stream);
+//!
+//! let handler = |req: &mut HttpRequest| req.respond_str("Hello world!");
+//! if h2::starts_with_preface(&mut stream).unwrap() {
+//!     h2::Connection::new(stream, ParserLimits::default())
+//!         .serve(handler)
+//!         .unwrap();
+//! }
+//! ```
+
+use core::fmt;
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    mem,
+};
+
+use crate::{HttpRequest, HttpStream, stream::PrefixedStream};
+
+mod connection;
+pub use connection::Connection;
+pub mod frame;
+pub mod hpack;
+
+/// The connection preface, sent by the client at the start of a connection
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Error code of a RST_STREAM or GOAWAY frame
+///
+/// See [RFC 9113 Section 7](https://www.rfc-editor.org/rfc/rfc9113#section-7)
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode(pub u32);
+
+impl ErrorCode {
+    pub const NO_ERROR: Self = Self(0x0);
+    pub const PROTOCOL_ERROR: Self = Self(0x1);
+    pub const INTERNAL_ERROR: Self = Self(0x2);
+    pub const FLOW_CONTROL_ERROR: Self = Self(0x3);
+    pub const SETTINGS_TIMEOUT: Self = Self(0x4);
+    pub const STREAM_CLOSED: Self = Self(0x5);
+    pub const FRAME_SIZE_ERROR: Self = Self(0x6);
+    pub const REFUSED_STREAM: Self = Self(0x7);
+    pub const CANCEL: Self = Self(0x8);
+    pub const COMPRESSION_ERROR: Self = Self(0x9);
+    pub const CONNECT_ERROR: Self = Self(0xa);
+    pub const ENHANCE_YOUR_CALM: Self = Self(0xb);
+    pub const INADEQUATE_SECURITY: Self = Self(0xc);
+    pub const HTTP_1_1_REQUIRED: Self = Self(0xd);
+
+    /// Returns the name of the error code, if it's known
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        const NAMES: [&str; 14] = [
+            "NO_ERROR",
+            "PROTOCOL_ERROR",
+            "INTERNAL_ERROR",
+            "FLOW_CONTROL_ERROR",
+            "SETTINGS_TIMEOUT",
+            "STREAM_CLOSED",
+            "FRAME_SIZE_ERROR",
+            "REFUSED_STREAM",
+            "CANCEL",
+            "COMPRESSION_ERROR",
+            "CONNECT_ERROR",
+            "ENHANCE_YOUR_CALM",
+            "INADEQUATE_SECURITY",
+            "HTTP_1_1_REQUIRED",
+        ];
+        NAMES.get(self.0 as usize).copied()
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "0x{:x}", self.0),
+        }
+    }
+}
+
+impl fmt::Debug for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Maximum value of a flow control window
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// Parameters of a SETTINGS frame
+///
+/// See [RFC 9113 Section 6.5.2](https://www.rfc-editor.org/rfc/rfc9113#section-6.5.2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub header_table_size: u32,
+    pub enable_push: bool,
+    /// None means unlimited
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_window_size: u32,
+    pub max_frame_size: u32,
+    /// None means unlimited
+    pub max_header_list_size: Option<u32>,
+}
+
+impl Settings {
+    pub const HEADER_TABLE_SIZE: u16 = 0x1;
+    pub const ENABLE_PUSH: u16 = 0x2;
+    pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+    pub const MAX_FRAME_SIZE: u16 = 0x5;
+    pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+    /// Smallest value of `max_frame_size`, and it's initial value
+    pub const MIN_FRAME_SIZE: u32 = 1 << 14;
+    /// Largest value of `max_frame_size`
+    pub const MAX_FRAME_SIZE_LIMIT: u32 = (1 << 24) - 1;
+
+    /// Applies the parameters of a SETTINGS frame's payload
+    ///
+    /// Unknown parameters are ignored.
+    ///
+    /// # Errors
+    /// If the payload, or any of the values, are invalid
+    pub fn apply(&mut self, payload: &[u8]) -> Result<(), ErrorCode> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(ErrorCode::FRAME_SIZE_ERROR);
+        }
+        for param in payload.chunks_exact(6) {
+            let id = u16::from_be_bytes([param[0], param[1]]);
+            let value = u32::from_be_bytes([param[2], param[3], param[4], param[5]]);
+            match id {
+                Self::HEADER_TABLE_SIZE => self.header_table_size = value,
+                Self::ENABLE_PUSH => {
+                    self.enable_push = match value {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(ErrorCode::PROTOCOL_ERROR),
+                    }
+                }
+                Self::MAX_CONCURRENT_STREAMS => self.max_concurrent_streams = Some(value),
+                Self::INITIAL_WINDOW_SIZE => {
+                    if value > MAX_WINDOW_SIZE {
+                        return Err(ErrorCode::FLOW_CONTROL_ERROR);
+                    }
+                    self.initial_window_size = value;
+                }
+                Self::MAX_FRAME_SIZE => {
+                    if !(Self::MIN_FRAME_SIZE..=Self::MAX_FRAME_SIZE_LIMIT).contains(&value) {
+                        return Err(ErrorCode::PROTOCOL_ERROR);
+                    }
+                    self.max_frame_size = value;
+                }
+                Self::MAX_HEADER_LIST_SIZE => self.max_header_list_size = Some(value),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes the parameters that differ from the initial values
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let initial = Self::default();
+        let mut params = Vec::new();
+        if self.header_table_size != initial.header_table_size {
+            params.push((Self::HEADER_TABLE_SIZE, self.header_table_size));
+        }
+        if self.enable_push != initial.enable_push {
+            params.push((Self::ENABLE_PUSH, u32::from(self.enable_push)));
+        }
+        if let Some(max) = self.max_concurrent_streams {
+            params.push((Self::MAX_CONCURRENT_STREAMS, max));
+        }
+        if self.initial_window_size != initial.initial_window_size {
+            params.push((Self::INITIAL_WINDOW_SIZE, self.initial_window_size));
+        }
+        if self.max_frame_size != initial.max_frame_size {
+            params.push((Self::MAX_FRAME_SIZE, self.max_frame_size));
+        }
+        if let Some(max) = self.max_header_list_size {
+            params.push((Self::MAX_HEADER_LIST_SIZE, max));
+        }
+        params
+            .into_iter()
+            .flat_map(|(id, value)| {
+                let mut param = [0; 6];
+                param[..2].copy_from_slice(&id.to_be_bytes());
+                param[2..].copy_from_slice(&value.to_be_bytes());
+                param
+            })
+            .collect()
+    }
+}
+
+impl Default for Settings {
+    /// The initial values, before any SETTINGS frame is received
+    fn default() -> Self {
+        Self {
+            header_table_size: 4096,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: 65_535,
+            max_frame_size: Self::MIN_FRAME_SIZE,
+            max_header_list_size: None,
+        }
+    }
+}
+
+/// Returns true if the client started the connection
+/// with the HTTP/2 [connection preface](PREFACE)
+///
+/// Nothing is consumed from the stream, so it can be
+/// parsed as HTTP/1.x if this function returns false.
+///
+/// # Errors
+/// If reading from the stream fails
+pub fn starts_with_preface(stream: &mut BufReader<Box<dyn HttpStream>>) -> io::Result<bool> {
+    /* "PRI" is not a valid HTTP/1 method, so the first bytes are enough */
+    const START: &[u8] = b"PRI ";
+    let buf = stream.fill_buf()?;
+    if buf.is_empty() || buf.len() >= START.len() || !START.starts_with(buf) {
+        return Ok(buf.starts_with(START));
+    }
+
+    /* The first read was too short to decide. The BufReader only reads
+     * when its buffer is empty, so the bytes read so far are taken out of
+     * it and put back in front of the stream. */
+    let old = mem::replace(stream, BufReader::new(crate::stream::dummy()));
+    let mut start = old.buffer().to_vec();
+    let mut inner = old.into_inner();
+    let mut chunk = [0; START.len()];
+    while start.len() < START.len() && START.starts_with(&start) {
+        let n = match inner.read(&mut chunk[..START.len() - start.len()]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                *stream = BufReader::new(Box::new(PrefixedStream::new(start, inner)));
+                return Err(err);
+            }
+        };
+        start.extend_from_slice(&chunk[..n]);
+    }
+    let found = start.starts_with(START);
+    *stream = BufReader::new(Box::new(PrefixedStream::new(start, inner)));
+    Ok(found)
+}
+
+/// Returns true if `req` asks to upgrade the connection to HTTP/2
+/// in cleartext (`Upgrade: h2c`)
+///
+/// Only requests without a body are upgraded, since the body must be read
+/// before switching protocols. The rest can be served over HTTP/1.1,
+/// ignoring the *Upgrade* header.
+#[must_use]
+pub fn is_upgrade(req: &HttpRequest) -> bool {
+    let has_token = |header: &str, token: &str| {
+        req.headers()
+            .get_all(header)
+            .flat_map(|v| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    };
+    req.version() == crate::HttpVersion::Http11
+        && has_token("Upgrade", "h2c")
+        && has_token("Connection", "HTTP2-Settings")
+        && req.headers().get_all("HTTP2-Settings").count() == 1
+        && req.content_length() == 0
+        && !req.is_chunked()
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,467 @@
+#![allow(clippy::unwrap_used)]
+
+use std::io::{self, BufReader, Read, Write};
+
+use super::{
+    Connection, ErrorCode, PREFACE, Settings,
+    frame::{Frame, FrameHeader, FrameType, flags},
+    hpack::{self, Decoder},
+    is_upgrade, starts_with_preface,
+};
+use crate::{
+    HttpRequest, HttpStream, ParserLimits, Result,
+    stream::shared::{Output, SharedStream},
+};
+
+fn shared(input: Vec<u8>) -> (BufReader<Box<dyn HttpStream>>, Output) {
+    let (stream, output) = SharedStream::new(input);
+    let stream: Box<dyn HttpStream> = Box::new(stream);
+    (BufReader::new(stream), output)
+}
+
+fn frame(kind: FrameType, flags: u8, id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    Frame::new(kind, flags, id, payload)
+        .write_to(&mut buf)
+        .unwrap();
+    buf
+}
+
+fn headers(id: u32, fields: &[(&str, &str)], end_stream: bool) -> Vec<u8> {
+    let mut block = Vec::new();
+    hpack::encode(fields.iter().copied(), &mut block);
+    let mut flags = flags::END_HEADERS;
+    if end_stream {
+        flags |= flags::END_STREAM;
+    }
+    frame(FrameType::Headers, flags, id, &block)
+}
+
+fn get(id: u32, path: &str) -> Vec<u8> {
+    headers(
+        id,
+        &[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":authority", "localhost"),
+            (":path", path),
+        ],
+        true,
+    )
+}
+
+/// Builds the input of a connection: the preface, an
+/// empty SETTINGS frame, and then the given frames
+fn client(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut input = PREFACE.to_vec();
+    input.extend(frame(FrameType::Settings, 0, 0, &[]));
+    input.extend(frames.concat());
+    input
+}
+
+fn read_frames(mut output: &[u8]) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    while let Some(header) = FrameHeader::read(&mut output).unwrap() {
+        frames.push(Frame::read_payload(header, &mut output).unwrap());
+    }
+    frames
+}
+
+/// Serves the given input, and returns the frames sent by
+/// the server, except for the SETTINGS frames
+fn serve<F>(input: Vec<u8>, handler: F) -> (Result<()>, Vec<Frame>)
+where
+    F: FnMut(&mut HttpRequest) -> Result<()>,
+{
+    let (stream, output) = shared(input);
+    let result = Connection::new(stream, ParserLimits::default()).serve(handler);
+    let output = output.lock().unwrap();
+    let frames = read_frames(&output)
+        .into_iter()
+        .filter(|f| f.kind != FrameType::Settings)
+        .collect();
+    (result, frames)
+}
+
+fn decode(frame: &Frame) -> Vec<(String, String)> {
+    assert_eq!(frame.kind, FrameType::Headers);
+    Decoder::new(4096)
+        .decode(&frame.payload, usize::MAX, usize::MAX)
+        .unwrap()
+        .into_iter()
+        .map(|(k, v)| (k.into(), v.into()))
+        .collect()
+}
+
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+#[test]
+fn get_request() {
+    let input = client(&[get(1, "/hello?name=h2"), get(3, "/")]);
+    let mut urls = Vec::new();
+    let (result, frames) = serve(input, |req| {
+        urls.push(format!("{} {:?}", req.url(), req.param("name")));
+        assert_eq!(req.header("host"), Some("localhost"));
+        req.respond_str("Hello")
+    });
+    result.unwrap();
+    assert_eq!(urls, ["/hello Some(\"h2\")", "/ None"]);
+
+    let ids: Vec<u32> = frames.iter().map(|f| f.stream_id).collect();
+    assert_eq!(ids, [1, 1, 1, 3, 3, 3]);
+
+    let head = decode(&frames[0]);
+    assert_eq!(head[0], (":status".into(), "200".into()));
+    assert_eq!(field(&head, "content-length"), Some("5"));
+    assert!(field(&head, "connection").is_none());
+    assert!(!frames[0].has_flag(flags::END_STREAM));
+    assert_eq!(frames[1].kind, FrameType::Data);
+    assert_eq!(frames[1].payload, b"Hello");
+    assert!(frames[2].has_flag(flags::END_STREAM));
+}
+
+#[test]
+fn head_request() {
+    let fields = [(":method", "HEAD"), (":scheme", "http"), (":path", "/")];
+    let input = client(&[headers(1, &fields, true)]);
+    let (result, frames) = serve(input, |req| req.respond_str("Hello"));
+    result.unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(field(&decode(&frames[0]), "content-length"), Some("5"));
+    assert!(frames[0].has_flag(flags::END_STREAM));
+}
+
+#[test]
+fn request_body() {
+    let fields = [
+        (":method", "POST"),
+        (":scheme", "http"),
+        (":path", "/echo"),
+        ("content-length", "11"),
+        ("cookie", "a=1"),
+        ("cookie", "b=2"),
+    ];
+    let input = client(&[
+        headers(1, &fields, false),
+        frame(FrameType::Data, 0, 1, b"Hello "),
+        frame(FrameType::Data, flags::PADDED, 1, b"\x02world\0\0"),
+        frame(FrameType::Data, flags::END_STREAM, 1, b""),
+    ]);
+    let (result, frames) = serve(input, |req| {
+        assert_eq!(req.cookie("b"), Some("2"));
+        let body = req.body()?.unwrap().to_vec();
+        req.respond_buf(&body)
+    });
+    result.unwrap();
+    let data: Vec<u8> = frames
+        .iter()
+        .filter(|f| f.kind == FrameType::Data)
+        .flat_map(|f| f.payload.clone())
+        .collect();
+    assert_eq!(data, b"Hello world");
+}
+
+#[test]
+fn chunked_response() {
+    let input = client(&[get(1, "/")]);
+    let (result, frames) = serve(input, |req| {
+        req.add_header("Transfer-Encoding", "chunked");
+        req.respond_chunked(&mut &b"chunked body"[..])
+    });
+    result.unwrap();
+    let head = decode(&frames[0]);
+    assert!(field(&head, "transfer-encoding").is_none());
+    let data: Vec<u8> = frames[1..].iter().flat_map(|f| f.payload.clone()).collect();
+    assert_eq!(data, b"chunked body");
+    assert!(frames.last().unwrap().has_flag(flags::END_STREAM));
+}
+
+#[test]
+fn ping_and_settings() {
+    let mut settings = Vec::new();
+    settings.extend_from_slice(&Settings::MAX_FRAME_SIZE.to_be_bytes());
+    settings.extend_from_slice(&20_000_u32.to_be_bytes());
+    let input = client(&[
+        frame(FrameType::Ping, 0, 0, b"12345678"),
+        frame(FrameType::Settings, 0, 0, &settings),
+    ]);
+    let (stream, output) = shared(input);
+    Connection::new(stream, ParserLimits::default())
+        .serve(|_| unreachable!())
+        .unwrap();
+    let frames = read_frames(&output.lock().unwrap());
+
+    assert_eq!(frames[0].kind, FrameType::Settings);
+    assert!(!frames[0].has_flag(flags::ACK));
+    let mut local = Settings::default();
+    local.apply(&frames[0].payload).unwrap();
+    assert_eq!(local.max_concurrent_streams, Some(100));
+
+    assert_eq!(
+        frames[1..]
+            .iter()
+            .map(|f| (f.kind, f.flags))
+            .collect::<Vec<_>>(),
+        [
+            (FrameType::Settings, flags::ACK),
+            (FrameType::Ping, flags::ACK),
+            (FrameType::Settings, flags::ACK),
+        ]
+    );
+    assert_eq!(frames[2].payload, b"12345678");
+}
+
+#[test]
+fn flow_control() {
+    let mut settings = Vec::new();
+    settings.extend_from_slice(&Settings::INITIAL_WINDOW_SIZE.to_be_bytes());
+    settings.extend_from_slice(&2_u32.to_be_bytes());
+    let input = client(&[
+        frame(FrameType::Settings, 0, 0, &settings),
+        get(1, "/"),
+        frame(FrameType::WindowUpdate, 0, 1, &3_u32.to_be_bytes()),
+    ]);
+    let (result, frames) = serve(input, |req| req.respond_str("Hello"));
+    result.unwrap();
+    let data: Vec<&[u8]> = frames
+        .iter()
+        .filter(|f| f.kind == FrameType::Data)
+        .map(|f| &f.payload[..])
+        .collect();
+    assert_eq!(data, [&b"He"[..], b"llo", b""]);
+}
+
+#[test]
+fn continuation() {
+    let mut block = Vec::new();
+    hpack::encode(
+        [
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/split"),
+        ],
+        &mut block,
+    );
+    let (first, second) = block.split_at(3);
+    let input = client(&[
+        frame(FrameType::Headers, flags::END_STREAM, 1, first),
+        frame(FrameType::Continuation, flags::END_HEADERS, 1, second),
+    ]);
+    let (result, frames) = serve(input, |req| {
+        assert_eq!(req.url(), "/split");
+        req.ok()
+    });
+    result.unwrap();
+    assert_eq!(decode(&frames[0])[0].1, "200");
+}
+
+#[test]
+fn malformed_requests() {
+    let input = client(&[
+        /* Missing :path */
+        headers(1, &[(":method", "GET"), (":scheme", "http")], true),
+        /* Uppercase name */
+        headers(
+            3,
+            &[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                ("X-Upper", "1"),
+            ],
+            true,
+        ),
+        /* Connection-specific header, with a body pending */
+        headers(
+            5,
+            &[
+                (":method", "POST"),
+                (":scheme", "http"),
+                (":path", "/"),
+                ("connection", "close"),
+            ],
+            false,
+        ),
+    ]);
+    let (result, frames) = serve(input, |_| unreachable!());
+    result.unwrap();
+    for (frame, id) in frames.iter().zip([1, 3, 5]) {
+        assert_eq!(frame.stream_id, id);
+        assert_eq!(decode(frame)[0].1, "400");
+        assert!(frame.has_flag(flags::END_STREAM));
+    }
+    assert_eq!(frames[3].kind, FrameType::RstStream);
+    assert_eq!(frames[3].stream_id, 5);
+}
+
+#[test]
+fn header_list_too_large() {
+    /* GET / over http, and then a big entry in the dynamic
+     * table, referenced many times */
+    let mut block = vec![0x82, 0x86, 0x84, 0x40, 0x06];
+    block.extend_from_slice(b"x-bomb");
+    block.extend([0x7f, 0xa1, 0x1e]);
+    block.extend([b'a'; 4000]);
+    block.extend([0xbe; 10_000]);
+    let input = client(&[
+        frame(
+            FrameType::Headers,
+            flags::END_HEADERS | flags::END_STREAM,
+            1,
+            &block,
+        ),
+        get(3, "/"),
+    ]);
+    let (result, frames) = serve(input, |req| {
+        assert!(req.header("x-bomb").is_none());
+        req.ok()
+    });
+    result.unwrap();
+    assert_eq!(frames[0].stream_id, 1);
+    assert_eq!(decode(&frames[0])[0].1, "431");
+    /* The connection is still usable */
+    assert_eq!(frames[1].stream_id, 3);
+    assert_eq!(decode(&frames[1])[0].1, "200");
+}
+
+#[test]
+fn handler_error() {
+    let input = client(&[get(1, "/")]);
+    let (result, frames) = serve(input, |_| Err("Oops".into()));
+    result.unwrap();
+    assert_eq!(frames[0].kind, FrameType::RstStream);
+    assert_eq!(frames[0].payload, ErrorCode::INTERNAL_ERROR.0.to_be_bytes());
+}
+
+#[test]
+fn protocol_error() {
+    let input = client(&[frame(FrameType::Data, 0, 0, b"data")]);
+    let (result, frames) = serve(input, |_| unreachable!());
+    assert!(result.is_err());
+    assert_eq!(frames[0].kind, FrameType::GoAway);
+    assert_eq!(
+        &frames[0].payload[4..8],
+        ErrorCode::PROTOCOL_ERROR.0.to_be_bytes()
+    );
+
+    let mut input = PREFACE.to_vec();
+    input.extend(get(1, "/"));
+    let (result, frames) = serve(input, |_| unreachable!());
+    assert!(result.is_err());
+    assert_eq!(frames[0].kind, FrameType::GoAway);
+}
+
+#[test]
+fn prior_knowledge() {
+    let (mut stream, _) = shared(client(&[]));
+    assert!(starts_with_preface(&mut stream).unwrap());
+    let (mut stream, _) = shared(b"GET / HTTP/1.1\r\n\r\n".to_vec());
+    assert!(!starts_with_preface(&mut stream).unwrap());
+}
+
+/// Stream that returns at most 2 bytes on each read
+struct Trickle(io::Cursor<Vec<u8>>);
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(2);
+        self.0.read(&mut buf[..n])
+    }
+}
+
+impl Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl HttpStream for Trickle {}
+
+#[test]
+fn partial_preface() {
+    let trickle = |input: &[u8]| -> BufReader<Box<dyn HttpStream>> {
+        BufReader::new(Box::new(Trickle(io::Cursor::new(input.to_vec()))))
+    };
+
+    /* Nothing is lost while looking for the preface */
+    let mut stream = trickle(PREFACE);
+    assert!(starts_with_preface(&mut stream).unwrap());
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, PREFACE);
+
+    let mut stream = trickle(b"PRX / HTTP/1.1\r\n\r\n");
+    assert!(!starts_with_preface(&mut stream).unwrap());
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"PRX / HTTP/1.1\r\n\r\n");
+
+    let mut stream = trickle(b"PR");
+    assert!(!starts_with_preface(&mut stream).unwrap());
+
+    let mut stream = trickle(b"PUT / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n");
+    assert!(!starts_with_preface(&mut stream).unwrap());
+    let req = HttpRequest::parse_buffered(stream, ParserLimits::default()).unwrap();
+    assert_eq!(req.url(), "/");
+}
+
+#[test]
+fn upgrade() {
+    let mut input = b"GET /up HTTP/1.1\r\n\
+                      Host: localhost\r\n\
+                      Connection: Upgrade, HTTP2-Settings\r\n\
+                      Upgrade: h2c\r\n\
+                      HTTP2-Settings: AAMAAABk\r\n\r\n"
+        .to_vec();
+    input.extend(client(&[get(3, "/next")]));
+    let (stream, output) = shared(input);
+    let req = HttpRequest::parse_buffered(stream, ParserLimits::default()).unwrap();
+    assert!(is_upgrade(&req));
+
+    let mut urls = Vec::new();
+    Connection::upgrade(req)
+        .unwrap()
+        .serve(|req| {
+            assert!(req.header("upgrade").is_none());
+            urls.push(req.url().to_string());
+            req.respond_str("Hi")
+        })
+        .unwrap();
+    assert_eq!(urls, ["/up", "/next"]);
+
+    let output = output.lock().unwrap();
+    let end = output.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = std::str::from_utf8(&output[..end]).unwrap();
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(head.contains("Upgrade: h2c\r\n"));
+
+    let frames: Vec<Frame> = read_frames(&output[end..])
+        .into_iter()
+        .filter(|f| f.kind == FrameType::Headers)
+        .collect();
+    assert_eq!(frames[0].stream_id, 1);
+    assert_eq!(frames[1].stream_id, 3);
+}
+
+#[test]
+fn not_upgrade() {
+    let (stream, _) = shared(b"POST / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\nContent-Length: 2\r\n\r\nab".to_vec());
+    let req = HttpRequest::parse_buffered(stream, ParserLimits::default()).unwrap();
+    assert!(!is_upgrade(&req));
+    assert!(Connection::upgrade(req).is_err());
+
+    /* The body is chunked, since the last Transfer-Encoding is */
+    let (stream, _) = shared(b"POST / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n".to_vec());
+    let req = HttpRequest::parse_buffered(stream, ParserLimits::default()).unwrap();
+    assert!(req.is_chunked());
+    assert!(!is_upgrade(&req));
+}
@@ -0,0 +1,334 @@
+//! HTTP Headers
+
+use core::{fmt, slice};
+
+use crate::{Result, err};
+
+/// A collection of HTTP headers
+///
+/// - Header names are case-insensitive, as described in
+///   [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-5.1)
+/// - A header can have multiple values (e.g. *Set-Cookie*)
+/// - Headers are iterated in the order they were inserted
+///
+/// # Example
+/// ```
+/// use http::HeaderMap;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert("Content-Length", "12");
+/// headers.append("Set-Cookie", "a=1");
+/// headers.append("Set-Cookie", "b=2");
+///
+/// assert_eq!(headers.get("content-length"), Some("12"));
+/// let cookies: Vec<&str> = headers.get_all("SET-COOKIE").collect();
+/// assert_eq!(cookies, ["a=1", "b=2"]);
+/// ```
+#[derive(Clone, Default)]
+pub struct HeaderMap {
+    entries: Vec<(Box<str>, Box<str>)>,
+}
+
+impl HeaderMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(cap),
+        }
+    }
+
+    /// Get the first value of the given header, if present
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &**v)
+    }
+
+    /// Get all the values of the given header, in insertion order
+    pub fn get_all<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &**v)
+    }
+
+    /// Returns true if the map contains a value for the given header
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+
+    /// Sets the value of a header, replacing all it's previous values.
+    ///
+    /// If the header was already present, it keeps it's original position.
+    ///
+    /// # Returns
+    /// The first of the previous values of the header, if any
+    pub fn insert(
+        &mut self,
+        name: impl Into<Box<str>>,
+        value: impl Into<Box<str>>,
+    ) -> Option<Box<str>> {
+        let name = name.into();
+        let value = value.into();
+        let Some(i) = self.position(&name) else {
+            self.entries.push((name, value));
+            return None;
+        };
+        let prev = core::mem::replace(&mut self.entries[i].1, value);
+        let mut idx = 0;
+        self.entries.retain(|(k, _)| {
+            let keep = idx <= i || !k.eq_ignore_ascii_case(&name);
+            idx += 1;
+            keep
+        });
+        Some(prev)
+    }
+
+    /// Adds a value for the header, keeping the previous ones.
+    pub fn append(&mut self, name: impl Into<Box<str>>, value: impl Into<Box<str>>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    /// Removes all the values of a header
+    ///
+    /// # Returns
+    /// The first of the removed values, if any
+    pub fn remove(&mut self, name: &str) -> Option<Box<str>> {
+        let i = self.position(name)?;
+        let prev = self.entries.remove(i).1;
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        Some(prev)
+    }
+
+    /// Iterates over all the (name, value) pairs, in insertion order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    /// Returns the number of values stored in the map
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Iterator over the entries of a [`HeaderMap`]
+pub struct Iter<'a>(slice::Iter<'a, (Box<str>, Box<str>)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&**k, &**v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (Box<str>, Box<str>);
+    type IntoIter = std::vec::IntoIter<(Box<str>, Box<str>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<K: Into<Box<str>>, V: Into<Box<str>>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Into<Box<str>>, V: Into<Box<str>>> Extend<(K, V)> for HeaderMap {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.append(k, v);
+        }
+    }
+}
+
+impl PartialEq for HeaderMap {
+    /// Two maps are equal if they contain the same values for
+    /// each header, regardless of the order of different headers.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .entries
+                .iter()
+                .all(|(k, _)| self.get_all(k).eq(other.get_all(k)))
+    }
+}
+
+impl Eq for HeaderMap {}
+
+impl fmt::Debug for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Returns true if the given string is a valid [token]
+///
+/// [token]: <https://www.rfc-editor.org/rfc/rfc9110#section-5.6.2>
+#[must_use]
+pub fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes().all(|b| {
+            b.is_ascii_alphanumeric()
+                || matches!(
+                    b,
+                    b'!' | b'#'
+                        | b'$'
+                        | b'%'
+                        | b'&'
+                        | b'\''
+                        | b'*'
+                        | b'+'
+                        | b'-'
+                        | b'.'
+                        | b'^'
+                        | b'_'
+                        | b'`'
+                        | b'|'
+                        | b'~'
+                )
+        })
+}
+
+/// Returns true if the given string is a valid [field-value]
+///
+/// [field-value]: <https://www.rfc-editor.org/rfc/rfc9110#section-5.5>
+#[must_use]
+pub fn is_field_value(s: &str) -> bool {
+    s.bytes()
+        .all(|b| b == b'\t' || b == b' ' || b.is_ascii_graphic() || b >= 0x80)
+}
+
+/// Parses a header line into a (name, value) pair.
+///
+/// The line is split on the first colon, and both parts are validated.
+/// Whitespace between the name and the colon, and obsolete line folding,
+/// are rejected, as described in [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-5).
+pub(crate) fn parse_header_line(line: &str) -> Result<(&str, &str)> {
+    let Some((name, value)) = line.split_once(':') else {
+        return err!(kind = Parse, "Malformed header line: missing ':'");
+    };
+    if !is_token(name) {
+        return err!(kind = Parse, "Invalid header name: \"{name}\"");
+    }
+    let value = value.trim_matches([' ', '\t']);
+    if !is_field_value(value) {
+        return err!(kind = Parse, "Invalid value for header \"{name}\"");
+    }
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HeaderMap, parse_header_line};
+
+    #[test]
+    fn case_insensitive() {
+        let mut map = HeaderMap::new();
+        map.insert("Content-Type", "text/plain");
+        assert_eq!(map.get("content-type"), Some("text/plain"));
+        assert!(map.contains("CONTENT-TYPE"));
+        assert_eq!(map.get("Content-Length"), None);
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut map = HeaderMap::new();
+        map.append("Accept", "a");
+        map.append("Host", "localhost");
+        map.append("accept", "b");
+
+        let prev = map.insert("ACCEPT", "c");
+        assert_eq!(prev.as_deref(), Some("a"));
+        let entries: Vec<_> = map.iter().collect();
+        assert_eq!(entries, [("Accept", "c"), ("Host", "localhost")]);
+    }
+
+    #[test]
+    fn append_keeps_order() {
+        let mut map = HeaderMap::new();
+        map.append("Set-Cookie", "a=1");
+        map.append("Date", "today");
+        map.append("Set-Cookie", "b=2");
+
+        let cookies: Vec<_> = map.get_all("set-cookie").collect();
+        assert_eq!(cookies, ["a=1", "b=2"]);
+        let names: Vec<_> = map.iter().map(|(k, _)| k).collect();
+        assert_eq!(names, ["Set-Cookie", "Date", "Set-Cookie"]);
+
+        assert_eq!(map.remove("SET-COOKIE").as_deref(), Some("a=1"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn equality() {
+        let a: HeaderMap = [("A", "1"), ("B", "2"), ("B", "3")].into_iter().collect();
+        let b: HeaderMap = [("b", "2"), ("a", "1"), ("b", "3")].into_iter().collect();
+        let c: HeaderMap = [("b", "3"), ("a", "1"), ("b", "2")].into_iter().collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn header_lines() {
+        assert_eq!(
+            parse_header_line("Host: example.com:8080").unwrap(),
+            ("Host", "example.com:8080")
+        );
+        assert_eq!(
+            parse_header_line("Location:\thttp://localhost/a?b=c:d \t").unwrap(),
+            ("Location", "http://localhost/a?b=c:d")
+        );
+        assert_eq!(parse_header_line("X-Empty:").unwrap(), ("X-Empty", ""));
+
+        assert!(parse_header_line("No colon").is_err());
+        assert!(parse_header_line("Host : localhost").is_err());
+        assert!(parse_header_line(": value").is_err());
+        assert!(parse_header_line("Bad(Name): value").is_err());
+        assert!(parse_header_line("X-Ctl: a\u{7}b").is_err());
+    }
+}
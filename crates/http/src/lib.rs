@@ -15,23 +15,43 @@
 //! req.send_to(tcp).unwrap();
 //! ```
 
+pub mod cookie;
+pub use cookie::SetCookie;
+pub mod date;
 pub mod encoding;
-mod error;
+pub mod error;
+pub mod h2;
 pub use error::HttpError;
+pub mod headers;
+pub use headers::HeaderMap;
+mod limits;
+pub use limits::ParserLimits;
 mod method;
 pub use method::HttpMethod;
+pub mod multipart;
+pub mod negotiation;
+pub mod parser;
+pub mod query;
+pub use query::QueryParams;
 pub mod request;
 pub use request::HttpRequest;
 pub mod response;
 pub use response::HttpResponse;
+pub mod sse;
 mod status;
-pub use status::StatusCode;
+pub use status::{Status, StatusCode};
 mod stream;
 pub use stream::HttpStream;
+mod version;
+pub use version::HttpVersion;
+pub mod websocket;
 
 #[doc(hidden)]
 pub mod prelude {
-    pub use crate::{HttpError, HttpMethod, HttpRequest, HttpResponse, HttpStream, StatusCode};
+    pub use crate::{
+        HeaderMap, HttpError, HttpMethod, HttpRequest, HttpResponse, HttpStream, HttpVersion,
+        Status, StatusCode,
+    };
 }
 
 pub type Result<T> = std::result::Result<T, HttpError>;
@@ -0,0 +1,87 @@
+/// Limits applied while parsing HTTP messages
+///
+/// They protect against clients that send huge request lines or
+/// an endless stream of headers. The header limits also apply to the
+/// trailers of a chunked body. When a request exceeds one of them,
+/// parsing fails with an [`UriTooLong`](crate::error::ErrorKind::UriTooLong)
+/// or a [`HeaderFieldsTooLarge`](crate::error::ErrorKind::HeaderFieldsTooLarge) error.
+///
+/// They also limit how much of an unread body is
+/// [discarded](crate::HttpRequest::discard_body) to
+/// reach the next request of a persistent connection.
+///
+/// # Example
+/// ```
+/// use http::{HttpRequest, ParserLimits};
+///
+/// let limits = ParserLimits::default().max_request_line(16);
+/// let err = HttpRequest::parse_with_limits("GET /a/very/long/path HTTP/1.0\r\n\r\n", limits)
+///         .unwrap_err();
+/// assert_eq!(err.status(), 414);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// Max length of the request line, in bytes
+    pub max_request_line: usize,
+    /// Max length of a single header line, in bytes
+    pub max_header_line: usize,
+    /// Max number of headers
+    pub max_headers: usize,
+    /// Max size of the whole header section, in bytes
+    pub max_header_bytes: usize,
+    /// Max number of bytes of a request body that are discarded
+    /// if the handler doesn't read it
+    pub max_unread_body: usize,
+}
+
+impl ParserLimits {
+    #[inline]
+    #[must_use]
+    pub fn max_request_line(mut self, n: usize) -> Self {
+        self.max_request_line = n;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn max_header_line(mut self, n: usize) -> Self {
+        self.max_header_line = n;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn max_headers(mut self, n: usize) -> Self {
+        self.max_headers = n;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn max_header_bytes(mut self, n: usize) -> Self {
+        self.max_header_bytes = n;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn max_unread_body(mut self, n: usize) -> Self {
+        self.max_unread_body = n;
+        self
+    }
+}
+
+impl Default for ParserLimits {
+    /// Default limits
+    ///
+    /// - Request line: 8 KiB
+    /// - Header line: 8 KiB
+    /// - Nº Headers: 100
+    /// - Header section: 64 KiB
+    /// - Unread body: 64 KiB
+    fn default() -> Self {
+        Self {
+            max_request_line: 8 * 1024,
+            max_header_line: 8 * 1024,
+            max_headers: 100,
+            max_header_bytes: 64 * 1024,
+            max_unread_body: 64 * 1024,
+        }
+    }
+}
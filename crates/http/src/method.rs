@@ -1,6 +1,6 @@
 use std::{fmt::Display, str::FromStr};
 
-use crate::{HttpError, Result, err};
+use crate::{HttpError, Result, error::ErrorKind, headers::is_token};
 
 /// Request Method
 ///
@@ -31,7 +31,15 @@ impl FromStr for HttpMethod {
             "CONNECT" => Ok(Self::CONNECT),
             "OPTIONS" => Ok(Self::OPTIONS),
             "TRACE" => Ok(Self::TRACE),
-            _ => err!("Couldn't parse request method \"{t}\""),
+            _ => {
+                /* A well-formed, but unknown method is answered with 501 */
+                let kind = if is_token(t) {
+                    ErrorKind::NotImplemented
+                } else {
+                    ErrorKind::Parse
+                };
+                HttpError::with_kind(kind, format!("Couldn't parse request method \"{t}\"")).err()
+            }
         }
     }
 }
@@ -0,0 +1,420 @@
+//! Streaming `multipart/form-data` parser
+//!
+//! See [RFC 7578](https://www.rfc-editor.org/rfc/rfc7578)
+//!
+//! # Example
+//! ```
+//! use std::io::Read;
+//! use http::multipart::Multipart;
+//!
+//! let body = "--XYZ\r\n\
+//!             Content-Disposition: form-data; name=\"title\"\r\n\
+//!             \r\n\
+//!             My file\r\n\
+//!             --XYZ\r\n\
+//!             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+//!             Content-Type: text/plain\r\n\
+//!             \r\n\
+//!             Hello world!\r\n\
+//!             --XYZ--\r\n";
+//!
+//! let mut multipart = Multipart::new(body.as_bytes(), "XYZ");
+//!
+//! let mut part = multipart.next_part().unwrap().unwrap();
+//! assert_eq!(part.name(), Some("title"));
+//!
+//! let mut part = multipart.next_part().unwrap().unwrap();
+//! assert_eq!(part.filename(), Some("a.txt"));
+//! let mut content = String::new();
+//! part.read_to_string(&mut content).unwrap();
+//! assert_eq!(content, "Hello world!");
+//!
+//! assert!(multipart.next_part().unwrap().is_none());
+//! ```
+
+use std::io::{self, Read};
+
+use crate::{HeaderMap, ParserLimits, Result, err, headers::parse_header_line};
+
+const READ_SIZE: usize = 8 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Inside the preamble, or the body of a part
+    Body,
+    /// Just after a delimiter
+    Delimiter,
+    /// The closing delimiter has been read
+    Done,
+}
+
+/// A reader for a multipart body
+///
+/// It reads the parts one by one with [`next_part`](Self::next_part).
+/// Only a small buffer is kept in memory, so each [`Part`]
+/// must be read while it's being processed.
+pub struct Multipart<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    delimiter: Box<[u8]>,
+    state: State,
+    limits: ParserLimits,
+}
+
+impl<R: Read> Multipart<R> {
+    /// Creates a new multipart reader, with the given boundary
+    pub fn new(reader: R, boundary: &str) -> Self {
+        let delimiter = format!("\r\n--{boundary}").into_bytes().into_boxed_slice();
+        Self {
+            reader,
+            /* The first delimiter doesn't need to be preceded by a CRLF.
+             * Adding it here allows to treat it like the rest. */
+            buf: b"\r\n".to_vec(),
+            pos: 0,
+            eof: false,
+            delimiter,
+            state: State::Body,
+            limits: ParserLimits::default(),
+        }
+    }
+
+    /// Sets the [limits](ParserLimits) for the headers of each part
+    #[must_use]
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the next part of the body, or [None] if there are no more
+    ///
+    /// If the previous part wasn't read completely, the rest
+    /// of it is skipped.
+    ///
+    /// # Errors
+    /// - If the reader fails
+    /// - If the body is malformed
+    pub fn next_part(&mut self) -> Result<Option<Part<'_, R>>> {
+        loop {
+            match self.state {
+                State::Body => {
+                    io::copy(&mut BodyRead(self), &mut io::sink())?;
+                }
+                State::Delimiter => break,
+                State::Done => return Ok(None),
+            }
+        }
+        let line = self.read_line()?;
+        if line.starts_with("--") {
+            self.state = State::Done;
+            return Ok(None);
+        }
+        if !line.trim_matches([' ', '\t']).is_empty() {
+            return err!(kind = Parse, "Unexpected data after multipart boundary");
+        }
+        let headers = self.read_headers()?;
+        let disposition = headers.get("Content-Disposition").unwrap_or("");
+        let name = header_param(disposition, "name");
+        let filename = header_param(disposition, "filename");
+        self.state = State::Body;
+        Ok(Some(Part {
+            multipart: self,
+            headers,
+            name,
+            filename,
+        }))
+    }
+
+    /// Reads from the underlying reader until `min` bytes are
+    /// available, or EOF is reached
+    fn fill(&mut self, min: usize) -> io::Result<()> {
+        if self.buf.len() - self.pos >= min {
+            return Ok(());
+        }
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        while !self.eof && self.buf.len() < min {
+            let len = self.buf.len();
+            self.buf.resize(len + READ_SIZE, 0);
+            let n = match self.reader.read(&mut self.buf[len..]) {
+                Ok(n) => n,
+                Err(err) => {
+                    self.buf.truncate(len);
+                    if err.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(err);
+                }
+            };
+            self.buf.truncate(len + n);
+            self.eof = n == 0;
+        }
+        Ok(())
+    }
+
+    /// Reads the body of the current part, stopping at the next delimiter
+    fn read_body(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.state != State::Body || out.is_empty() {
+            return Ok(0);
+        }
+        let dlen = self.delimiter.len();
+        self.fill(dlen)?;
+        let avail = &self.buf[self.pos..];
+        let n = match avail.windows(dlen).position(|w| *w == *self.delimiter) {
+            Some(0) => {
+                self.pos += dlen;
+                self.state = State::Delimiter;
+                return Ok(0);
+            }
+            Some(n) => n,
+            None if self.eof => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Multipart body ended without a closing boundary",
+                ));
+            }
+            /* The end of the buffer may contain the start of a delimiter */
+            None => avail.len() + 1 - dlen,
+        };
+        let n = n.min(out.len());
+        out[..n].copy_from_slice(&avail[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let max = self.limits.max_header_line;
+        loop {
+            let avail = &self.buf[self.pos..];
+            if let Some(i) = avail.iter().position(|&b| b == b'\n') {
+                let line = avail[..i].strip_suffix(b"\r").unwrap_or(&avail[..i]);
+                if line.len() > max {
+                    break;
+                }
+                let line = str::from_utf8(line)?.to_string();
+                self.pos += i + 1;
+                return Ok(line);
+            }
+            let len = avail.len();
+            if len > max + 1 {
+                break;
+            }
+            if self.eof {
+                return err!(kind = Parse, "Unexpected end of multipart body");
+            }
+            self.fill(len + 1)?;
+        }
+        err!(
+            kind = HeaderFieldsTooLarge,
+            "Multipart header line exceeds the limit of {max}"
+        )
+    }
+
+    fn read_headers(&mut self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        let mut total = 0;
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                break;
+            }
+            total += line.len() + 2;
+            if total > self.limits.max_header_bytes || headers.len() >= self.limits.max_headers {
+                return err!(
+                    kind = HeaderFieldsTooLarge,
+                    "Multipart headers are too large"
+                );
+            }
+            let (key, value) = parse_header_line(&line)?;
+            headers.append(key, value);
+        }
+        Ok(headers)
+    }
+}
+
+/// [Read] adapter over the body of the current part
+struct BodyRead<'a, R: Read>(&'a mut Multipart<R>);
+
+impl<R: Read> Read for BodyRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read_body(buf)
+    }
+}
+
+/// A part of a [`Multipart`] body
+///
+/// It implements [Read], to get the content of the part.
+pub struct Part<'a, R: Read> {
+    multipart: &'a mut Multipart<R>,
+    headers: HeaderMap,
+    name: Option<Box<str>>,
+    filename: Option<Box<str>>,
+}
+
+impl<R: Read> Part<'_, R> {
+    /// Headers of this part
+    #[inline]
+    #[must_use]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Name of the form field, from the *Content-Disposition* header
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Filename of the part, from the *Content-Disposition* header
+    ///
+    /// # NOTE
+    /// This value is sent by the client, so it can't be trusted
+    /// to be a safe path.
+    #[inline]
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content type of this part, if present
+    #[inline]
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("Content-Type")
+    }
+}
+
+impl<R: Read> Read for Part<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.multipart.read_body(buf)
+    }
+}
+
+/// Gets the value of a parameter in a header like
+/// *Content-Type* or *Content-Disposition*
+///
+/// ```text
+/// form-data; name="file"; filename="a.txt"
+/// ```
+///
+/// Quoted values are unescaped. The parameter name is case-insensitive.
+#[must_use]
+pub fn header_param(value: &str, key: &str) -> Option<Box<str>> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (name, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let (param, next) = if let Some(quoted) = after.strip_prefix('"') {
+            let mut param = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => param.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => param.push(c),
+                }
+            }
+            let next = quoted[end..].split_once(';').map_or("", |(_, next)| next);
+            (param, next)
+        } else {
+            let (param, next) = after.split_once(';').unwrap_or((after, ""));
+            (param.trim_end().to_string(), next)
+        };
+        if name.trim().eq_ignore_ascii_case(key) {
+            return Some(param.into_boxed_str());
+        }
+        if next.is_empty() {
+            return None;
+        }
+        rest = next;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Read;
+
+    use super::{Multipart, header_param};
+
+    /// A reader that returns at most `n` bytes on each read
+    struct Slow<'a>(&'a [u8], usize);
+
+    impl Read for Slow<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --b0undary\r\n\
+        Content-Disposition: form-data; name=\"a\"\r\n\
+        \r\n\
+        value with --b0und in it\r\n\
+        --b0undary   \r\n\
+        Content-Disposition: form-data; name=\"f\"; filename=\"x \\\"y\\\".bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n\
+        \r\n--b0undar\r\r\n\
+        --b0undary--\r\n\
+        epilogue";
+
+    #[test]
+    fn parts() {
+        for step in [1, 2, 5, 13, BODY.len()] {
+            let mut multipart = Multipart::new(Slow(BODY, step), "b0undary");
+
+            let mut part = multipart.next_part().unwrap().unwrap();
+            assert_eq!(part.name(), Some("a"));
+            assert_eq!(part.filename(), None);
+            let mut content = String::new();
+            part.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "value with --b0und in it");
+
+            let mut part = multipart.next_part().unwrap().unwrap();
+            assert_eq!(part.name(), Some("f"));
+            assert_eq!(part.filename(), Some("x \"y\".bin"));
+            assert_eq!(part.content_type(), Some("application/octet-stream"));
+            let mut content = Vec::new();
+            part.read_to_end(&mut content).unwrap();
+            assert_eq!(content, b"\r\n--b0undar\r");
+
+            assert!(multipart.next_part().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn skip_unread() {
+        let mut multipart = Multipart::new(BODY, "b0undary");
+        multipart.next_part().unwrap().unwrap();
+        let part = multipart.next_part().unwrap().unwrap();
+        assert_eq!(part.name(), Some("f"));
+    }
+
+    #[test]
+    fn missing_close() {
+        let body = b"--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nabc";
+        let mut multipart = Multipart::new(&body[..], "b");
+        let mut part = multipart.next_part().unwrap().unwrap();
+        assert!(part.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn params() {
+        let ct = "multipart/form-data; charset=utf-8; BOUNDARY=\"a;b\"";
+        assert_eq!(header_param(ct, "boundary").as_deref(), Some("a;b"));
+        assert_eq!(header_param(ct, "charset").as_deref(), Some("utf-8"));
+        assert_eq!(header_param(ct, "name"), None);
+        assert_eq!(header_param("form-data", "name"), None);
+    }
+}
@@ -0,0 +1,368 @@
+//! Content negotiation
+//!
+//! Functions to choose the best of the available representations
+//! of a resource, based on the *Accept*, *Accept-Language*,
+//! *Accept-Charset* and *Accept-Encoding* headers of a request.
+//!
+//! All the functions take the value of the header (or [None] if
+//! the request didn't send it) and the representations that the
+//! server offers, in order of preference. When several of them
+//! are equally acceptable, the first one is chosen.
+//!
+//! See [RFC 9110, Section 12](https://www.rfc-editor.org/rfc/rfc9110#section-12)
+//!
+//! # Example
+//! ```
+//! use http::negotiation::{Mime, media_type};
+//!
+//! let offered = [
+//!     Mime::new("application/json").unwrap(),
+//!     Mime::new("text/html").unwrap(),
+//! ];
+//! let accept = "text/*, application/json;q=0.5";
+//! let best = media_type(Some(accept), &offered).unwrap();
+//! assert_eq!(best.to_string(), "text/html");
+//! ```
+
+pub use mime::Mime;
+
+use crate::{Result, err};
+
+/// An element of a header with quality values
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QualityItem<'a> {
+    /// Value of the element, without its parameters
+    pub value: &'a str,
+    /// Parameters that come before the "q" parameter, if any
+    pub params: &'a str,
+    /// Weight of the element, in thousandths (0 to 1000)
+    pub quality: u16,
+}
+
+/// Parses a comma-separated list of elements with optional quality values
+///
+/// Malformed elements are skipped. Elements without a "q"
+/// parameter have a quality of 1000.
+///
+/// # Example
+/// ```
+/// use http::negotiation::parse_quality_list;
+///
+/// let list = parse_quality_list("da, en-gb;q=0.8, en;q=0.7");
+/// assert_eq!(list.len(), 3);
+/// assert_eq!(list[1].value, "en-gb");
+/// assert_eq!(list[1].quality, 800);
+/// ```
+#[must_use]
+pub fn parse_quality_list(header: &str) -> Vec<QualityItem<'_>> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let (value, rest) = item.split_once(';').unwrap_or((item, ""));
+            let value = value.trim();
+            if value.is_empty() {
+                return None;
+            }
+            let mut params = rest;
+            let mut quality = 1000;
+            let mut pos = 0;
+            for param in rest.split(';') {
+                if let Some((k, v)) = param.split_once('=')
+                    && k.trim().eq_ignore_ascii_case("q")
+                {
+                    quality = parse_qvalue(v.trim())?;
+                    params = &rest[..pos.min(rest.len())];
+                    break;
+                }
+                pos += param.len() + 1;
+            }
+            Some(QualityItem {
+                value,
+                params: params.trim().trim_end_matches(';').trim(),
+                quality,
+            })
+        })
+        .collect()
+}
+
+/// Parses a qvalue (`0[.ddd]` or `1[.000]`) into thousandths
+fn parse_qvalue(q: &str) -> Option<u16> {
+    let (int, frac) = q.split_once('.').unwrap_or((q, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac: u16 = format!("{frac:0<3}").parse().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/// Returns the first offer with the highest quality, if any is acceptable
+fn best<'o, T>(
+    offered: &'o [T],
+    mut quality_of: impl FnMut(&T) -> u16,
+    header: &str,
+) -> Result<&'o T> {
+    let mut best: Option<(&T, u16)> = None;
+    for offer in offered {
+        let q = quality_of(offer);
+        if q > 0 && best.is_none_or(|(_, bq)| q > bq) {
+            best = Some((offer, q));
+        }
+    }
+    match best {
+        Some((offer, _)) => Ok(offer),
+        None => err!(
+            kind = NotAcceptable,
+            "None of the available representations matches \"{header}\""
+        ),
+    }
+}
+
+/// Finds the media type that best matches an *Accept* header
+///
+/// A more specific media range takes precedence over a less
+/// specific one, so `text/html;q=0, text/*` accepts any text
+/// type except html. Media ranges with parameters other than
+/// "q" are not supported by [`Mime`], and are ignored.
+///
+/// If the header is [None], every type is acceptable.
+///
+/// # Errors
+/// If none of the offered types is acceptable.
+/// The error has kind [`NotAcceptable`](crate::error::ErrorKind::NotAcceptable)
+pub fn media_type<'o, 'm>(accept: Option<&str>, offered: &'o [Mime<'m>]) -> Result<&'o Mime<'m>> {
+    let Some(accept) = accept else {
+        return best(offered, |_| 1000, "");
+    };
+    let ranges: Vec<_> = parse_quality_list(accept)
+        .into_iter()
+        .filter(|item| item.params.is_empty())
+        .filter_map(|item| Some((Mime::new(item.value).ok()?, item.quality)))
+        .collect();
+    let quality_of = |offer: &Mime<'_>| {
+        ranges
+            .iter()
+            .filter_map(|(range, q)| {
+                let specificity = match (range.major(), range.minor()) {
+                    ("*", "*") => 0,
+                    (major, "*") if major.eq_ignore_ascii_case(offer.major()) => 1,
+                    (major, minor)
+                        if major.eq_ignore_ascii_case(offer.major())
+                            && minor.eq_ignore_ascii_case(offer.minor()) =>
+                    {
+                        2
+                    }
+                    _ => return None,
+                };
+                Some((specificity, *q))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0, |(_, q)| q)
+    };
+    best(offered, quality_of, accept)
+}
+
+/// Finds the language tag that best matches an *Accept-Language* header
+///
+/// Language ranges match using the "Basic Filtering" scheme
+/// of [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647#section-3.3.1):
+/// `en` matches both `en` and `en-US`. The longest matching range
+/// takes precedence.
+///
+/// If the header is [None], every language is acceptable.
+///
+/// # Errors
+/// If none of the offered languages is acceptable.
+/// The error has kind [`NotAcceptable`](crate::error::ErrorKind::NotAcceptable)
+///
+/// # Example
+/// ```
+/// use http::negotiation::language;
+///
+/// let best = language(Some("fr-CH, fr;q=0.9, en;q=0.8"), &["en-US", "fr"]).unwrap();
+/// assert_eq!(best, "fr");
+/// ```
+pub fn language<'o>(accept_language: Option<&str>, offered: &[&'o str]) -> Result<&'o str> {
+    let Some(header) = accept_language else {
+        return best(offered, |_| 1000, "").copied();
+    };
+    let ranges = parse_quality_list(header);
+    let quality_of = |offer: &&str| {
+        ranges
+            .iter()
+            .filter(|item| {
+                item.value == "*"
+                    || offer
+                        .get(..item.value.len())
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(item.value))
+                        && matches!(offer.as_bytes().get(item.value.len()), None | Some(b'-'))
+            })
+            .max_by_key(|item| {
+                if item.value == "*" {
+                    0
+                } else {
+                    item.value.len()
+                }
+            })
+            .map_or(0, |item| item.quality)
+    };
+    best(offered, quality_of, header).copied()
+}
+
+/// Finds the quality of a token in a list where "*" matches any token
+fn token_quality(items: &[QualityItem<'_>], token: &str) -> Option<u16> {
+    items
+        .iter()
+        .find(|item| item.value.eq_ignore_ascii_case(token))
+        .or_else(|| items.iter().find(|item| item.value == "*"))
+        .map(|item| item.quality)
+}
+
+/// Finds the charset that best matches an *Accept-Charset* header
+///
+/// If the header is [None], every charset is acceptable.
+///
+/// # Errors
+/// If none of the offered charsets is acceptable.
+/// The error has kind [`NotAcceptable`](crate::error::ErrorKind::NotAcceptable)
+pub fn charset<'o>(accept_charset: Option<&str>, offered: &[&'o str]) -> Result<&'o str> {
+    let Some(header) = accept_charset else {
+        return best(offered, |_| 1000, "").copied();
+    };
+    let items = parse_quality_list(header);
+    best(
+        offered,
+        |offer| token_quality(&items, offer).unwrap_or(0),
+        header,
+    )
+    .copied()
+}
+
+/// Finds the content coding that best matches an *Accept-Encoding* header
+///
+/// The "identity" coding (no encoding) is always acceptable,
+/// unless the header explicitly excludes it, with `identity;q=0`
+/// or `*;q=0`.
+///
+/// If the header is [None], every coding is acceptable.
+///
+/// # Errors
+/// If none of the offered codings is acceptable.
+/// The error has kind [`NotAcceptable`](crate::error::ErrorKind::NotAcceptable)
+///
+/// # Example
+/// ```
+/// use http::negotiation::encoding;
+///
+/// let offered = ["br", "gzip", "identity"];
+/// assert_eq!(encoding(Some("gzip, deflate"), &offered).unwrap(), "gzip");
+/// assert_eq!(encoding(Some(""), &offered).unwrap(), "identity");
+/// ```
+pub fn encoding<'o>(accept_encoding: Option<&str>, offered: &[&'o str]) -> Result<&'o str> {
+    let Some(header) = accept_encoding else {
+        return best(offered, |_| 1000, "").copied();
+    };
+    let items = parse_quality_list(header);
+    let quality_of = |offer: &&str| match token_quality(&items, offer) {
+        Some(q) => q,
+        /* Identity is acceptable unless excluded. Give it the
+         * lowest weight, so it's only chosen as a fallback */
+        None if offer.eq_ignore_ascii_case("identity") => 1,
+        None => 0,
+    };
+    best(offered, quality_of, header).copied()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::{Mime, charset, encoding, language, media_type, parse_quality_list};
+    use crate::error::ErrorKind;
+
+    fn mimes(types: &[&'static str]) -> Vec<Mime<'static>> {
+        types.iter().map(|t| Mime::new(*t).unwrap()).collect()
+    }
+
+    #[test]
+    fn quality_list() {
+        let list = parse_quality_list("text/html;level=1;q=0.5, , */*;Q=0, a;q=2, b;q=0.1234");
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].value, "text/html");
+        assert_eq!(list[0].params, "level=1");
+        assert_eq!(list[0].quality, 500);
+        assert_eq!(list[1].value, "*/*");
+        assert_eq!(list[1].quality, 0);
+    }
+
+    #[test]
+    fn media_specificity() {
+        let offered = mimes(&["text/html", "text/plain", "image/png"]);
+        let accept = "text/html;q=0, text/*;q=0.5, */*;q=0.1";
+        assert_eq!(media_type(Some(accept), &offered).unwrap().minor(), "plain");
+
+        let accept = "image/*, text/*;q=0.9";
+        assert_eq!(media_type(Some(accept), &offered).unwrap().minor(), "png");
+    }
+
+    #[test]
+    fn media_ties_keep_server_order() {
+        let offered = mimes(&["application/json", "text/html"]);
+        assert_eq!(media_type(Some("*/*"), &offered).unwrap().minor(), "json");
+        assert_eq!(media_type(None, &offered).unwrap().minor(), "json");
+    }
+
+    #[test]
+    fn not_acceptable() {
+        let offered = mimes(&["text/html"]);
+        let err = media_type(Some("application/json"), &offered).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotAcceptable);
+        assert_eq!(err.status(), 406);
+
+        let err = media_type(Some("*/*;q=0"), &offered).unwrap_err();
+        assert_eq!(err.status(), 406);
+    }
+
+    #[test]
+    fn languages() {
+        let offered = ["en-US", "es", "de"];
+        assert_eq!(
+            language(Some("es-ES, en;q=0.5"), &offered).unwrap(),
+            "en-US"
+        );
+        assert_eq!(language(Some("*;q=0.1, EN"), &offered).unwrap(), "en-US");
+        assert_eq!(language(Some("*, en;q=0"), &offered).unwrap(), "es");
+        assert!(language(Some("e"), &offered).is_err());
+    }
+
+    #[test]
+    fn charsets() {
+        let offered = ["utf-8", "iso-8859-1"];
+        assert_eq!(
+            charset(Some("ISO-8859-1, utf-8;q=0.5"), &offered).unwrap(),
+            "iso-8859-1"
+        );
+        assert_eq!(
+            charset(Some("*;q=0.2, utf-8;q=0"), &offered).unwrap(),
+            "iso-8859-1"
+        );
+        assert!(charset(Some("ascii"), &offered).is_err());
+    }
+
+    #[test]
+    fn encodings() {
+        let offered = ["br", "gzip", "identity"];
+        assert_eq!(encoding(Some("gzip;q=0.5, br"), &offered).unwrap(), "br");
+        assert_eq!(encoding(Some("deflate"), &offered).unwrap(), "identity");
+        assert_eq!(encoding(Some("*"), &offered).unwrap(), "br");
+        assert!(encoding(Some("identity;q=0"), &["identity"]).is_err());
+        assert!(encoding(Some("*;q=0"), &["gzip", "identity"]).is_err());
+        assert_eq!(
+            encoding(Some("*;q=0, identity"), &offered).unwrap(),
+            "identity"
+        );
+    }
+}
@@ -0,0 +1,661 @@
+//! Push-based HTTP/1.x parser
+//!
+//! [`Parser`] is a state machine that doesn't perform any IO.
+//! It's fed byte slices, and emits [events](Event) as it recognizes
+//! the parts of a message. This way, it can be driven from a
+//! non-blocking event loop, or from any buffer.
+//!
+//! # Example
+//! ```
+//! use http::parser::{Event, Head, Parser};
+//!
+//! let input = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nHello";
+//! let mut parser = Parser::request();
+//!
+//! let mut pos = 0;
+//! let mut body = Vec::new();
+//! loop {
+//!     let (n, event) = parser.parse(&input[pos..]).unwrap();
+//!     pos += n;
+//!     match event {
+//!         Some(Event::Head(Head::Request(head))) => assert_eq!(&*head.url, "/upload"),
+//!         Some(Event::Data(data)) => body.extend_from_slice(data),
+//!         Some(Event::End(_)) => break,
+//!         Some(Event::Head(Head::Response(_))) => unreachable!(),
+//!         /* A line was consumed, but no event is ready yet */
+//!         None => {}
+//!     }
+//! }
+//! assert_eq!(body, b"Hello");
+//! ```
+
+use core::mem;
+use std::io::{self, BufRead};
+
+use crate::{
+    HeaderMap, HttpError, HttpMethod, HttpVersion, ParserLimits, QueryParams, Result, Status,
+    encoding::BodyKind, err, error::ErrorKind, headers::parse_header_line,
+};
+
+/// Head of a request: it's request line and headers
+#[derive(Debug)]
+pub struct RequestHead {
+    pub method: HttpMethod,
+    pub url: Box<str>,
+    pub params: QueryParams,
+    pub version: HttpVersion,
+    pub headers: HeaderMap,
+}
+
+/// Head of a response: it's status line and headers
+#[derive(Debug)]
+pub struct ResponseHead {
+    pub version: HttpVersion,
+    pub status: Status,
+    pub headers: HeaderMap,
+}
+
+/// Head of a message
+#[derive(Debug)]
+pub enum Head {
+    Request(RequestHead),
+    Response(ResponseHead),
+}
+
+/// An event emitted by the [`Parser`]
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The head of the message has been parsed
+    Head(Head),
+    /// A piece of the message's body.
+    ///
+    /// The data is already decoded from the chunked transfer coding.
+    Data(&'a [u8]),
+    /// The message is complete. Contains the trailers, if
+    /// the body was chunked, or an empty map otherwise.
+    End(HeaderMap),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MessageKind {
+    Request,
+    Response,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Waiting for the request or status line
+    StartLine,
+    /// Reading the header section
+    Headers,
+    /// Inside a body with the given number of bytes left
+    Length(u64),
+    /// Inside a body that ends when the input ends
+    Close,
+    /// Waiting for a chunk-size line
+    ChunkSize,
+    /// Inside a chunk, with the given number of bytes left
+    ChunkData(u64),
+    /// Waiting for the CRLF after the chunk's data
+    ChunkDataEnd,
+    /// Reading the trailer section
+    Trailers,
+}
+
+enum StartLine {
+    Request {
+        method: HttpMethod,
+        url: Box<str>,
+        params: QueryParams,
+        version: HttpVersion,
+    },
+    Response {
+        version: HttpVersion,
+        status: Status,
+    },
+}
+
+/// Push-based HTTP/1.x message parser
+///
+/// The parser is fed with [`parse`](Self::parse), which consumes a
+/// prefix of the given slice and returns, at most, one [`Event`].
+/// When it returns [None] and consumes the whole slice, it needs more input.
+///
+/// After a message ends, the parser is ready to parse the next one
+/// on the same connection.
+pub struct Parser {
+    kind: MessageKind,
+    limits: ParserLimits,
+    state: State,
+    line: Vec<u8>,
+    start: Option<StartLine>,
+    fields: HeaderMap,
+    field_bytes: usize,
+    extensions: Vec<(Box<str>, Box<str>)>,
+}
+
+impl Parser {
+    /// Creates a parser for requests
+    #[must_use]
+    pub fn request() -> Self {
+        Self::new(MessageKind::Request)
+    }
+
+    /// Creates a parser for responses
+    #[must_use]
+    pub fn response() -> Self {
+        Self::new(MessageKind::Response)
+    }
+
+    /// Creates a parser for a chunked body, whose head was already parsed
+    ///
+    /// It emits the [data](Event::Data) of the chunks, and an [`Event::End`]
+    /// with the trailers. After that, it's [idle](Self::is_idle).
+    #[must_use]
+    pub fn chunked_body() -> Self {
+        let mut parser = Self::new(MessageKind::Request);
+        parser.state = State::ChunkSize;
+        parser
+    }
+
+    fn new(kind: MessageKind) -> Self {
+        Self {
+            kind,
+            limits: ParserLimits::default(),
+            state: State::StartLine,
+            line: Vec::new(),
+            start: None,
+            fields: HeaderMap::new(),
+            field_bytes: 0,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Sets the [limits](ParserLimits) of this parser
+    #[must_use]
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns true if the parser is between two messages
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        self.state == State::StartLine && self.line.is_empty()
+    }
+
+    /// Returns the extensions of the last chunk-size line
+    ///
+    /// Extensions without a value are returned with an empty string.
+    #[must_use]
+    pub fn chunk_extensions(&self) -> &[(Box<str>, Box<str>)] {
+        &self.extensions
+    }
+
+    /// Skips the body of the message whose head was just parsed
+    ///
+    /// A response can't tell by itself if it has a body in some cases,
+    /// like responses to a HEAD request. In those cases, this function
+    /// must be called after the [`Head`] event.
+    pub fn skip_body(&mut self) {
+        if matches!(
+            self.state,
+            State::Length(_) | State::Close | State::ChunkSize
+        ) {
+            self.state = State::Length(0);
+        }
+    }
+
+    /// Parses the given input
+    ///
+    /// Returns the number of bytes consumed, and the event produced, if any.
+    /// The unconsumed part of the input must be passed again on the next call.
+    ///
+    /// # Errors
+    /// If the message is malformed, or it exceeds the parser's [limits](ParserLimits)
+    pub fn parse<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        match self.state {
+            State::Length(0) => {
+                self.state = State::StartLine;
+                Ok((0, Some(Event::End(HeaderMap::new()))))
+            }
+            State::Length(left) | State::ChunkData(left) => {
+                if input.is_empty() {
+                    return Ok((0, None));
+                }
+                let n = usize::try_from(left).map_or(input.len(), |left| left.min(input.len()));
+                let left = left - n as u64;
+                self.state = match self.state {
+                    State::ChunkData(_) if left == 0 => State::ChunkDataEnd,
+                    State::ChunkData(_) => State::ChunkData(left),
+                    _ => State::Length(left),
+                };
+                Ok((n, Some(Event::Data(&input[..n]))))
+            }
+            State::Close => {
+                if input.is_empty() {
+                    Ok((0, None))
+                } else {
+                    Ok((input.len(), Some(Event::Data(input))))
+                }
+            }
+            _ => match self.read_line(input)? {
+                Some((n, line)) => Ok((n, self.on_line(line)?)),
+                None => Ok((input.len(), None)),
+            },
+        }
+    }
+
+    /// Signals the end of the input
+    ///
+    /// Like [`parse`](Self::parse), it returns one event at a time,
+    /// so it must be called until it returns [None].
+    ///
+    /// A body that ends with the connection is completed here.
+    ///
+    /// # Errors
+    /// If the input ended in the middle of a message, including
+    /// a head that wasn't terminated by an empty line
+    pub fn finish(&mut self) -> Result<Option<Event<'static>>> {
+        match self.state {
+            State::StartLine if self.line.is_empty() => Ok(None),
+            State::Length(0) | State::Close => {
+                self.state = State::StartLine;
+                Ok(Some(Event::End(HeaderMap::new())))
+            }
+            _ => err!(kind = Parse, "Unexpected end of message"),
+        }
+    }
+
+    /// Reads from `reader` until the head of a message is parsed
+    ///
+    /// Only the bytes of the head are consumed, so the body can
+    /// then be read from the same reader. The body of a previous
+    /// message, if any, is discarded.
+    ///
+    /// # Errors
+    /// - If the message is malformed
+    /// - If the reader fails, or reaches EOF before a head is found
+    pub fn read_head(&mut self, reader: &mut impl BufRead) -> Result<Head> {
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                match self.finish()? {
+                    Some(Event::Head(head)) => return Ok(head),
+                    Some(_) => continue,
+                    None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                }
+            }
+            let (n, event) = self.parse(buf)?;
+            let head = match event {
+                Some(Event::Head(head)) => Some(head),
+                _ => None,
+            };
+            reader.consume(n);
+            if let Some(head) = head {
+                return Ok(head);
+            }
+        }
+    }
+
+    /// Takes a line from the input, buffering it if it's incomplete
+    fn read_line(&mut self, input: &[u8]) -> Result<Option<(usize, Vec<u8>)>> {
+        let max = match self.state {
+            State::StartLine => self.limits.max_request_line,
+            _ => self.limits.max_header_line,
+        };
+        let Some(pos) = input.iter().position(|&b| b == b'\n') else {
+            self.line.extend_from_slice(input);
+            if self.line.len() > max.saturating_add(1) {
+                return Err(self.line_too_long(max));
+            }
+            return Ok(None);
+        };
+        self.line.extend_from_slice(&input[..pos]);
+        let mut line = mem::take(&mut self.line);
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.len() > max {
+            return Err(self.line_too_long(max));
+        }
+        Ok(Some((pos + 1, line)))
+    }
+
+    fn line_too_long(&self, max: usize) -> HttpError {
+        let (kind, what) = match (self.state, self.kind) {
+            (State::StartLine, MessageKind::Request) => (ErrorKind::UriTooLong, "Request line"),
+            (State::StartLine, MessageKind::Response) => (ErrorKind::Parse, "Status line"),
+            (State::Headers | State::Trailers, _) => {
+                (ErrorKind::HeaderFieldsTooLarge, "Header line")
+            }
+            _ => (ErrorKind::Parse, "Chunk line"),
+        };
+        HttpError::with_kind(kind, format!("{what} exceeds the limit of {max}"))
+    }
+
+    fn on_line(&mut self, line: Vec<u8>) -> Result<Option<Event<'static>>> {
+        let line = String::from_utf8(line)?;
+        match self.state {
+            State::StartLine => {
+                /* RFC 9112 Section 2.2: A server that is expecting to receive
+                 * and parse a request-line SHOULD ignore at least one empty line
+                 * (CRLF) received prior to the request-line. */
+                if !line.is_empty() {
+                    self.start = Some(match self.kind {
+                        MessageKind::Request => parse_request_line(&line)?,
+                        MessageKind::Response => parse_status_line(&line)?,
+                    });
+                    self.state = State::Headers;
+                }
+            }
+            State::Headers => {
+                if line.is_empty() {
+                    return self.end_head().map(Some);
+                }
+                self.add_field(&line)?;
+            }
+            State::ChunkSize => {
+                let size = parse_chunk_size(&line, &mut self.extensions)?;
+                self.state = if size == 0 {
+                    State::Trailers
+                } else {
+                    State::ChunkData(size)
+                };
+            }
+            State::ChunkDataEnd => {
+                if !line.is_empty() {
+                    return err!(kind = Parse, "Expected CRLF after chunk data");
+                }
+                self.state = State::ChunkSize;
+            }
+            State::Trailers => {
+                if line.is_empty() {
+                    self.state = State::StartLine;
+                    self.field_bytes = 0;
+                    return Ok(Some(Event::End(mem::take(&mut self.fields))));
+                }
+                self.add_field(&line)?;
+            }
+            State::Length(_) | State::ChunkData(_) | State::Close => {}
+        }
+        Ok(None)
+    }
+
+    fn add_field(&mut self, line: &str) -> Result<()> {
+        let too_large = |what: &str, max: usize| {
+            HttpError::with_kind(
+                ErrorKind::HeaderFieldsTooLarge,
+                format!("{what} exceeds the limit of {max}"),
+            )
+        };
+        self.field_bytes += line.len() + 2;
+        if self.field_bytes > self.limits.max_header_bytes {
+            return Err(too_large("Header section", self.limits.max_header_bytes));
+        }
+        if self.fields.len() >= self.limits.max_headers {
+            return Err(too_large("Number of headers", self.limits.max_headers));
+        }
+        if line.starts_with([' ', '\t']) {
+            return err!(kind = Parse, "Obsolete line folding is not supported");
+        }
+        let (key, value) = parse_header_line(line)?;
+        self.fields.append(key, value);
+        Ok(())
+    }
+
+    fn end_head(&mut self) -> Result<Event<'static>> {
+        let headers = mem::take(&mut self.fields);
+        self.field_bytes = 0;
+        let Some(start) = self.start.take() else {
+            return err!(kind = Parse, "Missing start line");
+        };
+        let (head, body) = match start {
+            StartLine::Request {
+                method,
+                url,
+                params,
+                version,
+            } => {
+                /* RFC 9112 Section 3.2: A server MUST respond with a 400 status code to any
+                 * HTTP/1.1 request message that lacks a Host header field and to any request
+                 * message that contains more than one Host header field */
+                let hosts = headers.get_all("Host").count();
+                if hosts > 1 || (hosts == 0 && version >= HttpVersion::Http11) {
+                    return err!(
+                        kind = Parse,
+                        "Expected exactly one Host header, found {hosts}"
+                    );
+                }
+                let body = BodyKind::of_request(&headers)?;
+                let head = RequestHead {
+                    method,
+                    url,
+                    params,
+                    version,
+                    headers,
+                };
+                (Head::Request(head), body)
+            }
+            StartLine::Response { version, status } => {
+                let body = BodyKind::of_response(status, &headers)?;
+                let head = ResponseHead {
+                    version,
+                    status,
+                    headers,
+                };
+                (Head::Response(head), body)
+            }
+        };
+        self.state = match body {
+            BodyKind::Empty => State::Length(0),
+            BodyKind::Length(len) => State::Length(len),
+            BodyKind::Chunked => State::ChunkSize,
+            BodyKind::Close => State::Close,
+        };
+        Ok(Event::Head(head))
+    }
+}
+
+fn parse_request_line(line: &str) -> Result<StartLine> {
+    let mut space = line.split_whitespace().take(3);
+    let method = space.next().unwrap_or("").parse()?;
+    let (url, params) = parse_target(space.next().unwrap_or(""))?;
+    let version: HttpVersion = space.next().unwrap_or("").parse()?;
+    if !matches!(version, HttpVersion::Http10 | HttpVersion::Http11) {
+        return HttpError::with_kind(
+            ErrorKind::NotImplemented,
+            format!("{version} is not supported"),
+        )
+        .with_status(Status::HTTP_VERSION_NOT_SUPPORTED)
+        .err();
+    }
+    Ok(StartLine::Request {
+        method,
+        url,
+        params,
+        version,
+    })
+}
+
+/// Splits a request target into it's decoded path and query
+pub(crate) fn parse_target(target: &str) -> Result<(Box<str>, QueryParams)> {
+    let (url, params) = match target.split_once('?') {
+        Some((url, query)) => (url, QueryParams::parse(query)?),
+        None => (target, QueryParams::new()),
+    };
+    Ok((url::decode(url)?.into(), params))
+}
+
+fn parse_status_line(line: &str) -> Result<StartLine> {
+    let mut space = line.split_whitespace().take(2);
+    let version = space.next().unwrap_or("").parse()?;
+    let status = space.next().unwrap_or("").parse()?;
+    Ok(StartLine::Response { version, status })
+}
+
+/// Parses a chunk-size line, with it's optional extensions.
+///
+/// ```text
+/// chunk-size [ *( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] ) ]
+/// ```
+fn parse_chunk_size(line: &str, extensions: &mut Vec<(Box<str>, Box<str>)>) -> Result<u64> {
+    let mut parts = line.split(';');
+    let size = parts.next().unwrap_or("").trim();
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return err!(kind = Parse, "Invalid chunk size");
+    }
+    let size =
+        u64::from_str_radix(size, 16).or_else(|_| err!(kind = Parse, "Chunk size too big"))?;
+
+    extensions.clear();
+    for ext in parts {
+        let mut ext = ext.splitn(2, '=');
+        let name = ext.next().unwrap_or("").trim();
+        if name.is_empty() {
+            return err!(kind = Parse, "Empty chunk extension");
+        }
+        let value = ext.next().unwrap_or("").trim().trim_matches('"');
+        extensions.push((name.into(), value.into()));
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::{Event, Head, Parser};
+    use crate::{HeaderMap, ParserLimits};
+
+    /// Feeds the input in slices of `step` bytes, and returns the
+    /// heads, the body of each message, and it's trailers
+    fn run(mut parser: Parser, input: &[u8], step: usize) -> Vec<(Head, Vec<u8>, HeaderMap)> {
+        let mut messages = Vec::new();
+        let mut pending = Vec::new();
+        let mut head = None;
+        let mut body = Vec::new();
+        let mut handle = |event: Event<'_>| match event {
+            Event::Head(h) => head = Some(h),
+            Event::Data(data) => body.extend_from_slice(data),
+            Event::End(trailers) => {
+                messages.push((head.take().unwrap(), std::mem::take(&mut body), trailers));
+            }
+        };
+        for piece in input.chunks(step) {
+            pending.extend_from_slice(piece);
+            loop {
+                let (n, event) = parser.parse(&pending).unwrap();
+                let done = n == 0 && event.is_none();
+                if let Some(event) = event {
+                    handle(event);
+                }
+                pending.drain(..n);
+                if done {
+                    break;
+                }
+            }
+        }
+        while let Some(event) = parser.finish().unwrap() {
+            handle(event);
+        }
+        messages
+    }
+
+    #[test]
+    fn pipelined_requests() {
+        let input = b"GET /a HTTP/1.1\r\nHost: x\r\n\r\n\
+                      POST /b?k=v HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nHello\
+                      PUT /c HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      3;ext=1\r\nabc\r\n2\r\nde\r\n0\r\nChecksum: 12\r\n\r\n";
+        for step in [1, 3, 7, input.len()] {
+            let messages = run(Parser::request(), input, step);
+            assert_eq!(messages.len(), 3);
+
+            let urls: Vec<_> = messages
+                .iter()
+                .map(|(head, ..)| match head {
+                    Head::Request(req) => req.url.to_string(),
+                    Head::Response(_) => panic!("Expected a request"),
+                })
+                .collect();
+            assert_eq!(urls, ["/a", "/b", "/c"]);
+
+            assert!(messages[0].1.is_empty());
+            assert_eq!(messages[1].1, b"Hello");
+            assert_eq!(messages[2].1, b"abcde");
+            assert_eq!(messages[2].2.get("checksum"), Some("12"));
+        }
+    }
+
+    #[test]
+    fn close_delimited_response() {
+        let input = b"HTTP/1.0 200 OK\r\nServer: test\r\n\r\nHello, world";
+        let messages = run(Parser::response(), input, 4);
+        assert_eq!(messages.len(), 1);
+        let Head::Response(head) = &messages[0].0 else {
+            panic!("Expected a response")
+        };
+        assert_eq!(head.status, 200);
+        assert_eq!(messages[0].1, b"Hello, world");
+    }
+
+    #[test]
+    fn skip_body() {
+        let mut parser = Parser::response();
+        let mut input: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+        loop {
+            let (n, event) = parser.parse(input).unwrap();
+            input = &input[n..];
+            if matches!(event, Some(Event::Head(_))) {
+                break;
+            }
+        }
+        assert!(input.is_empty());
+        parser.skip_body();
+        assert!(matches!(parser.parse(b""), Ok((0, Some(Event::End(_))))));
+        assert!(parser.is_idle());
+    }
+
+    #[test]
+    fn errors() {
+        let parse_all = |mut parser: Parser, mut input: &[u8]| {
+            while !input.is_empty() {
+                let (n, _) = parser.parse(input)?;
+                input = &input[n..];
+            }
+            Ok::<_, crate::HttpError>(())
+        };
+
+        let limits = ParserLimits::default().max_request_line(10);
+        let err =
+            parse_all(Parser::request().with_limits(limits), b"GET /some/long/url").unwrap_err();
+        assert_eq!(err.status(), 414);
+
+        for input in [
+            "POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: gzip\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\nZZ\r\n",
+            "GET / HTTP/1.1\r\n\r\n",
+        ] {
+            let err = parse_all(Parser::request(), input.as_bytes()).unwrap_err();
+            assert_eq!(err.status(), 400, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn truncated_head() {
+        for input in [
+            "GET / HTTP/1.1\r\nHost: a",
+            "GET / HTTP/1.1\r\nHost: a\r\n",
+            "GET / HTTP/1.1",
+        ] {
+            let err = Parser::request()
+                .read_head(&mut input.as_bytes())
+                .unwrap_err();
+            assert_eq!(err.status(), 400, "{input:?}");
+        }
+        let head = Parser::request().read_head(&mut "GET / HTTP/1.1\r\nHost: a\r\n\r\n".as_bytes());
+        assert!(head.is_ok());
+    }
+}
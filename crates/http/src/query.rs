@@ -0,0 +1,237 @@
+//! Query parameters
+
+use core::{fmt, slice};
+use std::str::FromStr;
+
+use crate::{HttpError, Result, error::ErrorKind};
+
+/// Parameters of a query string
+///
+/// - Parameters keep the order in which they appear
+/// - A key can appear multiple times (e.g. `?tag=a&tag=b`)
+/// - A key without a value (`?flag`) is different from
+///   a key with an empty value (`?flag=`)
+///
+/// Serializing the parameters with [`Display`](fmt::Display)
+/// and parsing them back returns the same parameters.
+///
+/// # Example
+/// ```
+/// use http::QueryParams;
+///
+/// let params: QueryParams = "tag=a&flag&tag=b&name=John%20Doe".parse().unwrap();
+/// assert_eq!(params.get("tag"), Some("a"));
+/// assert_eq!(params.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+/// assert_eq!(params.get("flag"), Some(""));
+/// assert!(params.is_flag("flag"));
+/// assert_eq!(params.get("name"), Some("John Doe"));
+///
+/// assert_eq!(params.to_string(), "tag=a&flag&tag=b&name=John%20Doe");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    params: Vec<(Box<str>, Option<Box<str>>)>,
+}
+
+impl QueryParams {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a query string, without the leading '?'
+    ///
+    /// Keys and values are url-decoded. Empty parameters
+    /// (like in `a&&b`) are ignored.
+    ///
+    /// # Errors
+    /// If a key or a value is not correctly encoded
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut params = Self::new();
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = match param.split_once('=') {
+                Some((key, value)) => (key, Some(decode(value)?)),
+                None => (param, None),
+            };
+            params.params.push((decode(key)?, value));
+        }
+        Ok(params)
+    }
+
+    /// Gets the first value of the given key
+    ///
+    /// A key without a value returns an empty string.
+    /// To tell them apart, use [`is_flag`](Self::is_flag)
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    /// Gets all the values of the given key, in order
+    pub fn get_all<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a str> {
+        self.params
+            .iter()
+            .filter(move |(k, _)| &**k == key)
+            .map(|(_, v)| v.as_deref().unwrap_or(""))
+    }
+
+    /// Returns true if the key is present
+    #[must_use]
+    pub fn contains(&self, key: &str) -> bool {
+        self.params.iter().any(|(k, _)| &**k == key)
+    }
+
+    /// Returns true if the first occurrence of the key has no value (e.g. `?flag`)
+    #[must_use]
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.params
+            .iter()
+            .find(|(k, _)| &**k == key)
+            .is_some_and(|(_, v)| v.is_none())
+    }
+
+    /// Adds a parameter, after the existing ones
+    pub fn append(&mut self, key: impl Into<Box<str>>, value: impl Into<Box<str>>) {
+        self.params.push((key.into(), Some(value.into())));
+    }
+
+    /// Adds a parameter without a value, after the existing ones
+    pub fn append_flag(&mut self, key: impl Into<Box<str>>) {
+        self.params.push((key.into(), None));
+    }
+
+    /// Removes all the occurrences of the key
+    pub fn remove(&mut self, key: &str) {
+        self.params.retain(|(k, _)| &**k != key);
+    }
+
+    /// Iterates over the parameters, in order
+    ///
+    /// Parameters without a value have a [None] value
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.params.iter(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+fn decode(s: &str) -> Result<Box<str>> {
+    url::decode(s)
+        .map(Into::into)
+        .map_err(|err| HttpError::with_kind(ErrorKind::Parse, err))
+}
+
+/// Iterator over the parameters of a [`QueryParams`]
+pub struct Iter<'a> {
+    inner: slice::Iter<'a, (Box<str>, Option<Box<str>>)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&**k, v.as_deref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a QueryParams {
+    type Item = (&'a str, Option<&'a str>);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromStr for QueryParams {
+    type Err = HttpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for QueryParams {
+    /// Serializes the parameters into a query string, without the leading '?'
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (k, v)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            f.write_str(&url::encode(k).unwrap_or_default())?;
+            if let Some(v) = v {
+                write!(f, "={}", url::encode(v).unwrap_or_default())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for QueryParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::QueryParams;
+
+    #[test]
+    fn multiple_values() {
+        let params = QueryParams::parse("tag=a&x=1&tag=b").unwrap();
+        assert_eq!(params.len(), 3);
+        assert_eq!(params.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(params.get("x"), Some("1"));
+        assert_eq!(params.get("y"), None);
+    }
+
+    #[test]
+    fn flags() {
+        let params = QueryParams::parse("flag&empty=").unwrap();
+        assert!(params.is_flag("flag"));
+        assert!(!params.is_flag("empty"));
+        assert!(!params.is_flag("missing"));
+        assert_eq!(params.get("flag"), Some(""));
+        assert_eq!(params.get("empty"), Some(""));
+        assert_eq!(params.to_string(), "flag&empty=");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut params = QueryParams::new();
+        params.append("a b", "c&d=e");
+        params.append_flag("ü");
+        params.append("k", "");
+        params.append("a b", "x=y");
+
+        let s = params.to_string();
+        assert_eq!(s, "a%20b=c%26d%3De&%C3%BC&k=&a%20b=x%3Dy");
+        assert_eq!(QueryParams::parse(&s).unwrap(), params);
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        let err = QueryParams::parse("a=%G1").unwrap_err();
+        assert_eq!(err.status(), 400);
+    }
+}
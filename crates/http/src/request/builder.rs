@@ -1,7 +1,11 @@
 use core::marker::PhantomData;
-use std::{collections::HashMap, io::BufReader};
+use std::io::BufReader;
 
-use crate::{HttpMethod, HttpRequest, stream};
+use crate::{
+    HeaderMap, HttpMethod, HttpRequest, HttpVersion, ParserLimits, QueryParams, Status,
+    encoding::{BodyKind, BodyReader, ContentCoding},
+    stream,
+};
 
 pub struct Url;
 pub struct NoUrl;
@@ -9,11 +13,11 @@ pub struct NoUrl;
 pub struct HttpRequestBuilder<U> {
     method: HttpMethod,
     url: Option<Box<str>>,
-    headers: HashMap<Box<str>, Box<str>>,
-    response_headers: HashMap<Box<str>, Box<str>>,
-    params: HashMap<Box<str>, Box<str>>,
-    version: f32,
-    status: u16,
+    headers: HeaderMap,
+    response_headers: HeaderMap,
+    params: QueryParams,
+    version: HttpVersion,
+    status: Status,
     body: Option<Box<[u8]>>,
     _pd: PhantomData<U>,
 }
@@ -25,26 +29,26 @@ impl<U> HttpRequestBuilder<U> {
     }
 
     pub fn header(mut self, k: impl Into<Box<str>>, v: impl Into<Box<str>>) -> Self {
-        self.headers.insert(k.into(), v.into());
+        self.headers.insert(k, v);
         self
     }
 
     pub fn response_header(mut self, k: impl Into<Box<str>>, v: impl Into<Box<str>>) -> Self {
-        self.response_headers.insert(k.into(), v.into());
+        self.response_headers.insert(k, v);
         self
     }
 
     pub fn param(mut self, k: impl Into<Box<str>>, v: impl Into<Box<str>>) -> Self {
-        self.params.insert(k.into(), v.into());
+        self.params.append(k, v);
         self
     }
 
-    pub fn version(mut self, v: f32) -> Self {
+    pub fn version(mut self, v: HttpVersion) -> Self {
         self.version = v;
         self
     }
 
-    pub fn status(mut self, code: u16) -> Self {
+    pub fn status(mut self, code: Status) -> Self {
         self.status = code;
         self
     }
@@ -53,20 +57,35 @@ impl<U> HttpRequestBuilder<U> {
         self.body = Some(body.into());
         self
     }
+
+    /// Asks the server to compress the response
+    ///
+    /// Sends an *Accept-Encoding* header with all the supported
+    /// [codings](ContentCoding). The body of the [response](crate::HttpResponse)
+    /// returned by [`send_to`](HttpRequest::send_to) is decoded transparently.
+    pub fn compressed(self) -> Self {
+        let codings = ContentCoding::ALL.map(ContentCoding::as_str).join(", ");
+        self.header("Accept-Encoding", codings)
+    }
 }
 
 impl HttpRequestBuilder<Url> {
     pub fn build(self) -> HttpRequest {
         HttpRequest {
             body: self.body,
+            body_truncated: false,
             status: self.status,
             params: self.params,
             headers: self.headers,
             method: self.method,
             url: self.url.unwrap(),
-            stream: BufReader::new(stream::dummy()),
+            stream: BodyReader::new(BufReader::new(stream::dummy()), BodyKind::Empty),
             version: self.version,
             response_headers: self.response_headers,
+            trailers: HeaderMap::new(),
+            limits: ParserLimits::default(),
+            encoding_selector: None,
+            expect_continue: false,
         }
     }
 }
@@ -76,12 +95,12 @@ impl HttpRequestBuilder<NoUrl> {
         Self {
             method: HttpMethod::GET,
             url: None,
-            response_headers: HashMap::new(),
-            headers: HashMap::new(),
-            params: HashMap::new(),
-            status: 200,
+            response_headers: HeaderMap::new(),
+            headers: HeaderMap::new(),
+            params: QueryParams::new(),
+            status: Status::OK,
             body: None,
-            version: 1.0,
+            version: HttpVersion::Http10,
             _pd: PhantomData,
         }
     }
@@ -1,37 +1,62 @@
 mod parse;
 use core::fmt;
 use std::{
-    collections::HashMap,
     env,
     ffi::OsStr,
-    io::{BufRead, BufReader, BufWriter, Read, Write},
+    io::{self, BufReader, BufWriter, Read, Write},
     path::Path,
+    sync::Arc,
 };
 
 use parse::parse_request;
+pub(crate) use parse::{expects_continue, from_head};
 
 use crate::{
-    HttpMethod, HttpResponse, HttpStream, Result, StatusCode,
-    encoding::Chunked,
+    HeaderMap, HttpMethod, HttpResponse, HttpStream, HttpVersion, ParserLimits, QueryParams,
+    Result, Status, StatusCode,
+    cookie::{SetCookie, parse_cookies},
+    encoding::{self, BodyReader, Chunked, CompressReader, ContentCoding},
+    err,
+    headers::is_field_value,
+    multipart::{Multipart, header_param},
+    negotiation::{self, Mime},
+    parser::RequestHead,
     request::builder::{HttpRequestBuilder, NoUrl},
+    sse::SseWriter,
     stream::IntoHttpStream,
 };
 
 pub mod builder;
 
+/// [Reader](BodyReader) for the body of an [`HttpRequest`]
+pub type RequestBody<'a> = &'a mut BodyReader<BufReader<Box<dyn HttpStream>>>;
+
+/// Function that chooses the [content coding](ContentCoding) of a response
+///
+/// See [`HttpRequest::set_encoding_selector`]
+pub type EncodingSelector = Arc<dyn Fn(&mut HttpRequest) -> Option<ContentCoding> + Send + Sync>;
+
+/// Default max size of a body parsed by [`HttpRequest::form`]
+pub const MAX_FORM_SIZE: usize = 1024 * 1024;
+
 /// HTTP Request
 ///
 /// Represents an HTTP request
 pub struct HttpRequest {
     method: HttpMethod,
     url: Box<str>,
-    headers: HashMap<Box<str>, Box<str>>,
-    params: HashMap<Box<str>, Box<str>>,
-    response_headers: HashMap<Box<str>, Box<str>>,
-    version: f32,
-    stream: BufReader<Box<dyn HttpStream>>,
-    status: u16,
+    headers: HeaderMap,
+    params: QueryParams,
+    response_headers: HeaderMap,
+    version: HttpVersion,
+    stream: BodyReader<BufReader<Box<dyn HttpStream>>>,
+    status: Status,
     body: Option<Box<[u8]>>,
+    body_truncated: bool,
+    trailers: HeaderMap,
+    limits: ParserLimits,
+    encoding_selector: Option<EncodingSelector>,
+    expect_continue: bool,
 }
 
 impl fmt::Debug for HttpRequest {
@@ -45,6 +70,7 @@ impl fmt::Debug for HttpRequest {
             .field("version", &self.version)
             .field("status", &self.status)
             .field("body", &self.body)
+            .field("trailers", &self.trailers)
             .finish()
     }
 }
@@ -57,25 +83,79 @@ impl HttpRequest {
 
     /// Read and parse an HTTP request from the given [`HttpStream`]
     pub fn parse<S: IntoHttpStream>(stream: S) -> Result<Self> {
+        Self::parse_with_limits(stream, ParserLimits::default())
+    }
+
+    /// Read and parse an HTTP request from the given [`HttpStream`],
+    /// enforcing the given [limits](ParserLimits)
+    ///
+    /// The limits are also applied to the following requests
+    /// read with [`keep_alive`](Self::keep_alive)
+    pub fn parse_with_limits<S: IntoHttpStream>(stream: S, limits: ParserLimits) -> Result<Self> {
         let stream: Box<dyn HttpStream> = Box::new(stream.into_http_stream());
-        parse_request(BufReader::new(stream))
+        parse_request(BufReader::new(stream), limits)
     }
 
-    #[inline]
-    pub fn keep_alive(self) -> Result<Self> {
-        let mut req = parse_request(self.stream)?;
+    /// Parses a request from an already buffered stream
+    ///
+    /// Unlike [`parse_with_limits`](Self::parse_with_limits), this keeps
+    /// the bytes that were already read into the buffer. It's useful
+    /// after peeking the stream, like [`h2::starts_with_preface`] does.
+    ///
+    /// [`h2::starts_with_preface`]: crate::h2::starts_with_preface
+    ///
+    /// # Errors
+    /// If the request can't be parsed
+    pub fn parse_buffered(
+        stream: BufReader<Box<dyn HttpStream>>,
+        limits: ParserLimits,
+    ) -> Result<Self> {
+        parse_request(stream, limits)
+    }
+
+    /// Parses the next request from the same stream
+    ///
+    /// The unread part of this request's body is
+    /// [discarded](Self::discard_body) first.
+    ///
+    /// # Errors
+    /// - If the remaining body is too large to be discarded
+    /// - If the next request can't be parsed
+    pub fn keep_alive(mut self) -> Result<Self> {
+        self.discard_body()?;
+        let mut req = parse_request(self.stream.into_inner(), self.limits)?;
         req.set_header("Connection", "keep-alive");
         Ok(req)
     }
 
+    /// Splits the request into it's head and stream
+    ///
+    /// The unread part of the body stays in the stream.
+    pub(crate) fn into_parts(self) -> (RequestHead, BufReader<Box<dyn HttpStream>>, ParserLimits) {
+        let head = RequestHead {
+            method: self.method,
+            url: self.url,
+            params: self.params,
+            version: self.version,
+            headers: self.headers,
+        };
+        (head, self.stream.into_inner(), self.limits)
+    }
+
+    /// Get the underlying stream
     #[inline]
     pub fn stream(&self) -> &BufReader<Box<dyn HttpStream>> {
-        &self.stream
+        self.stream.get_ref()
     }
 
+    /// Get the underlying stream
+    ///
+    /// # NOTE
+    /// Reading from it directly skips the framing of the body.
+    /// To read the body, use [`read_body`](Self::read_body) or [`body`](Self::body)
     #[inline]
     pub fn stream_mut(&mut self) -> &mut BufReader<Box<dyn HttpStream>> {
-        &mut self.stream
+        self.stream.get_mut()
     }
 
     /// Url of the request
@@ -91,14 +171,17 @@ impl HttpRequest {
     /// Get the query parameters
     #[inline]
     #[must_use]
-    pub fn params(&self) -> &HashMap<Box<str>, Box<str>> {
+    pub fn params(&self) -> &QueryParams {
         &self.params
     }
 
+    /// Get the first value of the given query parameter
+    ///
+    /// See [`QueryParams::get`]
     #[inline]
     #[must_use]
     pub fn param(&self, key: &str) -> Option<&str> {
-        self.params.get(key).map(AsRef::as_ref)
+        self.params.get(key)
     }
 
     /// Get the filename for the request
@@ -117,14 +200,9 @@ impl HttpRequest {
     pub fn write_to(&self, f: &mut dyn Write) -> Result<()> {
         write!(f, "{} {}", self.method(), self.url())?;
         if !self.params().is_empty() {
-            write!(f, "?")?;
-            for (k, v) in self.params() {
-                let ke = url::encode(k).unwrap_or("".into());
-                let ve = url::encode(v).unwrap_or("".into());
-                write!(f, "{ke}={ve}&")?;
-            }
+            write!(f, "?{}", self.params())?;
         }
-        write!(f, " HTTP/{}\r\n", self.version())?;
+        write!(f, " {}\r\n", self.version())?;
 
         for (k, v) in self.headers() {
             write!(f, "{k}: {v}\r\n")?;
@@ -141,13 +219,27 @@ impl HttpRequest {
 
     /// Sends the ``HttpRequest`` to a [stream](HttpStream)
     ///
+    /// If the request accepts any of the supported [codings](ContentCoding),
+    /// the response is [decompressed](HttpResponse::set_decompress).
+    ///
+    /// The response to a HEAD request doesn't have a [body](HttpResponse::skip_body).
+    ///
     /// # Errors
     /// If the transfer fails, returns the error
     pub fn send_to<Out: IntoHttpStream>(&self, stream: Out) -> crate::Result<HttpResponse> {
         let mut stream = stream.into_http_stream();
         self.write_to(&mut stream)?;
         stream.flush()?;
-        HttpResponse::parse(stream)
+        let mut res = HttpResponse::parse(stream)?;
+        if self.method == HttpMethod::HEAD {
+            res.skip_body();
+        }
+        let supported = ContentCoding::ALL.map(ContentCoding::as_str);
+        let decompress = self
+            .header("Accept-Encoding")
+            .is_some_and(|ae| negotiation::encoding(Some(ae), &supported).is_ok());
+        res.set_decompress(decompress);
+        Ok(res)
     }
     #[inline]
     #[must_use]
@@ -157,22 +249,56 @@ impl HttpRequest {
 
     #[inline]
     #[must_use]
-    pub fn status(&self) -> u16 {
+    pub fn status(&self) -> Status {
         self.status
     }
 
     #[inline]
-    pub fn set_status(&mut self, status: u16) -> &mut Self {
+    pub fn set_status(&mut self, status: Status) -> &mut Self {
         self.status = status;
         self
     }
 
     #[inline]
     #[must_use]
-    pub fn version(&self) -> f32 {
+    pub fn version(&self) -> HttpVersion {
         self.version
     }
 
+    /// Returns the version used to respond to this request
+    ///
+    /// HTTP/1.0 requests get an HTTP/1.0 response, and
+    /// every other version is answered with HTTP/1.1
+    #[inline]
+    #[must_use]
+    pub fn response_version(&self) -> HttpVersion {
+        match self.version {
+            HttpVersion::Http10 => HttpVersion::Http10,
+            _ => HttpVersion::Http11,
+        }
+    }
+
+    /// Returns true if the connection can be kept open after
+    /// responding to this request
+    ///
+    /// A `Connection: close` header always closes the connection.
+    /// Otherwise, HTTP/1.1 connections are persistent, and HTTP/1.0
+    /// ones are only persistent if the client sends `Connection: keep-alive`
+    #[must_use]
+    pub fn is_persistent(&self) -> bool {
+        let has_option = |opt: &str| {
+            self.headers
+                .get_all("Connection")
+                .flat_map(|v| v.split(','))
+                .any(|o| o.trim().eq_ignore_ascii_case(opt))
+        };
+        if has_option("close") {
+            false
+        } else {
+            self.version.is_persistent_by_default() || has_option("keep-alive")
+        }
+    }
+
     /// Get the value of the *Content-Length* HTTP header
     ///
     /// If the header is not present, or if it fails to parse
@@ -187,21 +313,140 @@ impl HttpRequest {
     }
 
     /// Get the value of the given header key, if present
+    ///
+    /// If the header appears multiple times, the first
+    /// value is returned. To get all of them, use
+    /// [`HeaderMap::get_all`] on [headers](Self::headers)
     #[inline]
     #[must_use]
     pub fn header(&self, key: &str) -> Option<&str> {
-        self.headers.get(key).map(AsRef::as_ref)
+        self.headers.get(key)
     }
 
     #[inline]
     #[must_use]
-    pub fn headers(&self) -> &HashMap<Box<str>, Box<str>> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    /// Get the headers that will be sent on the response
+    #[inline]
+    #[must_use]
+    pub fn response_headers(&self) -> &HeaderMap {
+        &self.response_headers
+    }
+
+    /// Sets a header for the response, replacing it's previous value
     #[inline]
     pub fn set_header(&mut self, key: impl Into<Box<str>>, value: impl Into<Box<str>>) {
-        self.response_headers.insert(key.into(), value.into());
+        self.response_headers.insert(key, value);
+    }
+
+    /// Adds a header to the response, keeping it's previous values
+    ///
+    /// This is useful for headers that can be sent
+    /// multiple times, like *Set-Cookie*
+    #[inline]
+    pub fn add_header(&mut self, key: impl Into<Box<str>>, value: impl Into<Box<str>>) {
+        self.response_headers.append(key, value);
+    }
+
+    /// Iterates over the cookies sent by the client,
+    /// as (name, value) pairs
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.get_all("Cookie").flat_map(parse_cookies)
+    }
+
+    /// Get the value of the given cookie, if present
+    #[must_use]
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    /// Adds a *Set-Cookie* header to the response
+    ///
+    /// It can be called multiple times, to send multiple cookies.
+    ///
+    /// # Errors
+    /// If the cookie's attributes contain characters not allowed in a header
+    pub fn add_cookie(&mut self, cookie: &SetCookie) -> Result<()> {
+        let cookie = cookie.to_string();
+        if !is_field_value(&cookie) {
+            return err!(kind = Parse, "Invalid cookie: {cookie:?}");
+        }
+        self.add_header("Set-Cookie", cookie);
+        Ok(())
+    }
+
+    /// Chooses the best of the offered media types,
+    /// based on the request's *Accept* header
+    ///
+    /// See [`negotiation::media_type`]
+    ///
+    /// # Errors
+    /// If none of the offered types is acceptable (406)
+    pub fn negotiate_type<'o, 'm>(&self, offered: &'o [Mime<'m>]) -> Result<&'o Mime<'m>> {
+        negotiation::media_type(self.header("Accept"), offered)
+    }
+
+    /// Chooses the best of the offered languages,
+    /// based on the request's *Accept-Language* header
+    ///
+    /// See [`negotiation::language`]
+    ///
+    /// # Errors
+    /// If none of the offered languages is acceptable (406)
+    pub fn negotiate_language<'o>(&self, offered: &[&'o str]) -> Result<&'o str> {
+        negotiation::language(self.header("Accept-Language"), offered)
+    }
+
+    /// Chooses the best of the offered charsets,
+    /// based on the request's *Accept-Charset* header
+    ///
+    /// See [`negotiation::charset`]
+    ///
+    /// # Errors
+    /// If none of the offered charsets is acceptable (406)
+    pub fn negotiate_charset<'o>(&self, offered: &[&'o str]) -> Result<&'o str> {
+        negotiation::charset(self.header("Accept-Charset"), offered)
+    }
+
+    /// Chooses the best of the offered content codings,
+    /// based on the request's *Accept-Encoding* header
+    ///
+    /// See [`negotiation::encoding`]
+    ///
+    /// # Errors
+    /// If none of the offered codings is acceptable (406)
+    pub fn negotiate_encoding<'o>(&self, offered: &[&'o str]) -> Result<&'o str> {
+        negotiation::encoding(self.header("Accept-Encoding"), offered)
+    }
+
+    /// Returns true if the body of the request is sent
+    /// with the chunked [*Transfer-Encoding*](https://www.rfc-editor.org/rfc/rfc9112#section-6.1)
+    #[must_use]
+    pub fn is_chunked(&self) -> bool {
+        /* The same check that decides how the body is read */
+        encoding::body::is_chunked(&self.headers) == Some(true)
+    }
+
+    /// Get the trailer headers of a [chunked](Self::is_chunked) request
+    ///
+    /// # NOTE
+    /// Trailers are sent after the body, so this map will be empty
+    /// until the body has been read, with [body](Self::body) or
+    /// [read_body](Self::read_body).
+    #[inline]
+    #[must_use]
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    /// Get the value of the given trailer key, if present
+    #[inline]
+    #[must_use]
+    pub fn trailer(&self, key: &str) -> Option<&str> {
+        self.trailers.get(key)
     }
 
     /// Reads the body from the stream into the buffer.
@@ -212,7 +457,7 @@ impl HttpRequest {
     pub(crate) fn read_body_into_buffer(&mut self) -> Result<()> {
         let len = self.content_length();
         let mut buf = Vec::with_capacity(len);
-        self.stream.read_to_end(&mut buf)?;
+        self.read_body(&mut buf)?;
         self.body = Some(buf.into_boxed_slice());
         Ok(())
     }
@@ -228,7 +473,8 @@ impl HttpRequest {
     /// [read_body](Self::read_body) function.
     ///
     /// # Errors
-    /// If some IO error happens when reading the body from the [`stream`]
+    /// - If some IO error happens when reading the body from the [`stream`]
+    /// - If the body was rejected by [form](Self::form) for being too big
     ///
     /// # Returns
     /// And option of &[u8]. A None variant means the request doesn't have a body.
@@ -242,55 +488,325 @@ impl HttpRequest {
         Ok(self.body.as_deref())
     }
 
-    /// Returns true if the [`stream`](HttpStream) has a body,
-    /// and false if it's empty.
+    /// Parses an `application/x-www-form-urlencoded` body
+    ///
+    /// The body is decoded into [`QueryParams`], just like the query of the url.
+    /// Bodies bigger than [`MAX_FORM_SIZE`] are rejected. To use
+    /// another limit, see [`form_with_limit`](Self::form_with_limit)
+    ///
+    /// The body is kept in the request, so it can still be accessed with [body](Self::body)
+    ///
+    /// # Errors
+    /// - If the *Content-Type* is not `application/x-www-form-urlencoded`,
+    ///   the error is of kind [`UnsupportedMediaType`](crate::error::ErrorKind::UnsupportedMediaType)
+    /// - If the body is too big, the error is of kind [`PayloadTooLarge`](crate::error::ErrorKind::PayloadTooLarge).
+    ///   Part of the body may have been read by then, so later calls
+    ///   to this method or to [body](Self::body) fail too
+    /// - If the body is not correctly encoded
+    #[inline]
+    pub fn form(&mut self) -> Result<QueryParams> {
+        self.form_with_limit(MAX_FORM_SIZE)
+    }
+
+    /// Like [form](Self::form), but rejecting bodies bigger than `max` bytes
+    ///
+    /// # Errors
+    /// See [form](Self::form)
+    pub fn form_with_limit(&mut self, max: usize) -> Result<QueryParams> {
+        let content_type = self.header("Content-Type").unwrap_or("");
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        if !mime.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            return err!(
+                kind = UnsupportedMediaType,
+                "Expected a Content-Type of application/x-www-form-urlencoded, found \"{content_type}\""
+            );
+        }
+        if self.content_length() > max || self.body_truncated {
+            return err!(
+                kind = PayloadTooLarge,
+                "Form body exceeds the limit of {max} bytes"
+            );
+        }
+        if self.body.is_none() {
+            self.send_continue()?;
+            let mut buf = Vec::new();
+            (&mut self.stream)
+                .take(max as u64 + 1)
+                .read_to_end(&mut buf)?;
+            if buf.len() > max {
+                /* The rest of the body is still in the stream, so
+                 * it can't be read again, neither here nor with body() */
+                self.body_truncated = true;
+                return err!(
+                    kind = PayloadTooLarge,
+                    "Form body exceeds the limit of {max} bytes"
+                );
+            }
+            self.store_trailers();
+            self.body = Some(buf.into_boxed_slice());
+        }
+        let body = self.body.as_deref().unwrap_or_default();
+        if body.len() > max {
+            return err!(
+                kind = PayloadTooLarge,
+                "Form body exceeds the limit of {max} bytes"
+            );
+        }
+        QueryParams::parse(str::from_utf8(body)?)
+    }
+
+    /// Returns a [`Multipart`] reader for a `multipart/form-data` body
+    ///
+    /// The parts are read from the stream as they're requested, so
+    /// big uploads don't need to be kept in memory.
+    ///
+    /// # Errors
+    /// If the *Content-Type* is not multipart, or it doesn't have
+    /// a boundary, the error is of kind [`UnsupportedMediaType`](crate::error::ErrorKind::UnsupportedMediaType)
+    pub fn multipart(&mut self) -> Result<Multipart<RequestBody<'_>>> {
+        let content_type = self.header("Content-Type").unwrap_or("");
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        let is_multipart = mime
+            .get(..10)
+            .is_some_and(|m| m.eq_ignore_ascii_case("multipart/"));
+        let boundary =
+            header_param(content_type, "boundary").filter(|b| (1..=70).contains(&b.len()));
+        let (true, Some(boundary)) = (is_multipart, boundary) else {
+            return err!(
+                kind = UnsupportedMediaType,
+                "Expected a multipart Content-Type with a boundary, found \"{content_type}\""
+            );
+        };
+        self.send_continue()?;
+        Ok(Multipart::new(&mut self.stream, &boundary).with_limits(self.limits))
+    }
+
+    /// Returns true if the request has a body that hasn't been read
+    /// from the [`stream`](HttpStream), or if it has been loaded into
+    /// the request's buffer.
     ///
     /// This method is preferred to check the presence of a body,
     /// over calling [body](Self::body) and checking the returned Option,
     /// since this function doesn't allocate memory, nor mutates the request.
     ///
     /// # Errors
-    /// If some IO error happens in the process of checking the
-    /// [`stream`]'s availability
-    ///
-    /// [`stream`]: HttpStream
+    /// This function currently doesn't fail
     pub fn has_body(&mut self) -> Result<bool> {
-        Ok(self.body.is_some() || !self.stream.fill_buf()?.is_empty())
+        Ok(self.body.is_some() || !self.stream.is_done())
     }
 
     /// Reads the request body into [writer](Write)
     ///
+    /// The body is read according to the request's framing: up to
+    /// it's *Content-Length*, or until the last chunk if it's
+    /// [chunked](Self::is_chunked). In that case, the trailers are
+    /// stored in the request.
+    ///
+    /// If the client is [waiting](Self::expects_continue) for a
+    /// *100 Continue*, it's sent before reading.
+    ///
     /// # Errors
-    /// If, while reading or writing, some io Error is found
+    /// - If, while reading or writing, some io Error is found
+    /// - If part of the body was already read by [form](Self::form), and
+    ///   rejected for being too big. The error is of kind
+    ///   [`PayloadTooLarge`](crate::error::ErrorKind::PayloadTooLarge)
     pub fn read_body(&mut self, out: &mut dyn Write) -> Result<usize> {
-        let mut total = 0;
-        loop {
-            let slice = self.stream.fill_buf()?;
-            if slice.is_empty() {
-                break;
-            }
-            out.write_all(slice)?;
-
-            let len = slice.len();
-            self.stream.consume(len);
-            total += len;
+        if self.body_truncated {
+            return err!(
+                kind = PayloadTooLarge,
+                "The body was partially read, and exceeds the size limit"
+            );
         }
+        self.send_continue()?;
+        let total = io::copy(&mut self.stream, out)?;
+        self.store_trailers();
         out.flush()?;
-        Ok(total)
+        Ok(usize::try_from(total).unwrap_or(usize::MAX))
+    }
+
+    /// Discards the part of the body that hasn't been read
+    ///
+    /// This leaves the stream at the start of the next request.
+    /// It's called by [`keep_alive`](Self::keep_alive).
+    ///
+    /// # Errors
+    /// - If the remaining body is bigger than the
+    ///   [limit](ParserLimits::max_unread_body). In that case,
+    ///   the connection should be closed.
+    /// - If the client [expected](Self::expects_continue) a *100 Continue*,
+    ///   and the body was declined by responding without reading it.
+    /// - If some IO error happens while reading the body
+    pub fn discard_body(&mut self) -> Result<()> {
+        if self.stream.is_done() {
+            return Ok(());
+        }
+        if self.expect_continue {
+            /* The client may or may not send the body after the
+             * response, so we can't know where the next request starts */
+            return err!("The body of the request was declined");
+        }
+        let max = self.limits.max_unread_body as u64;
+        io::copy(&mut (&mut self.stream).take(max), &mut io::sink())?;
+        if !self.stream.is_done() {
+            return err!(
+                kind = PayloadTooLarge,
+                "The unread body exceeds the limit of {max} bytes"
+            );
+        }
+        self.store_trailers();
+        Ok(())
+    }
+
+    fn store_trailers(&mut self) {
+        if let Some(trailers) = self.stream.trailers() {
+            self.trailers = trailers.clone();
+        }
+    }
+
+    /// Returns true if the client sent `Expect: 100-continue`,
+    /// and is still waiting for a *100 Continue* before sending the body
+    ///
+    /// The *100 Continue* is sent automatically when the body starts
+    /// being read. To decline the body, respond with a final status
+    /// (like *413 Content Too Large*) without reading it.
+    /// The connection is closed after that response.
+    #[inline]
+    #[must_use]
+    pub fn expects_continue(&self) -> bool {
+        self.expect_continue
+    }
+    fn send_continue(&mut self) -> Result<()> {
+        if self.expect_continue {
+            self.send_interim(Status::CONTINUE, &HeaderMap::new())?;
+        }
+        Ok(())
+    }
+    /// Sends an interim (1xx) response, before the final one
+    ///
+    /// HTTP/1.0 clients don't understand interim responses,
+    /// so nothing is sent to them.
+    ///
+    /// # Errors
+    /// - If the status is not informational (1xx)
+    /// - If some io error is produced while sending the response
+    pub fn send_interim(&mut self, status: Status, headers: &HeaderMap) -> Result<()> {
+        if !status.is_informational() {
+            return err!("{status} is not an informational status");
+        }
+        if self.version < HttpVersion::Http11 {
+            return Ok(());
+        }
+        let stream = self.stream.get_mut().get_mut();
+        write!(
+            stream,
+            "{} {status} {}\r\n",
+            HttpVersion::Http11,
+            status.reason().unwrap_or("")
+        )?;
+        for (k, v) in headers {
+            write!(stream, "{k}: {v}\r\n")?;
+        }
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        if status == Status::CONTINUE {
+            self.expect_continue = false;
+        }
+        Ok(())
+    }
+    /// Sends a *103 Early Hints* response, with a *Link*
+    /// header for each of the given links
+    ///
+    /// This allows the client to start loading resources
+    /// while the final response is being prepared.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use http::HttpRequest;
+    ///
+    /// fn handler(req: &mut HttpRequest) -> http::Result<()> {
+    ///     req.send_early_hints(&["</style.css>; rel=preload; as=style"])?;
+    ///     req.respond_str("<link rel=\"stylesheet\" href=\"/style.css\">")
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// - If a link is not a valid header value
+    /// - If some io error is produced while sending the response
+    pub fn send_early_hints(&mut self, links: &[&str]) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        for &link in links {
+            if !is_field_value(link) {
+                return err!(kind = Parse, "Invalid Link header: {link:?}");
+            }
+            headers.append("Link", link);
+        }
+        self.send_interim(Status::EARLY_HINTS, &headers)
+    }
+    /// Sets the function that chooses the [content coding](ContentCoding)
+    /// of the response
+    ///
+    /// It's called right before sending a response with a body, so it
+    /// can look at the response's status and headers. If it returns a
+    /// coding, the body is compressed with it and sent chunked.
+    ///
+    /// The selector is not called for responses that can't be encoded:
+    /// responses to HTTP/1.0 requests, partial responses, responses without
+    /// a body and responses that already have a *Content-Encoding*.
+    ///
+    /// The *ETag* of an encoded response is made [specific](ContentCoding::etag)
+    /// to the coding.
+    #[inline]
+    pub fn set_encoding_selector(&mut self, selector: Option<EncodingSelector>) {
+        self.encoding_selector = selector;
+    }
+    /// Chooses the coding of the response, and sets the headers for it
+    fn select_encoding(&mut self) -> Option<ContentCoding> {
+        if self.response_version() < HttpVersion::Http11
+            || self.status.is_informational()
+            || self.status == Status::NO_CONTENT
+            || self.status == Status::PARTIAL_CONTENT
+            || self.status == Status::NOT_MODIFIED
+            || self.response_headers.contains("Content-Encoding")
+            || self.response_headers.contains("Content-Range")
+        {
+            return None;
+        }
+        let selector = self.encoding_selector.clone()?;
+        let coding = selector(self)?;
+        self.response_headers.remove("Content-Length");
+        self.set_header("Content-Encoding", coding.as_str());
+        self.set_header("Transfer-Encoding", "chunked");
+        /* The compressed body is a different representation */
+        if let Some(etag) = self.response_headers.get("ETag") {
+            let etag = coding.etag(etag);
+            self.set_header("ETag", etag);
+        }
+        Some(coding)
     }
     /// Respond to the request without a body
     ///
     /// # Errors
     /// If some io error is produced while sending the request
     pub fn respond(&mut self) -> Result<()> {
+        /* A response to HEAD has the same headers as the response to GET */
+        if self.method == HttpMethod::HEAD {
+            self.select_encoding();
+        }
+        self.send_head()
+    }
+    fn send_head(&mut self) -> Result<()> {
+        if self.expect_continue && !self.stream.is_done() {
+            /* The body is declined, so this connection can't be reused */
+            self.set_header("Connection", "close");
+        }
         let response_line = format!(
-            "HTTP/{} {} {}\r\n",
-            self.version,
+            "{} {} {}\r\n",
+            self.response_version(),
             self.status,
             self.status_msg()
         );
-        self.stream.get_mut().write_all(response_line.as_bytes())?;
-        let stream = self.stream.get_mut();
+        let stream = self.stream.get_mut().get_mut();
+        stream.write_all(response_line.as_bytes())?;
         for (k, v) in &self.response_headers {
             stream.write_all(k.as_bytes())?;
             stream.write_all(b": ")?;
@@ -325,7 +841,7 @@ impl HttpRequest {
         F: FnOnce(&mut dyn Write) -> Result<()>,
     {
         self.respond()?;
-        let mut out = BufWriter::new(self.stream.get_mut());
+        let mut out = BufWriter::new(self.stream.get_mut().get_mut());
         f(&mut out)?;
         out.flush()?;
         Ok(())
@@ -335,12 +851,76 @@ impl HttpRequest {
     /// # Errors
     /// If some io error is produced while sending the request
     pub fn respond_reader(&mut self, reader: &mut dyn Read) -> Result<()> {
+        match self.select_encoding() {
+            Some(coding) => {
+                let mut reader = Chunked::with_default_size(CompressReader::new(reader, coding));
+                self.send(&mut reader)
+            }
+            None => self.send(reader),
+        }
+    }
+    /// Respond to the request as a chunked transfer
+    ///
+    /// This means that the Content-Length of the request doen't need to be known.
+    ///
+    /// # Errors
+    /// If some io error is produced while sending the request
+    pub fn respond_chunked(&mut self, reader: &mut dyn Read) -> Result<()> {
+        match self.select_encoding() {
+            Some(coding) => {
+                let mut reader = Chunked::with_default_size(CompressReader::new(reader, coding));
+                self.send(&mut reader)
+            }
+            None => {
+                self.set_header("Transfer-Encoding", "chunked");
+                self.send(&mut Chunked::with_default_size(reader))
+            }
+        }
+    }
+    /// Returns the *Last-Event-ID* header, sent by a client
+    /// reconnecting to an [event stream](Self::respond_sse)
+    #[inline]
+    #[must_use]
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.header("Last-Event-ID")
+    }
+    /// Responds with an event stream
+    ///
+    /// It sends the head of a `text/event-stream` response, and
+    /// returns an [`SseWriter`] to send the events with.
+    /// The body is chunked, unless the client speaks HTTP/1.0.
+    /// In that case the connection is closed when the stream ends.
+    ///
+    /// # Errors
+    /// If some io error is produced while sending the head
+    pub fn respond_sse(&mut self) -> Result<SseWriter<'_>> {
+        let chunked = self.response_version() != HttpVersion::Http10;
+        self.response_headers.remove("Content-Length");
+        self.response_headers.remove("Content-Encoding");
+        self.set_header("Content-Type", "text/event-stream");
+        self.set_header("Cache-Control", "no-cache");
+        if chunked {
+            self.set_header("Transfer-Encoding", "chunked");
+        } else {
+            self.set_header("Connection", "close");
+        }
+        self.send_head()?;
+
+        let last_event_id = self.last_event_id().map(Box::from);
+        let out = self.stream.get_mut().get_mut();
+        out.flush()?;
+        if self.method == HttpMethod::HEAD {
+            return Ok(SseWriter::closed(out));
+        }
+        Ok(SseWriter::new(out, chunked, last_event_id))
+    }
+    fn send(&mut self, reader: &mut dyn Read) -> Result<()> {
         const CHUNK_SIZE: usize = 1024;
         let mut buf: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
 
-        self.respond()?;
+        self.send_head()?;
 
-        let stream = self.stream.get_mut();
+        let stream = self.stream.get_mut().get_mut();
         while let Ok(n) = reader.read(&mut buf) {
             if n == 0 {
                 break;
@@ -349,17 +929,6 @@ impl HttpRequest {
         }
         Ok(())
     }
-    /// Respond to the request as a chunked transfer
-    ///
-    /// This means that the Content-Length of the request doen't need to be known.
-    ///
-    /// # Errors
-    /// If some io error is produced while sending the request
-    pub fn respond_chunked(&mut self, reader: &mut dyn Read) -> Result<()> {
-        self.set_header("Transfer-Encoding", "chunked");
-        let mut reader = Chunked::with_default_size(reader);
-        self.respond_reader(&mut reader)
-    }
     /// Respond with a basic HTML error page
     ///
     /// # Errors
@@ -375,7 +944,7 @@ impl HttpRequest {
     /// If some io error is produced while sending the request
     #[inline]
     pub fn ok(&mut self) -> Result<()> {
-        self.set_status(200).respond()
+        self.set_status(Status::OK).respond()
     }
     /// Respond to the request with an 403 FORBIDDEN status
     ///
@@ -383,7 +952,7 @@ impl HttpRequest {
     /// If some io error is produced while sending the request
     #[inline]
     pub fn forbidden(&mut self) -> Result<()> {
-        self.set_status(403).respond_error_page()
+        self.set_status(Status::FORBIDDEN).respond_error_page()
     }
     /// Respond to the request with an 401 UNAUTHORIZED status
     ///
@@ -391,7 +960,7 @@ impl HttpRequest {
     /// If some io error is produced while sending the request
     #[inline]
     pub fn unauthorized(&mut self) -> Result<()> {
-        self.set_status(401).respond_error_page()
+        self.set_status(Status::UNAUTHORIZED).respond_error_page()
     }
     /// Respond to the request with an 404 NOT FOUND status
     ///
@@ -399,7 +968,7 @@ impl HttpRequest {
     /// If some io error is produced while sending the request
     #[inline]
     pub fn not_found(&mut self) -> Result<()> {
-        self.set_status(404).respond_error_page()
+        self.set_status(Status::NOT_FOUND).respond_error_page()
     }
     /// Respond to the request with an 500 INTERNAL SERVER ERROR status
     ///
@@ -407,7 +976,8 @@ impl HttpRequest {
     /// If some io error is produced while sending the request
     #[inline]
     pub fn server_error(&mut self) -> Result<()> {
-        self.set_status(500).respond_error_page()
+        self.set_status(Status::INTERNAL_SERVER_ERROR)
+            .respond_error_page()
     }
     #[inline]
     #[must_use]
@@ -454,6 +1024,7 @@ impl PartialEq for HttpRequest {
             && self.version == other.version
             && self.status == other.status
             && self.body == other.body
+            && self.trailers == other.trailers
     }
 }
 
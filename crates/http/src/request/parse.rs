@@ -1,64 +1,100 @@
-use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader},
+use std::io::BufReader;
+
+use crate::{
+    HeaderMap, HttpError, HttpStream, HttpVersion, ParserLimits, Result, Status,
+    encoding::{BodyKind, BodyReader},
+    err,
+    error::ErrorKind,
+    parser::{Head, Parser, RequestHead},
+    request::HttpRequest,
 };
 
-use crate::{HttpStream, Result, err, request::HttpRequest};
+/// Sends a bare response with the given status, and no body.
+///
+/// This is used when the request is so malformed that
+/// it can't even be passed to a handler.
+fn reject(stream: &mut dyn HttpStream, status: Status) {
+    let msg = status.reason().unwrap_or("");
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status} {msg}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+    let _ = stream.flush();
+}
 
-pub(super) fn parse_request(mut stream: BufReader<Box<dyn HttpStream>>) -> Result<HttpRequest> {
-    let mut line = String::new();
-    /* Parse request line */
-    stream.read_line(&mut line)?;
-    let mut space = line.split_whitespace().take(3);
-    let method = space.next().unwrap_or("").parse()?;
-    let mut url = space.next().unwrap_or("");
-    let mut params = HashMap::new();
-    if url.contains('?') {
-        /* Parse URL */
-        let mut split = url.split('?');
-        let new_url = split.next().unwrap_or("");
-        let query = split.next().unwrap_or("");
-        for arg in query.split('&') {
-            let mut arg = arg.split('=');
-            let k = arg.next().unwrap_or("");
-            let v = arg.next().unwrap_or("");
-            let k = url::decode(k)?.into();
-            let v = url::decode(v)?.into();
-            params.insert(k, v);
+pub(super) fn parse_request(
+    mut stream: BufReader<Box<dyn HttpStream>>,
+    limits: ParserLimits,
+) -> Result<HttpRequest> {
+    let mut parser = Parser::request().with_limits(limits);
+    let head = match parser.read_head(&mut stream) {
+        Ok(Head::Request(head)) => head,
+        Ok(Head::Response(_)) => return err!(kind = Parse, "Expected a request"),
+        Err(err) => {
+            if err.kind() != ErrorKind::Io {
+                reject(&mut **stream.get_mut(), err.status());
+            }
+            return Err(err);
         }
-        url = new_url;
-    }
-    let url = url::decode(url)?.into();
-    let version: f32 = space
-        .next()
-        .unwrap_or("")
-        .replace("HTTP/", "")
-        .parse()
-        .or_else(|_| err!("Could not parse HTTP Version"))?;
-    line.clear();
-    /* Parse Headers */
-    let mut headers = HashMap::new();
-    while stream.read_line(&mut line).is_ok() {
-        let l = line.trim();
-        if l.is_empty() {
-            break;
+    };
+    let kind = match BodyKind::of_request(&head.headers) {
+        Ok(kind) => kind,
+        Err(err) => {
+            reject(&mut **stream.get_mut(), err.status());
+            return Err(err);
+        }
+    };
+    let expect_continue = match expects_continue(&head, kind) {
+        Ok(expect) => expect,
+        Err(err) => {
+            reject(&mut **stream.get_mut(), err.status());
+            return Err(err);
+        }
+    };
+    Ok(from_head(head, stream, kind, limits, expect_continue))
+}
+
+/// Returns true if the client is waiting for a *100 Continue*
+///
+/// # Errors
+/// If the request has an expectation other than `100-continue`
+pub(crate) fn expects_continue(head: &RequestHead, kind: BodyKind) -> Result<bool> {
+    Ok(match head.headers.get("Expect") {
+        None => false,
+        /* HTTP/1.0 clients don't understand 1xx responses */
+        Some(e) if e.trim().eq_ignore_ascii_case("100-continue") => {
+            head.version >= HttpVersion::Http11 && kind != BodyKind::Empty
+        }
+        Some(e) => {
+            return HttpError::with_kind(ErrorKind::Parse, format!("Unsupported expectation: {e}"))
+                .with_status(Status::EXPECTATION_FAILED)
+                .err();
         }
-        let mut splt = l.split(':');
-        let key = splt.next().unwrap_or("").into();
-        let value = splt.next().unwrap_or("").trim().into();
-        headers.insert(key, value);
-        line.clear();
-    }
-    let response_headers = HashMap::new();
-    Ok(HttpRequest {
-        method,
-        url,
-        headers,
-        params,
-        response_headers,
-        version,
-        stream,
-        status: 200,
-        body: None,
     })
 }
+
+/// Builds a request from an already parsed head
+pub(crate) fn from_head(
+    head: RequestHead,
+    stream: BufReader<Box<dyn HttpStream>>,
+    kind: BodyKind,
+    limits: ParserLimits,
+    expect_continue: bool,
+) -> HttpRequest {
+    HttpRequest {
+        method: head.method,
+        url: head.url,
+        headers: head.headers,
+        params: head.params,
+        response_headers: HeaderMap::new(),
+        version: head.version,
+        stream: BodyReader::new(stream, kind).with_limits(limits),
+        status: Status::OK,
+        body: None,
+        body_truncated: false,
+        trailers: HeaderMap::new(),
+        limits,
+        encoding_selector: None,
+        expect_continue,
+    }
+}
@@ -1,12 +1,21 @@
 #![allow(clippy::unwrap_used)]
 
-use std::str::FromStr;
+use std::{io::Read, str::FromStr, sync::Arc};
 
 use crate::{
-    HttpRequest,
+    HeaderMap, HttpRequest, HttpResponse, HttpVersion, ParserLimits, SetCookie, Status,
+    encoding::{ContentCoding, DecompressReader},
     request::HttpMethod::{self, *},
+    sse::Event,
+    stream::shared::{Output, SharedStream},
 };
 
+/// Parses the request, and returns the buffer where the response will be written
+fn shared(req: &str) -> (HttpRequest, Output) {
+    let (stream, output) = SharedStream::new(req);
+    (HttpRequest::parse(stream).unwrap(), output)
+}
+
 #[test]
 fn parse_method() {
     assert!(HttpMethod::from_str("unknown").is_err());
@@ -24,10 +33,10 @@ fn parse_method() {
 
 #[test]
 fn parse_test() {
-    let parsed = HttpRequest::parse("GET / HTTP/1.0\r\nHEADER-TEST: Hello world!\r\n").unwrap();
+    let parsed = HttpRequest::parse("GET / HTTP/1.0\r\nHEADER-TEST: Hello world!\r\n\r\n").unwrap();
     let expected = HttpRequest::builder()
         .url("/")
-        .version(1.0)
+        .version(HttpVersion::Http10)
         .header("HEADER-TEST", "Hello world!")
         .method(HttpMethod::GET)
         .build();
@@ -39,7 +48,7 @@ fn send_to_test() {
     let req = HttpRequest::builder()
         .method(HttpMethod::GET)
         .url("/hello")
-        .version(1.1)
+        .version(HttpVersion::Http11)
         .body("BODY".as_bytes())
         .build();
     let mut b: Vec<u8> = Vec::new();
@@ -49,10 +58,26 @@ fn send_to_test() {
     assert_eq!(s, "GET /hello HTTP/1.1\r\nBODY\r\n");
 }
 
+#[test]
+fn send_head() {
+    let req = HttpRequest::builder()
+        .method(HttpMethod::HEAD)
+        .url("/")
+        .version(HttpVersion::Http11)
+        .build();
+    let (stream, _) = SharedStream::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n");
+    let mut res = req.send_to(stream).unwrap();
+    assert_eq!(res.content_length(), 5);
+    let mut body = Vec::new();
+    assert_eq!(res.write_to(&mut body).unwrap(), 0);
+    assert_eq!(res.body().unwrap(), Some(&[][..]));
+}
+
 #[test]
 fn parse() {
     let req = "GET /index?param=abc&param2=def HTTP/1.0
 HEADER1: header1
+Content-Length: 7
 
 Hellooo";
     let mut req = HttpRequest::parse(req).unwrap();
@@ -64,6 +89,7 @@ Hellooo";
         .method(HttpMethod::GET)
         .url("/index")
         .header("HEADER1", "header1")
+        .header("Content-Length", "7")
         .param("param", "abc")
         .param("param2", "def")
         .body(*b"Hellooo")
@@ -71,3 +97,415 @@ Hellooo";
 
     assert_eq!(req, expected);
 }
+
+#[test]
+fn chunked_body() {
+    let req = "POST /upload HTTP/1.1\r
+Host: localhost\r
+Transfer-Encoding: chunked\r
+Trailer: Checksum\r
+\r
+5;ext=1\r
+Hello\r
+7\r
+, world\r
+0\r
+Checksum: 1234\r
+\r
+";
+    let mut req = HttpRequest::parse(req).unwrap();
+    assert!(req.is_chunked());
+    assert!(req.trailers().is_empty());
+
+    let split = "POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n";
+    assert!(HttpRequest::parse(split).unwrap().is_chunked());
+
+    let mut body = Vec::new();
+    let n = req.read_body(&mut body).unwrap();
+    assert_eq!(n, 12);
+    assert_eq!(body, b"Hello, world");
+    assert_eq!(req.trailer("Checksum"), Some("1234"));
+}
+
+#[test]
+fn header_with_colons() {
+    let req = HttpRequest::parse(
+        "GET / HTTP/1.1\r\nHost: example.com:8080\r\nReferer: http://example.com/a:b\r\n\r\n",
+    )
+    .unwrap();
+    assert_eq!(req.header("host"), Some("example.com:8080"));
+    assert_eq!(req.header("Referer"), Some("http://example.com/a:b"));
+}
+
+#[test]
+fn malformed_header() {
+    for req in [
+        "GET / HTTP/1.1\r\nHost localhost\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost : localhost\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: localhost\r\n folded\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Nul: a\0b\r\n\r\n",
+    ] {
+        assert!(HttpRequest::parse(req).is_err(), "{req:?} should fail");
+    }
+}
+
+#[test]
+fn host_required() {
+    let err = HttpRequest::parse("GET / HTTP/1.1\r\n\r\n").unwrap_err();
+    assert_eq!(err.status(), 400);
+    let err = HttpRequest::parse("GET / HTTP/1.0\r\nHost: a\r\nHost: b\r\n\r\n").unwrap_err();
+    assert_eq!(err.status(), 400);
+    assert!(HttpRequest::parse("GET / HTTP/1.0\r\n\r\n").is_ok());
+}
+
+#[test]
+fn unsupported_version() {
+    let err = HttpRequest::parse("GET / HTTP/1.2\r\nHost: a\r\n\r\n").unwrap_err();
+    assert_eq!(err.status(), 505);
+    let err = HttpRequest::parse("GET / HTTP/2\r\nHost: a\r\n\r\n").unwrap_err();
+    assert_eq!(err.status(), 505);
+}
+
+#[test]
+fn persistence() {
+    let cases = [
+        ("HTTP/1.1", "", true),
+        ("HTTP/1.1", "Connection: close\r\n", false),
+        ("HTTP/1.1", "Connection: Upgrade, Close\r\n", false),
+        ("HTTP/1.0", "", false),
+        ("HTTP/1.0", "Connection: Keep-Alive\r\n", true),
+    ];
+    for (version, header, persistent) in cases {
+        let req = format!("GET / {version}\r\nHost: localhost\r\n{header}\r\n");
+        let req = HttpRequest::parse(req.as_str()).unwrap();
+        assert_eq!(req.is_persistent(), persistent, "{req:?}");
+        assert_eq!(req.response_version(), req.version());
+    }
+}
+
+#[test]
+fn limits() {
+    let limits = ParserLimits::default()
+        .max_request_line(20)
+        .max_header_line(20)
+        .max_headers(2)
+        .max_header_bytes(30);
+    let parse = |req: &str| HttpRequest::parse_with_limits(req, limits);
+
+    assert!(parse("GET / HTTP/1.0\r\nA: 1\r\nB: 2\r\n\r\n").is_ok());
+
+    let err = parse("GET /too/long/for/limit HTTP/1.0\r\n\r\n").unwrap_err();
+    assert_eq!(err.status(), 414);
+
+    for req in [
+        "GET / HTTP/1.0\r\nX-Long: aaaaaaaaaaaaaaaaaaaaa\r\n\r\n",
+        "GET / HTTP/1.0\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n",
+        "GET / HTTP/1.0\r\nA: 11111111111\r\nB: 22222222222\r\n\r\n",
+    ] {
+        let err = parse(req).unwrap_err();
+        assert_eq!(err.status(), 431, "{req:?}");
+    }
+}
+
+#[test]
+fn query_round_trip() {
+    let req = HttpRequest::parse("GET /search?tag=a&flag&tag=b&q=x%20y HTTP/1.0\r\n\r\n").unwrap();
+    assert_eq!(req.params().get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+    assert!(req.params().is_flag("flag"));
+    assert_eq!(req.param("q"), Some("x y"));
+
+    let mut out = Vec::new();
+    req.write_to(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "GET /search?tag=a&flag&tag=b&q=x%20y HTTP/1.0\r\n\r\n"
+    );
+}
+
+#[test]
+fn form() {
+    let req = "POST /login HTTP/1.1\r
+Host: localhost\r
+Content-Type: application/x-www-form-urlencoded; charset=utf-8\r
+Content-Length: 36\r
+\r
+user=john+doe&remember&pass=a%26b%3D";
+    let mut req = HttpRequest::parse(req).unwrap();
+    let form = req.form().unwrap();
+    assert_eq!(form.get("user"), Some("john doe"));
+    assert!(form.is_flag("remember"));
+    assert_eq!(form.get("pass"), Some("a&b="));
+    assert_eq!(req.body().unwrap().map(<[u8]>::len), Some(36));
+
+    let err = req.form_with_limit(10).unwrap_err();
+    assert_eq!(err.status(), 413);
+}
+
+#[test]
+fn form_too_large() {
+    let req = "POST /login HTTP/1.1\r
+Host: localhost\r
+Content-Type: application/x-www-form-urlencoded\r
+Transfer-Encoding: chunked\r
+\r
+14\r
+user=john&pass=abcde\r
+0\r
+\r
+";
+    let mut req = HttpRequest::parse(req).unwrap();
+    let err = req.form_with_limit(10).unwrap_err();
+    assert_eq!(err.status(), 413);
+
+    /* The truncated body is not returned later */
+    assert_eq!(req.form().unwrap_err().status(), 413);
+    assert_eq!(req.body().unwrap_err().status(), 413);
+}
+
+#[test]
+fn form_content_type() {
+    let req = "POST / HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+    let err = HttpRequest::parse(req).unwrap().form().unwrap_err();
+    assert_eq!(err.status(), 415);
+}
+
+#[test]
+fn multipart() {
+    let req = "POST /upload HTTP/1.1\r
+Host: localhost\r
+Content-Type: multipart/form-data; boundary=\"xyz\"\r
+Content-Length: 86\r
+\r
+--xyz\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r
+\r
+abc\r
+--xyz--\r
+";
+    let mut req = HttpRequest::parse(req).unwrap();
+    let mut multipart = req.multipart().unwrap();
+    let mut part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.filename(), Some("a.txt"));
+    let mut content = String::new();
+    std::io::Read::read_to_string(&mut part, &mut content).unwrap();
+    assert_eq!(content, "abc");
+    assert!(multipart.next_part().unwrap().is_none());
+
+    let mut req =
+        HttpRequest::parse("POST / HTTP/1.0\r\nContent-Type: text/plain\r\n\r\n").unwrap();
+    assert_eq!(req.multipart().err().unwrap().status(), 415);
+}
+
+#[test]
+fn cookies() {
+    let req = "GET / HTTP/1.0\r\nCookie: id=123; theme=dark\r\nCookie: lang=es\r\n\r\n";
+    let mut req = HttpRequest::parse(req).unwrap();
+    assert_eq!(req.cookie("theme"), Some("dark"));
+    assert_eq!(req.cookie("lang"), Some("es"));
+    assert_eq!(req.cookie("missing"), None);
+    assert_eq!(req.cookies().count(), 3);
+
+    req.add_cookie(&SetCookie::new("a", "1").unwrap()).unwrap();
+    req.add_cookie(&SetCookie::new("b", "2").unwrap().path("/").unwrap())
+        .unwrap();
+    assert!(SetCookie::new("c", "3").unwrap().path("/\r\nX: y").is_err());
+    let set: Vec<_> = req.response_headers().get_all("set-cookie").collect();
+    assert_eq!(set, ["a=1", "b=2; Path=/"]);
+}
+
+#[test]
+fn encoded_response() {
+    let body = "Hello world! ".repeat(100);
+    let (mut req, output) = shared("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    req.set_encoding_selector(Some(Arc::new(|_| Some(ContentCoding::Gzip))));
+    req.set_header("ETag", "\"abc\"");
+    req.respond_str(&body).unwrap();
+
+    let output = output.lock().unwrap().clone();
+    let mut res = HttpResponse::parse(output).unwrap();
+    assert_eq!(res.header("Content-Encoding"), Some("gzip"));
+    assert_eq!(res.header("Content-Length"), None);
+    assert_eq!(res.header("Transfer-Encoding"), Some("chunked"));
+    assert_eq!(res.header("ETag"), Some("\"abc-gzip\""));
+
+    let mut decoded = String::new();
+    DecompressReader::new(res.body_reader().unwrap(), ContentCoding::Gzip)
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, body);
+
+    /* HTTP/1.0 responses can't be chunked */
+    let (mut req, output) = shared("GET / HTTP/1.0\r\n\r\n");
+    req.set_encoding_selector(Some(Arc::new(|_| Some(ContentCoding::Gzip))));
+    req.respond_str(&body).unwrap();
+    let output = output.lock().unwrap().clone();
+    let res = HttpResponse::parse(output).unwrap();
+    assert_eq!(res.header("Content-Encoding"), None);
+    assert_eq!(res.content_length(), body.len());
+}
+
+#[test]
+fn unread_body() {
+    let req = "POST /a HTTP/1.1\r\nHost: x\r\nContent-Length: 26\r\n\r\n\
+               GET /smuggled HTTP/1.1\r\n\r\n\
+               POST /b HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+               5\r\nhello\r\n0\r\nSum: 1\r\n\r\n\
+               GET /c HTTP/1.1\r\nHost: x\r\n\r\nextra";
+    let req = HttpRequest::parse(req).unwrap();
+    assert_eq!(req.url(), "/a");
+
+    let mut req = req.keep_alive().unwrap();
+    assert_eq!(req.url(), "/b");
+    assert!(req.has_body().unwrap());
+    /* Read only part of the body */
+    let mut buf = [0; 2];
+    req.read_body(&mut &mut buf[..]).unwrap_err();
+
+    let mut req = req.keep_alive().unwrap();
+    assert_eq!(req.url(), "/c");
+    assert!(!req.has_body().unwrap());
+    assert_eq!(req.body().unwrap(), Some(&[][..]));
+}
+
+#[test]
+fn length_and_chunked() {
+    let req = "POST /a HTTP/1.1\r\nHost: x\r\nContent-Length: 35\r\n\
+               Transfer-Encoding: chunked\r\n\r\n\
+               0\r\n\r\n\
+               GET /smuggled HTTP/1.1\r\nHost: x\r\n\r\n";
+    let (stream, output) = SharedStream::new(req);
+    let err = HttpRequest::parse(stream).unwrap_err();
+    assert_eq!(err.status(), 400);
+
+    let output = output.lock().unwrap().clone();
+    let res = HttpResponse::parse(output).unwrap();
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.header("Connection"), Some("close"));
+
+    /* Also on a persistent connection */
+    let req = format!("GET / HTTP/1.1\r\nHost: x\r\n\r\n{req}");
+    let req = HttpRequest::parse(req).unwrap();
+    assert!(req.keep_alive().is_err());
+}
+
+#[test]
+fn unread_body_limit() {
+    let req = "POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 10\r\n\r\n0123456789";
+    let limits = ParserLimits::default().max_unread_body(4);
+    let mut req = HttpRequest::parse_with_limits(req, limits).unwrap();
+    assert_eq!(req.discard_body().unwrap_err().status(), 413);
+
+    let req = "POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 4\r\n\r\n0123";
+    let mut req = HttpRequest::parse_with_limits(req, limits).unwrap();
+    req.discard_body().unwrap();
+    assert!(!req.has_body().unwrap());
+}
+
+#[test]
+fn trailer_limits() {
+    let trailers = "X: y\r\n".repeat(10);
+    let input = format!(
+        "POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n{trailers}\r\n"
+    );
+    let limits = ParserLimits::default().max_headers(4);
+    let mut req = HttpRequest::parse_with_limits(input.as_str(), limits).unwrap();
+    assert_eq!(req.discard_body().unwrap_err().status(), 431);
+
+    let limits = ParserLimits::default().max_header_bytes(48);
+    let mut req = HttpRequest::parse_with_limits(input.as_str(), limits).unwrap();
+    assert_eq!(req.body().unwrap_err().status(), 431);
+}
+
+#[test]
+fn expect_continue() {
+    let req = "POST / HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nContent-Length: 4\r\n\r\nbody";
+    let (mut req, output) = shared(req);
+    assert!(req.expects_continue());
+    assert_eq!(req.body().unwrap().unwrap(), b"body");
+    assert!(!req.expects_continue());
+    req.ok().unwrap();
+
+    let output = output.lock().unwrap().clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n"));
+}
+
+#[test]
+fn expect_continue_declined() {
+    let req = "POST / HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nContent-Length: 4\r\n\r\n";
+    let (mut req, output) = shared(req);
+    req.set_status(Status::CONTENT_TOO_LARGE).respond().unwrap();
+    assert!(req.expects_continue());
+    assert!(req.discard_body().is_err());
+
+    let output = output.lock().unwrap().clone();
+    let res = HttpResponse::parse(output).unwrap();
+    assert_eq!(res.status(), 413);
+    assert_eq!(res.header("Connection"), Some("close"));
+
+    /* HTTP/1.0 clients don't wait for the 100 */
+    let req = "POST / HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 4\r\n\r\nbody";
+    let req = HttpRequest::parse(req).unwrap();
+    assert!(!req.expects_continue());
+}
+
+#[test]
+fn expectation_failed() {
+    let req = "POST / HTTP/1.1\r\nHost: x\r\nExpect: something\r\nContent-Length: 4\r\n\r\nbody";
+    let err = HttpRequest::parse(req).unwrap_err();
+    assert_eq!(err.status(), 417);
+}
+
+#[test]
+fn early_hints() {
+    let (mut req, output) = shared("GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    req.send_early_hints(&[
+        "</style.css>; rel=preload; as=style",
+        "</app.js>; rel=preload",
+    ])
+    .unwrap();
+    assert!(req.send_interim(Status::OK, &HeaderMap::new()).is_err());
+    req.ok().unwrap();
+
+    let output = output.lock().unwrap().clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with(
+        "HTTP/1.1 103 Early Hints\r\n\
+         Link: </style.css>; rel=preload; as=style\r\n\
+         Link: </app.js>; rel=preload\r\n\r\n\
+         HTTP/1.1 200 OK\r\n"
+    ));
+}
+
+#[test]
+fn event_stream() {
+    let (mut req, output) = shared("GET /events HTTP/1.1\r\nHost: x\r\nLast-Event-ID: 3\r\n\r\n");
+    assert_eq!(req.last_event_id(), Some("3"));
+    let mut sse = req.respond_sse().unwrap();
+    assert_eq!(sse.last_event_id(), Some("3"));
+    sse.send(&Event::new("hello").id("4")).unwrap();
+    drop(sse);
+
+    let output = output.lock().unwrap().clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(output.contains("Content-Type: text/event-stream\r\n"));
+    assert!(output.contains("Transfer-Encoding: chunked\r\n"));
+    assert!(output.ends_with("\r\n\r\n13\r\nid: 4\ndata: hello\n\n\r\n0\r\n\r\n"));
+
+    let (mut req, output) = shared("GET /events HTTP/1.0\r\n\r\n");
+    let mut sse = req.respond_sse().unwrap();
+    sse.data("hello").unwrap();
+    drop(sse);
+    let output = output.lock().unwrap().clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Connection: close\r\n"));
+    assert!(output.ends_with("\r\n\r\ndata: hello\n\n"));
+
+    let (mut req, output) = shared("HEAD /events HTTP/1.1\r\nHost: x\r\n\r\n");
+    let mut sse = req.respond_sse().unwrap();
+    assert!(!sse.is_connected());
+    sse.data("hello").unwrap();
+    drop(sse);
+    assert!(output.lock().unwrap().ends_with(b"\r\n\r\n"));
+}
@@ -1,35 +1,35 @@
-use std::{collections::HashMap, io::BufReader};
+use std::io::BufReader;
 
-use crate::{HttpResponse, stream};
+use crate::{HeaderMap, HttpResponse, HttpVersion, Status, stream};
 
 pub struct HttpResponseBuilder {
-    headers: HashMap<Box<str>, Box<str>>,
-    status: u16,
+    headers: HeaderMap,
+    status: Status,
     body: Option<Box<[u8]>>,
-    version: f32,
+    version: HttpVersion,
 }
 
 impl HttpResponseBuilder {
     pub fn new() -> Self {
         Self {
-            headers: HashMap::new(),
-            status: 200,
+            headers: HeaderMap::new(),
+            status: Status::OK,
             body: None,
-            version: 1.0,
+            version: HttpVersion::Http10,
         }
     }
 
     pub fn header(mut self, k: impl Into<Box<str>>, v: impl Into<Box<str>>) -> Self {
-        self.headers.insert(k.into(), v.into());
+        self.headers.insert(k, v);
         self
     }
 
-    pub fn version(mut self, v: f32) -> Self {
+    pub fn version(mut self, v: HttpVersion) -> Self {
         self.version = v;
         self
     }
 
-    pub fn status(mut self, code: u16) -> Self {
+    pub fn status(mut self, code: Status) -> Self {
         self.status = code;
         self
     }
@@ -46,6 +46,9 @@ impl HttpResponseBuilder {
             headers: self.headers,
             stream: BufReader::new(stream::dummy()),
             version: self.version,
+            trailers: HeaderMap::new(),
+            decompress: false,
+            skip_body: false,
         }
     }
 }
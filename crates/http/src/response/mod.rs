@@ -1,23 +1,65 @@
 use core::fmt;
-use std::{
-    collections::HashMap,
-    io::{self, BufRead, BufReader, Read, Write},
-};
+use std::io::{self, BufRead, BufReader, Read, Write};
 
 use parse::parse_response;
 
-use crate::{HttpStream, Result, response::builder::HttpResponseBuilder, stream::IntoHttpStream};
+use crate::{
+    HeaderMap, HttpStream, HttpVersion, Result, Status,
+    cookie::parse_cookies,
+    encoding::{BodyKind, BodyReader, ContentCoding, DecompressReader},
+    err,
+    response::builder::HttpResponseBuilder,
+    stream::IntoHttpStream,
+};
 
 pub mod builder;
 mod parse;
 
+type RawBody<'a> = BodyReader<&'a mut BufReader<Box<dyn HttpStream>>>;
+
+/// [Reader](Read) for the body of an [`HttpResponse`]
+///
+/// If [decompression](HttpResponse::set_decompress) is enabled,
+/// it decodes the body according to its *Content-Encoding*.
+pub struct ResponseBody<'a>(ResponseBodyInner<'a>);
+
+enum ResponseBodyInner<'a> {
+    Raw(RawBody<'a>),
+    Decoded(DecompressReader<RawBody<'a>>),
+}
+
+impl ResponseBody<'_> {
+    /// Returns the trailers of the body, if it was chunked
+    ///
+    /// They are only available after reading the whole body.
+    #[must_use]
+    pub fn into_trailers(self) -> HeaderMap {
+        match self.0 {
+            ResponseBodyInner::Raw(r) => r.into_trailers(),
+            ResponseBodyInner::Decoded(r) => r.into_inner().into_trailers(),
+        }
+    }
+}
+
+impl Read for ResponseBody<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            ResponseBodyInner::Raw(r) => r.read(buf),
+            ResponseBodyInner::Decoded(r) => r.read(buf),
+        }
+    }
+}
+
 /// An Http response
 pub struct HttpResponse {
-    headers: HashMap<Box<str>, Box<str>>,
+    headers: HeaderMap,
     stream: BufReader<Box<dyn HttpStream>>,
-    status: u16,
+    status: Status,
     body: Option<Box<[u8]>>,
-    version: f32,
+    version: HttpVersion,
+    trailers: HeaderMap,
+    decompress: bool,
+    skip_body: bool,
 }
 
 impl fmt::Debug for HttpResponse {
@@ -27,6 +69,7 @@ impl fmt::Debug for HttpResponse {
             .field("status", &self.status)
             .field("body", &self.body)
             .field("version", &self.version)
+            .field("trailers", &self.trailers)
             .finish()
     }
 }
@@ -42,7 +85,7 @@ impl HttpResponse {
     }
     #[inline]
     #[must_use]
-    pub fn status(&self) -> u16 {
+    pub fn status(&self) -> Status {
         self.status
     }
 
@@ -58,21 +101,141 @@ impl HttpResponse {
     #[inline]
     #[must_use]
     pub fn header(&self, key: &str) -> Option<&str> {
-        self.headers.get(key).map(|s| &**s)
+        self.headers.get(key)
+    }
+
+    /// Iterates over the cookies set by the server in
+    /// *Set-Cookie* headers, as (name, value) pairs
+    ///
+    /// The attributes of the cookies are ignored.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .get_all("Set-Cookie")
+            .filter_map(|c| parse_cookies(c.split(';').next().unwrap_or("")).next())
     }
 
     #[inline]
     #[must_use]
-    pub fn version(&self) -> f32 {
+    pub fn version(&self) -> HttpVersion {
         self.version
     }
 
     #[inline]
     #[must_use]
-    pub fn headers(&self) -> &HashMap<Box<str>, Box<str>> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    /// Get the trailer headers of a chunked response
+    ///
+    /// # NOTE
+    /// Trailers are sent after the body, so this map will be empty
+    /// until the body has been read.
+    #[inline]
+    #[must_use]
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    /// Returns how the length of this response's body is determined.
+    ///
+    /// See [RFC 9112, section 6.3](https://www.rfc-editor.org/rfc/rfc9112#section-6.3)
+    ///
+    /// # Errors
+    /// If the *Content-Length* header is invalid
+    pub fn body_kind(&self) -> Result<BodyKind> {
+        if self.skip_body {
+            return Ok(BodyKind::Empty);
+        }
+        BodyKind::of_response(self.status, &self.headers)
+    }
+
+    /// Marks the response as not having a body
+    ///
+    /// A response can't tell by itself if it has a body in some cases, like
+    /// responses to a HEAD request, which have the *Content-Length* of the
+    /// body that a GET would get. [`send_to`](crate::HttpRequest::send_to)
+    /// calls this for the responses to HEAD requests.
+    #[inline]
+    pub fn skip_body(&mut self) {
+        self.skip_body = true;
+    }
+
+    /// Returns the [content coding](ContentCoding) of the body,
+    /// or None if it's not encoded
+    ///
+    /// # Errors
+    /// - If the coding is not supported
+    /// - If the body has more than one coding applied
+    pub fn content_coding(&self) -> Result<Option<ContentCoding>> {
+        let mut codings = self
+            .headers
+            .get_all("Content-Encoding")
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case("identity"));
+        let Some(coding) = codings.next() else {
+            return Ok(None);
+        };
+        if codings.next().is_some() {
+            return err!(
+                kind = UnsupportedMediaType,
+                "Multiple content codings are not supported"
+            );
+        }
+        coding.parse().map(Some)
+    }
+
+    /// Enables or disables the decoding of the body
+    ///
+    /// When enabled, [`body_reader`](Self::body_reader), [`body`](Self::body)
+    /// and [`write_to`](Self::write_to) decode the body according to the
+    /// response's *Content-Encoding*. The raw bytes can still be read
+    /// with [`raw_body_reader`](Self::raw_body_reader)
+    ///
+    /// It's enabled by default for responses to requests built with
+    /// [`compressed`](crate::request::builder::HttpRequestBuilder::compressed)
+    #[inline]
+    pub fn set_decompress(&mut self, decompress: bool) {
+        self.decompress = decompress;
+    }
+
+    /// Returns a [reader](ResponseBody) for the body of this response.
+    ///
+    /// The reader honors the framing of the response, as
+    /// returned by [`body_kind`](Self::body_kind), and stops
+    /// at the end of the body.
+    ///
+    /// If [decompression](Self::set_decompress) is enabled,
+    /// the body is decoded.
+    ///
+    /// # Errors
+    /// - If the *Content-Length* header is invalid
+    /// - If decompression is enabled and the *Content-Encoding*
+    ///   is not supported
+    pub fn body_reader(&mut self) -> Result<ResponseBody<'_>> {
+        let coding = if self.decompress {
+            self.content_coding()?
+        } else {
+            None
+        };
+        let raw = self.raw_body_reader()?;
+        Ok(ResponseBody(match coding {
+            Some(coding) => ResponseBodyInner::Decoded(DecompressReader::new(raw, coding)),
+            None => ResponseBodyInner::Raw(raw),
+        }))
+    }
+
+    /// Returns a [reader](BodyReader) for the body of this response,
+    /// without decoding it.
+    ///
+    /// # Errors
+    /// If the *Content-Length* header is invalid
+    pub fn raw_body_reader(&mut self) -> Result<BodyReader<&mut BufReader<Box<dyn HttpStream>>>> {
+        let kind = self.body_kind()?;
+        Ok(BodyReader::new(&mut self.stream, kind))
+    }
+
     /// Reads the body from the stream into the buffer.
     ///
     /// This method is primarly used by [`body`](Self::body), and
@@ -80,10 +243,10 @@ impl HttpResponse {
     /// the [stream]'s buffer.
     ///
     /// [stream]: HttpStream
-    pub(crate) fn read_body_into_buffer(&mut self) -> std::io::Result<()> {
+    pub(crate) fn read_body_into_buffer(&mut self) -> Result<()> {
         let len = self.content_length();
         let mut buf = Vec::with_capacity(len);
-        self.stream.read_to_end(&mut buf)?;
+        self.read_body(&mut buf)?;
         self.body = Some(buf.into_boxed_slice());
         Ok(())
     }
@@ -130,43 +293,34 @@ impl HttpResponse {
 
     /// Reads the response's body into [writer](Write)
     ///
+    /// See [`write_to`](Self::write_to)
+    ///
     /// # Errors
     /// If, while reading or writing, some io Error is found
     pub fn read_body(&mut self, writer: &mut dyn Write) -> Result<()> {
-        const CHUNK_SIZE: usize = 1024;
-        let mut buf: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
-        let len = self.content_length();
-        let n = len / CHUNK_SIZE;
-        let remainder = len % CHUNK_SIZE;
-
-        for _ in 0..n {
-            self.stream.read_exact(&mut buf)?;
-            writer.write_all(&buf)?;
-        }
-
-        if remainder > 0 {
-            self.stream.read_exact(&mut buf[0..remainder])?;
-            writer.write_all(&buf[0..remainder])?;
-        }
-
+        self.write_to(writer)?;
         Ok(())
     }
 
+    /// Writes the response's body into [writer](Write)
+    ///
+    /// The body is read with a [`body_reader`](Self::body_reader), so the framing
+    /// of the response is removed, and the stream is left at the end of the body.
+    /// If the body is chunked, the trailers are stored in the response.
+    ///
+    /// # Errors
+    /// If, while reading or writing, some io Error is found
     pub fn write_to(&mut self, out: &mut dyn io::Write) -> io::Result<usize> {
-        let mut total = 0;
-        loop {
-            let slice = self.stream.fill_buf()?;
-            if slice.is_empty() {
-                break;
-            }
-            out.write_all(slice)?;
-
-            let len = slice.len();
-            self.stream.consume(len);
-            total += len;
+        let mut body = self
+            .body_reader()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let total = io::copy(&mut body, out);
+        let trailers = body.into_trailers();
+        if !trailers.is_empty() {
+            self.trailers = trailers;
         }
         out.flush()?;
-        Ok(total)
+        Ok(usize::try_from(total?).unwrap_or(usize::MAX))
     }
 }
 
@@ -176,6 +330,7 @@ impl PartialEq for HttpResponse {
             && self.status == other.status
             && self.body == other.body
             && self.version == other.version
+            && self.trailers == other.trailers
     }
 }
 
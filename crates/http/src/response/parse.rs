@@ -1,45 +1,32 @@
-use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader},
-};
+use std::io::BufReader;
 
 use super::HttpResponse;
-use crate::{HttpStream, err};
+use crate::{
+    HeaderMap, HttpStream, Status, err,
+    parser::{Head, Parser},
+};
 
 pub(super) fn parse_response(
     mut stream: BufReader<Box<dyn HttpStream>>,
 ) -> crate::Result<HttpResponse> {
-    let mut line = String::new();
-    /* Parse request line */
-    stream.read_line(&mut line)?;
-    let mut space = line.split_whitespace().take(3);
-    let version: f32 = space
-        .next()
-        .unwrap_or("")
-        .replace("HTTP/", "")
-        .parse()
-        .or_else(|_| err!("Could not parse HTTP Version"))?;
-    let status: u16 = space.next().unwrap_or("").parse()?;
-
-    line.clear();
-    /* Parse Headers */
-    let mut headers = HashMap::new();
-    while stream.read_line(&mut line).is_ok() {
-        let l = line.trim();
-        if l.is_empty() {
-            break;
+    let head = loop {
+        let Head::Response(head) = Parser::response().read_head(&mut stream)? else {
+            return err!(kind = Parse, "Expected a response");
+        };
+        /* Interim responses (like 100 Continue, or 103 Early Hints)
+         * come before the final one. 101 ends the HTTP exchange */
+        if !head.status.is_informational() || head.status == Status::SWITCHING_PROTOCOLS {
+            break head;
         }
-        let mut splt = l.split(':');
-        let key = Box::from(splt.next().unwrap_or(""));
-        let value = Box::from(splt.next().unwrap_or("").trim());
-        headers.insert(key, value);
-        line.clear();
-    }
+    };
     Ok(HttpResponse {
-        headers,
-        version,
+        headers: head.headers,
+        version: head.version,
         stream,
-        status,
+        status: head.status,
         body: None,
+        trailers: HeaderMap::new(),
+        decompress: false,
+        skip_body: false,
     })
 }
@@ -1,4 +1,10 @@
+use std::io::{Read, Write};
+
 use super::HttpResponse;
+use crate::{
+    HttpVersion,
+    encoding::{CompressWriter, ContentCoding},
+};
 
 #[test]
 fn response() {
@@ -18,7 +24,7 @@ aaaaaaaaaaaaaaaaaaaa"#,
     res.read_body_into_buffer().unwrap();
 
     let expected = HttpResponse::builder()
-        .version(1.1)
+        .version(HttpVersion::Http11)
         .header("Date", "1-2-2024")
         .header("Server", "SRV")
         .header("Content-Length", "20")
@@ -30,3 +36,99 @@ aaaaaaaaaaaaaaaaaaaa"#,
 
     assert_eq!(res, expected);
 }
+
+#[test]
+fn chunked_response() {
+    let mut res = HttpResponse::parse(
+        "HTTP/1.1 200 OK\r
+Transfer-Encoding: chunked\r
+\r
+5\r
+Hello\r
+7\r
+, world\r
+0\r
+Server-Timing: 12\r
+\r
+HTTP/1.1 204 No Content\r
+\r
+",
+    )
+    .unwrap();
+
+    let mut body = Vec::new();
+    res.read_body(&mut body).unwrap();
+    assert_eq!(body, b"Hello, world");
+    assert_eq!(res.trailers().get("server-timing"), Some("12"));
+}
+
+#[test]
+fn length_delimited() {
+    let mut res =
+        HttpResponse::parse("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHelloGARBAGE").unwrap();
+    let mut body = Vec::new();
+    let n = res.write_to(&mut body).unwrap();
+    assert_eq!(n, 5);
+    assert_eq!(body, b"Hello");
+}
+
+#[test]
+fn cookies() {
+    let res = HttpResponse::parse(
+        "HTTP/1.1 200 OK\r\nSet-Cookie: a=1; Path=/\r\nSet-Cookie: b=2\r\nContent-Length: 0\r\n\r\n",
+    )
+    .unwrap();
+    let cookies: Vec<_> = res.cookies().collect();
+    assert_eq!(cookies, [("a", "1"), ("b", "2")]);
+}
+
+#[test]
+fn decompress() {
+    for coding in ContentCoding::ALL {
+        let mut body = CompressWriter::new(Vec::new(), coding);
+        body.write_all(b"Hello world!").unwrap();
+        let body = body.finish().unwrap();
+
+        let mut res = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: {coding}\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        res.extend_from_slice(&body);
+
+        let mut raw = HttpResponse::parse(res.clone()).unwrap();
+        assert_eq!(raw.body().unwrap().unwrap(), &body[..]);
+
+        let mut res = HttpResponse::parse(res).unwrap();
+        res.set_decompress(true);
+        assert_eq!(res.content_coding().unwrap(), Some(coding));
+        let mut text = String::new();
+        res.body_reader()
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "Hello world!");
+    }
+}
+
+#[test]
+fn unsupported_coding() {
+    let mut res =
+        HttpResponse::parse("HTTP/1.1 200 OK\r\nContent-Encoding: gzip, br\r\n\r\n").unwrap();
+    res.set_decompress(true);
+    assert_eq!(res.body_reader().err().unwrap().status(), 415);
+
+    let res = HttpResponse::parse("HTTP/1.1 200 OK\r\nContent-Encoding: identity\r\n\r\n").unwrap();
+    assert_eq!(res.content_coding().unwrap(), None);
+}
+
+#[test]
+fn interim_responses() {
+    let res = "HTTP/1.1 100 Continue\r\n\r\n\
+               HTTP/1.1 103 Early Hints\r\nLink: </a.css>; rel=preload\r\n\r\n\
+               HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nOK";
+    let mut res = HttpResponse::parse(res).unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.header("Link"), None);
+    assert_eq!(res.body().unwrap(), Some(&b"OK"[..]));
+}
@@ -0,0 +1,352 @@
+//! Server-Sent Events
+//!
+//! Implements the `text/event-stream` format of the
+//! [HTML Living Standard](https://html.spec.whatwg.org/multipage/server-sent-events.html).
+//!
+//! An [`SseWriter`] is obtained with [`HttpRequest::respond_sse`]. It sends
+//! each [`Event`] as soon as it's written, so the client receives it right away.
+//!
+//! # Example
+//! ```no_run
+//! use std::{thread, time::Duration};
+//! use http::{HttpRequest, sse::Event};
+//!
+//! fn clock(req: &mut HttpRequest) -> http::Result<()> {
+//!     let mut sse = req.respond_sse()?;
+//!     let mut n: u64 = sse.last_event_id().and_then(|id| id.parse().ok()).unwrap_or(0);
+//!     while sse.is_connected() {
+//!         n += 1;
+//!         sse.send(&Event::new(format!("tick {n}")).event("tick").id(n.to_string()))?;
+//!         thread::sleep(Duration::from_secs(1));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::Result;
+
+/// An event of an event stream
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Creates an event with the given data
+    ///
+    /// The data can span multiple lines. Each one is sent in its own `data` field.
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+    /// Sets the type of the event. The client dispatches
+    /// events without a type as "message"
+    #[must_use]
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+    /// Sets the id of the event. If the client reconnects, it
+    /// sends the last id it received in the *Last-Event-ID* header
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+    /// Sets the time the client waits before reconnecting
+    #[must_use]
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+    /// Encodes the event in the `text/event-stream` format
+    ///
+    /// # Errors
+    /// If the type or the id contain a line break, or the id contains a NUL
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let is_line = |s: &str| !s.contains(['\r', '\n']);
+        let mut buf = Vec::new();
+        if let Some(event) = &self.event {
+            if !is_line(event) {
+                return Err("Event type can't contain line breaks".into());
+            }
+            write_field(&mut buf, "event", event);
+        }
+        if let Some(id) = &self.id {
+            if !is_line(id) || id.contains('\0') {
+                return Err("Event id can't contain line breaks or NUL".into());
+            }
+            write_field(&mut buf, "id", id);
+        }
+        if let Some(retry) = self.retry {
+            write_field(&mut buf, "retry", &retry.as_millis().to_string());
+        }
+        for line in split_lines(&self.data) {
+            write_field(&mut buf, "data", line);
+        }
+        buf.push(b'\n');
+        Ok(buf)
+    }
+}
+
+fn write_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(b": ");
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+/// Splits on CRLF, LF and CR, which are all line breaks in an event stream
+fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .flat_map(|l| l.strip_suffix('\r').unwrap_or(l).split('\r'))
+}
+
+/// Returns true if the error means that the client went away
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::WriteZero
+    )
+}
+
+/// Writes events to the client
+///
+/// Every write is flushed immediately. When the client disconnects, the
+/// writes that fail because of it return `Ok`, and [`is_connected`] returns
+/// false from then on, so the handler can stop producing events.
+///
+/// Since a disconnect is only noticed when writing, a handler that
+/// waits a long time between events should send a [`keep_alive`]
+/// every few seconds.
+///
+/// The stream is terminated when the writer is [finished](Self::finish) or dropped.
+///
+/// [`is_connected`]: Self::is_connected
+/// [`keep_alive`]: Self::keep_alive
+pub struct SseWriter<'a> {
+    out: &'a mut dyn Write,
+    chunked: bool,
+    connected: bool,
+    last_event_id: Option<Box<str>>,
+}
+
+impl<'a> SseWriter<'a> {
+    pub(crate) fn new(
+        out: &'a mut dyn Write,
+        chunked: bool,
+        last_event_id: Option<Box<str>>,
+    ) -> Self {
+        Self {
+            out,
+            chunked,
+            connected: true,
+            last_event_id,
+        }
+    }
+    /// Creates a writer that never sends anything. Used to respond to HEAD requests
+    pub(crate) fn closed(out: &'a mut dyn Write) -> Self {
+        Self {
+            out,
+            chunked: false,
+            connected: false,
+            last_event_id: None,
+        }
+    }
+    /// Returns the value of the request's *Last-Event-ID* header
+    ///
+    /// See [`HttpRequest::last_event_id`](crate::HttpRequest::last_event_id)
+    #[must_use]
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+    /// Returns false once the client has disconnected,
+    /// or after the stream is [finished](Self::finish)
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+    /// Sends an event
+    ///
+    /// # Errors
+    /// - If the event can't be [encoded](Event::encode)
+    /// - If writing fails for a reason other than the client disconnecting
+    pub fn send(&mut self, event: &Event) -> Result<()> {
+        let buf = event.encode()?;
+        self.write(&buf)
+    }
+    /// Sends an event with only a `data` field
+    ///
+    /// # Errors
+    /// See [`send`](Self::send)
+    pub fn data(&mut self, data: &str) -> Result<()> {
+        self.send(&Event::new(data))
+    }
+    /// Sends a comment. Clients ignore them
+    ///
+    /// # Errors
+    /// If writing fails for a reason other than the client disconnecting
+    pub fn comment(&mut self, text: &str) -> Result<()> {
+        let mut buf = Vec::new();
+        for line in split_lines(text) {
+            buf.push(b':');
+            buf.extend_from_slice(line.as_bytes());
+            buf.push(b'\n');
+        }
+        buf.push(b'\n');
+        self.write(&buf)
+    }
+    /// Sends an empty comment. This keeps intermediaries from closing
+    /// an idle connection, and lets the writer notice a disconnect.
+    ///
+    /// # Errors
+    /// If writing fails for a reason other than the client disconnecting
+    pub fn keep_alive(&mut self) -> Result<()> {
+        self.write(b":\n\n")
+    }
+    fn write(&mut self, buf: &[u8]) -> Result<()> {
+        if !self.connected {
+            return Ok(());
+        }
+        let out = &mut *self.out;
+        let result = if self.chunked {
+            write!(out, "{:X}\r\n", buf.len())
+                .and_then(|()| out.write_all(buf))
+                .and_then(|()| out.write_all(b"\r\n"))
+        } else {
+            out.write_all(buf)
+        };
+        let result = result.and_then(|()| out.flush());
+        self.check(result)
+    }
+    fn check(&mut self, result: io::Result<()>) -> Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                self.connected = false;
+                if is_disconnect(&err) {
+                    Ok(())
+                } else {
+                    Err(err.into())
+                }
+            }
+        }
+    }
+    /// Ends the stream
+    ///
+    /// # Errors
+    /// If writing fails for a reason other than the client disconnecting
+    pub fn finish(mut self) -> Result<()> {
+        self.end()
+    }
+    fn end(&mut self) -> Result<()> {
+        if !self.connected {
+            return Ok(());
+        }
+        self.connected = false;
+        if self.chunked {
+            let result = self
+                .out
+                .write_all(b"0\r\n\r\n")
+                .and_then(|()| self.out.flush());
+            return self.check(result);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SseWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.end();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use std::{io, time::Duration};
+
+    use super::{Event, SseWriter};
+
+    #[test]
+    fn encode() {
+        let event = Event::new("first\nsecond\r\nthird\rfourth\n")
+            .event("update")
+            .id("42")
+            .retry(Duration::from_secs(3));
+        let expected = "event: update\nid: 42\nretry: 3000\n\
+                        data: first\ndata: second\ndata: third\ndata: fourth\ndata: \n\n";
+        assert_eq!(
+            String::from_utf8(event.encode().unwrap()).unwrap(),
+            expected
+        );
+        assert_eq!(Event::new("").encode().unwrap(), b"data: \n\n");
+
+        assert!(Event::new("").event("a\nb").encode().is_err());
+        assert!(Event::new("").id("a\rb").encode().is_err());
+        assert!(Event::new("").id("a\0b").encode().is_err());
+    }
+
+    #[test]
+    fn chunked() {
+        let mut out = Vec::new();
+        let mut sse = SseWriter::new(&mut out, true, Some("7".into()));
+        assert_eq!(sse.last_event_id(), Some("7"));
+        sse.data("hi").unwrap();
+        sse.comment("ping").unwrap();
+        sse.keep_alive().unwrap();
+        sse.finish().unwrap();
+        assert_eq!(
+            out,
+            b"A\r\ndata: hi\n\n\r\n7\r\n:ping\n\n\r\n3\r\n:\n\n\r\n0\r\n\r\n"
+        );
+
+        let mut out = Vec::new();
+        let mut sse = SseWriter::new(&mut out, false, None);
+        sse.data("hi").unwrap();
+        drop(sse);
+        assert_eq!(out, b"data: hi\n\n");
+    }
+
+    struct Disconnected(io::ErrorKind);
+
+    impl io::Write for Disconnected {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(self.0.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn disconnect() {
+        let mut out = Disconnected(io::ErrorKind::BrokenPipe);
+        let mut sse = SseWriter::new(&mut out, true, None);
+        assert!(sse.is_connected());
+        sse.data("lost").unwrap();
+        assert!(!sse.is_connected());
+        sse.data("ignored").unwrap();
+
+        let mut out = Disconnected(io::ErrorKind::PermissionDenied);
+        let mut sse = SseWriter::new(&mut out, true, None);
+        assert!(sse.keep_alive().is_err());
+        assert!(!sse.is_connected());
+    }
+}
@@ -1,3 +1,226 @@
+use core::{fmt, str::FromStr};
+
+use crate::{HttpError, Result, err};
+
+/// HTTP Status Code
+///
+/// A valid HTTP status code, in the range `100..=599`.
+/// It contains named constants for every code in the
+/// [IANA HTTP Status Code Registry].
+///
+/// # Example
+/// ```
+/// use http::Status;
+///
+/// let status = Status::new(404).unwrap();
+/// assert_eq!(status, Status::NOT_FOUND);
+/// assert_eq!(status.reason(), Some("Not Found"));
+/// assert!(status.is_client_error());
+///
+/// assert!(Status::new(600).is_err());
+/// ```
+///
+/// [IANA HTTP Status Code Registry]: <https://www.iana.org/assignments/http-status-codes/http-status-codes.xhtml>
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Status(u16);
+
+macro_rules! statuses {
+    ($( $name:ident = $code:literal, $phrase:literal; )*) => {
+        impl Status {
+            $(
+                #[doc = concat!("`", $code, " ", $phrase, "`")]
+                pub const $name: Status = Status($code);
+            )*
+
+            /// Returns the canonical reason phrase for this status code,
+            /// or None if the code is not registered.
+            #[must_use]
+            pub const fn reason(self) -> Option<&'static str> {
+                match self.0 {
+                    $( $code => Some($phrase), )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+statuses! {
+    CONTINUE = 100, "Continue";
+    SWITCHING_PROTOCOLS = 101, "Switching Protocols";
+    PROCESSING = 102, "Processing";
+    EARLY_HINTS = 103, "Early Hints";
+    UPLOAD_RESUMPTION_SUPPORTED = 104, "Upload Resumption Supported";
+
+    OK = 200, "OK";
+    CREATED = 201, "Created";
+    ACCEPTED = 202, "Accepted";
+    NON_AUTHORITATIVE_INFORMATION = 203, "Non-Authoritative Information";
+    NO_CONTENT = 204, "No Content";
+    RESET_CONTENT = 205, "Reset Content";
+    PARTIAL_CONTENT = 206, "Partial Content";
+    MULTI_STATUS = 207, "Multi-Status";
+    ALREADY_REPORTED = 208, "Already Reported";
+    IM_USED = 226, "IM Used";
+
+    MULTIPLE_CHOICES = 300, "Multiple Choices";
+    MOVED_PERMANENTLY = 301, "Moved Permanently";
+    FOUND = 302, "Found";
+    SEE_OTHER = 303, "See Other";
+    NOT_MODIFIED = 304, "Not Modified";
+    USE_PROXY = 305, "Use Proxy";
+    TEMPORARY_REDIRECT = 307, "Temporary Redirect";
+    PERMANENT_REDIRECT = 308, "Permanent Redirect";
+
+    BAD_REQUEST = 400, "Bad Request";
+    UNAUTHORIZED = 401, "Unauthorized";
+    PAYMENT_REQUIRED = 402, "Payment Required";
+    FORBIDDEN = 403, "Forbidden";
+    NOT_FOUND = 404, "Not Found";
+    METHOD_NOT_ALLOWED = 405, "Method Not Allowed";
+    NOT_ACCEPTABLE = 406, "Not Acceptable";
+    PROXY_AUTHENTICATION_REQUIRED = 407, "Proxy Authentication Required";
+    REQUEST_TIMEOUT = 408, "Request Timeout";
+    CONFLICT = 409, "Conflict";
+    GONE = 410, "Gone";
+    LENGTH_REQUIRED = 411, "Length Required";
+    PRECONDITION_FAILED = 412, "Precondition Failed";
+    CONTENT_TOO_LARGE = 413, "Content Too Large";
+    URI_TOO_LONG = 414, "URI Too Long";
+    UNSUPPORTED_MEDIA_TYPE = 415, "Unsupported Media Type";
+    RANGE_NOT_SATISFIABLE = 416, "Range Not Satisfiable";
+    EXPECTATION_FAILED = 417, "Expectation Failed";
+    IM_A_TEAPOT = 418, "I'm a teapot";
+    MISDIRECTED_REQUEST = 421, "Misdirected Request";
+    UNPROCESSABLE_CONTENT = 422, "Unprocessable Content";
+    LOCKED = 423, "Locked";
+    FAILED_DEPENDENCY = 424, "Failed Dependency";
+    TOO_EARLY = 425, "Too Early";
+    UPGRADE_REQUIRED = 426, "Upgrade Required";
+    PRECONDITION_REQUIRED = 428, "Precondition Required";
+    TOO_MANY_REQUESTS = 429, "Too Many Requests";
+    REQUEST_HEADER_FIELDS_TOO_LARGE = 431, "Request Header Fields Too Large";
+    UNAVAILABLE_FOR_LEGAL_REASONS = 451, "Unavailable For Legal Reasons";
+
+    INTERNAL_SERVER_ERROR = 500, "Internal Server Error";
+    NOT_IMPLEMENTED = 501, "Not Implemented";
+    BAD_GATEWAY = 502, "Bad Gateway";
+    SERVICE_UNAVAILABLE = 503, "Service Unavailable";
+    GATEWAY_TIMEOUT = 504, "Gateway Timeout";
+    HTTP_VERSION_NOT_SUPPORTED = 505, "HTTP Version Not Supported";
+    VARIANT_ALSO_NEGOTIATES = 506, "Variant Also Negotiates";
+    INSUFFICIENT_STORAGE = 507, "Insufficient Storage";
+    LOOP_DETECTED = 508, "Loop Detected";
+    NOT_EXTENDED = 510, "Not Extended";
+    NETWORK_AUTHENTICATION_REQUIRED = 511, "Network Authentication Required";
+}
+
+impl Status {
+    /// Creates a status from the given code
+    ///
+    /// # Errors
+    /// If the code is outside the `100..=599` range
+    pub fn new(code: u16) -> Result<Self> {
+        if (100..=599).contains(&code) {
+            Ok(Self(code))
+        } else {
+            err!(kind = Parse, "Invalid status code: {code}")
+        }
+    }
+
+    /// Returns the numeric value of this status
+    #[inline]
+    #[must_use]
+    pub const fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Returns true for 1XX status codes
+    #[inline]
+    #[must_use]
+    pub const fn is_informational(self) -> bool {
+        self.0 >= 100 && self.0 < 200
+    }
+
+    /// Returns true for 2XX status codes
+    #[inline]
+    #[must_use]
+    pub const fn is_success(self) -> bool {
+        self.0 >= 200 && self.0 < 300
+    }
+
+    /// Returns true for 3XX status codes
+    #[inline]
+    #[must_use]
+    pub const fn is_redirection(self) -> bool {
+        self.0 >= 300 && self.0 < 400
+    }
+
+    /// Returns true for 4XX status codes
+    #[inline]
+    #[must_use]
+    pub const fn is_client_error(self) -> bool {
+        self.0 >= 400 && self.0 < 500
+    }
+
+    /// Returns true for 5XX status codes
+    #[inline]
+    #[must_use]
+    pub const fn is_server_error(self) -> bool {
+        self.0 >= 500 && self.0 < 600
+    }
+}
+
+impl TryFrom<u16> for Status {
+    type Error = HttpError;
+
+    fn try_from(value: u16) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl FromStr for Status {
+    type Err = HttpError;
+
+    /// Parses a status code, which must be exactly 3 digits
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() != 3 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return err!(kind = Parse, "Invalid status code: \"{s}\"");
+        }
+        Self::new(s.parse()?)
+    }
+}
+
+impl From<Status> for u16 {
+    fn from(value: Status) -> Self {
+        value.0
+    }
+}
+
+impl From<Status> for u64 {
+    fn from(value: Status) -> Self {
+        value.0.into()
+    }
+}
+
+impl PartialEq<u16> for Status {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.0, self.reason().unwrap_or("?"))
+    }
+}
+
 pub trait StatusCode {
     /// Returns true if the status code of the
     /// request represents an OK status (200-300)
@@ -41,51 +264,18 @@ impl<T: TryInto<u64> + Copy> StatusCode for T {
     }
 
     fn status_msg(&self) -> &'static str {
-        let Ok(n) = into!(self) else {
-            return "?";
-        };
-        match n {
-            200 => "OK",
-            201 => "CREATED",
-            202 => "ACCEPTED",
-            203 => "NON-AUTHORITATIVE INFORMATION",
-            204 => "NO CONTENT",
-            205 => "RESET CONTENT",
-            206 => "PARTIAL CONTENT",
-            300 => "MULTIPLE CHOICES",
-            301 => "MOVED PERMANENTLY",
-            302 => "FOUND",
-            303 => "SEE OTHER",
-            304 => "NOT MODIFIED",
-            307 => "TEMPORARY REDIRECT",
-            308 => "PERMANENT REDIRECT",
-            400 => "BAD REQUEST",
-            401 => "UNAUTHORIZED",
-            403 => "FORBIDDEN",
-            404 => "NOT FOUND",
-            405 => "METHOD NOT ALLOWED",
-            406 => "NOT ACCEPTABLE",
-            407 => "PROXY AUTHENTICATION REQUIRED",
-            408 => "REQUEST TIMEOUT",
-            409 => "CONFLICT",
-            410 => "GONE",
-            411 => "LENGTH REQUIRED",
-            412 => "PRECONDITION FAILED",
-            413 => "PAYLOAD TOO LARGE",
-            414 => "URI TOO LONG",
-            415 => "UNSUPPORTED MEDIA TYPE",
-            416 => "REQUESTED RANGE NOT SATISFIABLE",
-            429 => "TOO MANY REQUESTS",
-            501 => "NOT IMPLEMENTED",
-            500 => "INTERNAL SERVER ERROR",
-            _ => "?",
-        }
+        into!(self)
+            .ok()
+            .and_then(|n| u16::try_from(n).ok())
+            .and_then(|n| Status::new(n).ok())
+            .and_then(Status::reason)
+            .unwrap_or("?")
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::StatusCode;
+    use super::{Status, StatusCode};
 
     #[test]
     fn code_test() {
@@ -97,4 +287,38 @@ mod test {
         assert!(510.is_server_err());
         assert!(!600.is_server_err());
     }
+
+    #[test]
+    fn registry() {
+        for (code, msg) in [
+            (100, "Continue"),
+            (101, "Switching Protocols"),
+            (418, "I'm a teapot"),
+            (422, "Unprocessable Content"),
+            (431, "Request Header Fields Too Large"),
+            (502, "Bad Gateway"),
+            (503, "Service Unavailable"),
+            (504, "Gateway Timeout"),
+        ] {
+            assert_eq!(code.status_msg(), msg);
+            assert_eq!(Status::new(code).unwrap().reason(), Some(msg));
+        }
+        assert_eq!(299.status_msg(), "?");
+        assert_eq!(Status::new(299).unwrap().reason(), None);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Status::new(99).is_err());
+        assert!(Status::new(600).is_err());
+        assert_eq!("404".parse::<Status>().unwrap(), Status::NOT_FOUND);
+        assert!("4040".parse::<Status>().is_err());
+        assert!("+40".parse::<Status>().is_err());
+
+        let status = Status::BAD_GATEWAY;
+        assert!(status.is_server_error() && !status.is_client_error());
+        assert!(Status::EARLY_HINTS.is_informational());
+        assert!(Status::PERMANENT_REDIRECT.is_redirection());
+        assert!(status.is_http_err());
+    }
 }
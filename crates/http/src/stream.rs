@@ -69,19 +69,26 @@ impl StringStream {
 
 impl HttpStream for StringStream {}
 
-impl IntoHttpStream for String {
+impl IntoHttpStream for Vec<u8> {
     type Stream = StringStream;
 
     fn into_http_stream(self) -> Self::Stream {
-        let src_vec = self.into_bytes();
         StringStream {
-            input: src_vec,
+            input: self,
             offset: 0,
             output: Vec::new(),
         }
     }
 }
 
+impl IntoHttpStream for String {
+    type Stream = StringStream;
+
+    fn into_http_stream(self) -> Self::Stream {
+        self.into_bytes().into_http_stream()
+    }
+}
+
 impl IntoHttpStream for &str {
     type Stream = StringStream;
 
@@ -132,6 +139,51 @@ pub fn dummy() -> Box<dyn HttpStream> {
     Box::new(DummyStream)
 }
 
+/// Stream that returns some bytes, already read from `inner`,
+/// before reading from `inner` again
+pub(crate) struct PrefixedStream {
+    prefix: io::Cursor<Vec<u8>>,
+    inner: Box<dyn HttpStream>,
+}
+
+impl PrefixedStream {
+    pub(crate) fn new(prefix: Vec<u8>, inner: Box<dyn HttpStream>) -> Self {
+        Self {
+            prefix: io::Cursor::new(prefix),
+            inner,
+        }
+    }
+}
+
+impl Read for PrefixedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.prefix.read(buf)? {
+            0 => self.inner.read(buf),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for PrefixedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl HttpStream for PrefixedStream {
+    fn set_blocking(&mut self) -> io::Result<()> {
+        self.inner.set_blocking()
+    }
+
+    fn set_non_blocking(&mut self, timeout: Duration) -> io::Result<()> {
+        self.inner.set_non_blocking(timeout)
+    }
+}
+
 #[cfg(feature = "tls")]
 impl<C, SD, S: HttpStream> HttpStream for rustls::StreamOwned<C, S>
 where
@@ -146,3 +198,55 @@ where
         self.sock.set_non_blocking(timeout)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod shared {
+    #![allow(clippy::unwrap_used)]
+
+    use std::{
+        io::{self, Read, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use super::HttpStream;
+
+    /// Data written to a [`SharedStream`]
+    pub(crate) type Output = Arc<Mutex<Vec<u8>>>;
+
+    /// Stream that keeps a copy of the data written to it
+    pub(crate) struct SharedStream {
+        input: io::Cursor<Vec<u8>>,
+        output: Output,
+    }
+
+    impl SharedStream {
+        /// Creates a stream that reads `input`, and returns
+        /// it along with the data that gets written to it
+        pub(crate) fn new(input: impl Into<Vec<u8>>) -> (Self, Output) {
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let stream = Self {
+                input: io::Cursor::new(input.into()),
+                output: Arc::clone(&output),
+            };
+            (stream, output)
+        }
+    }
+
+    impl Read for SharedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for SharedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl HttpStream for SharedStream {}
+}
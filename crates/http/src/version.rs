@@ -0,0 +1,131 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{HttpError, Result, Status, err, error::ErrorKind};
+
+/// HTTP Version
+///
+/// # Example
+/// ```
+/// use http::HttpVersion;
+///
+/// let version: HttpVersion = "HTTP/1.1".parse().unwrap();
+/// assert_eq!(version, HttpVersion::Http11);
+/// assert_eq!(version.to_string(), "HTTP/1.1");
+///
+/// assert!("HTTP/1.10".parse::<HttpVersion>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HttpVersion {
+    Http09,
+    Http10,
+    Http11,
+    Http2,
+    Http3,
+}
+
+impl HttpVersion {
+    /// Returns the textual representation of the version,
+    /// as sent in the request and status lines
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpVersion::Http09 => "HTTP/0.9",
+            HttpVersion::Http10 => "HTTP/1.0",
+            HttpVersion::Http11 => "HTTP/1.1",
+            HttpVersion::Http2 => "HTTP/2",
+            HttpVersion::Http3 => "HTTP/3",
+        }
+    }
+
+    /// Returns true if connections are persistent by default on this version.
+    ///
+    /// That's the case since HTTP/1.1. On HTTP/1.0, connections are closed
+    /// after each response, unless the client sends a `Connection: keep-alive` header.
+    #[must_use]
+    pub fn is_persistent_by_default(self) -> bool {
+        self >= HttpVersion::Http11
+    }
+}
+
+impl FromStr for HttpVersion {
+    type Err = HttpError;
+
+    /// Parses an HTTP version
+    ///
+    /// The version must have the exact format `HTTP/<major>.<minor>`.
+    /// HTTP/2 and HTTP/3 can omit the minor version.
+    ///
+    /// # Errors
+    /// - If the version is malformed, the error has a 400 status
+    /// - If it's well formed, but unknown, the error has a 505 status
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "HTTP/0.9" => HttpVersion::Http09,
+            "HTTP/1.0" => HttpVersion::Http10,
+            "HTTP/1.1" => HttpVersion::Http11,
+            "HTTP/2" | "HTTP/2.0" => HttpVersion::Http2,
+            "HTTP/3" | "HTTP/3.0" => HttpVersion::Http3,
+            _ => {
+                let well_formed = s.strip_prefix("HTTP/").is_some_and(|v| {
+                    let v = v.as_bytes();
+                    v.len() == 3 && v[0].is_ascii_digit() && v[1] == b'.' && v[2].is_ascii_digit()
+                });
+                if well_formed {
+                    return HttpError::with_kind(
+                        ErrorKind::NotImplemented,
+                        format!("Unsupported HTTP version \"{s}\""),
+                    )
+                    .with_status(Status::HTTP_VERSION_NOT_SUPPORTED)
+                    .err();
+                }
+                return err!(kind = Parse, "Could not parse HTTP Version \"{s}\"");
+            }
+        })
+    }
+}
+
+impl Display for HttpVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HttpVersion;
+
+    #[test]
+    fn parse() {
+        for v in [
+            HttpVersion::Http09,
+            HttpVersion::Http10,
+            HttpVersion::Http11,
+            HttpVersion::Http2,
+            HttpVersion::Http3,
+        ] {
+            assert_eq!(v.as_str().parse::<HttpVersion>().unwrap(), v);
+        }
+        assert_eq!(
+            "HTTP/2.0".parse::<HttpVersion>().unwrap(),
+            HttpVersion::Http2
+        );
+    }
+
+    #[test]
+    fn strict() {
+        for v in [
+            "HTTP/1.10",
+            "HTTP/1e0",
+            "HTTP/1",
+            "http/1.1",
+            "1.1",
+            "HTTP/ 1.1",
+            "",
+        ] {
+            let err = v.parse::<HttpVersion>().unwrap_err();
+            assert_eq!(err.status(), 400, "{v}");
+        }
+        let err = "HTTP/1.2".parse::<HttpVersion>().unwrap_err();
+        assert_eq!(err.status(), 505);
+    }
+}
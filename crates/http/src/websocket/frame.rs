@@ -0,0 +1,304 @@
+//! WebSocket frames
+//!
+//! See [RFC 6455 Section 5](https://www.rfc-editor.org/rfc/rfc6455#section-5)
+
+use core::fmt;
+use std::{
+    error::Error,
+    io::{self, Read, Write},
+};
+
+use super::CloseCode;
+
+/// Max payload of a control frame
+pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+/// Opcode of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+    /// Opcodes reserved for future use
+    Reserved(u8),
+}
+
+impl Opcode {
+    /// Returns true for Close, Ping and Pong
+    #[must_use]
+    pub fn is_control(self) -> bool {
+        u8::from(self) & 0x8 != 0
+    }
+}
+
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xA => Opcode::Pong,
+            n => Opcode::Reserved(n),
+        }
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+            Opcode::Reserved(n) => n,
+        }
+    }
+}
+
+/// An error found while reading a frame
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    /// The frame violates the protocol
+    Protocol(&'static str),
+    /// The payload is bigger than the allowed maximum
+    TooBig,
+}
+
+impl FrameError {
+    /// Returns the code to close the connection with
+    #[must_use]
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            FrameError::Io(_) => CloseCode::ABNORMAL,
+            FrameError::Protocol(_) => CloseCode::PROTOCOL_ERROR,
+            FrameError::TooBig => CloseCode::MESSAGE_TOO_BIG,
+        }
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(err) => write!(f, "{err}"),
+            FrameError::Protocol(msg) => write!(f, "WebSocket protocol error: {msg}"),
+            FrameError::TooBig => write!(f, "WebSocket message too big"),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+impl From<io::Error> for FrameError {
+    fn from(err: io::Error) -> Self {
+        FrameError::Io(err)
+    }
+}
+
+/// A frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// This is the last fragment of a message
+    pub fin: bool,
+    pub opcode: Opcode,
+    /// Masking key. Frames sent by a client must be masked,
+    /// and frames sent by a server must not.
+    pub mask: Option<[u8; 4]>,
+    /// The payload, unmasked
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Creates an unmasked, final frame
+    pub fn new(opcode: Opcode, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            fin: true,
+            opcode,
+            mask: None,
+            payload: payload.into(),
+        }
+    }
+
+    /// Reads a frame
+    ///
+    /// # Errors
+    /// - If the reader fails, or ends before the frame does
+    /// - If the frame is malformed, uses reserved bits or
+    ///   opcodes, or it's payload exceeds `max_payload`
+    pub fn read(reader: &mut impl Read, max_payload: usize) -> Result<Self, FrameError> {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        /* No extensions are negotiated, so the RSV bits must be 0 */
+        if head[0] & 0x70 != 0 {
+            return Err(FrameError::Protocol("Reserved bits are set"));
+        }
+        let opcode = Opcode::from(head[0] & 0xf);
+        if let Opcode::Reserved(_) = opcode {
+            return Err(FrameError::Protocol("Reserved opcode"));
+        }
+
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u64::from(u16::from_be_bytes(len))
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                let len = u64::from_be_bytes(len);
+                if len >> 63 != 0 {
+                    return Err(FrameError::Protocol("Invalid payload length"));
+                }
+                len
+            }
+            n => u64::from(n),
+        };
+        if opcode.is_control() && (!fin || len > MAX_CONTROL_PAYLOAD as u64) {
+            return Err(FrameError::Protocol("Invalid control frame"));
+        }
+        let len = usize::try_from(len).map_err(|_| FrameError::TooBig)?;
+        if len > max_payload {
+            return Err(FrameError::TooBig);
+        }
+
+        let mask = if head[1] & 0x80 != 0 {
+            let mut key = [0; 4];
+            reader.read_exact(&mut key)?;
+            Some(key)
+        } else {
+            None
+        };
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload)?;
+        if let Some(key) = mask {
+            apply_mask(&mut payload, key);
+        }
+        Ok(Self {
+            fin,
+            opcode,
+            mask,
+            payload,
+        })
+    }
+
+    /// Writes this frame, masking the payload if it has a key
+    ///
+    /// # Errors
+    /// If the writer fails
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let len = self.payload.len();
+        let mut buf = Vec::with_capacity(14 + len);
+        buf.push(u8::from(self.fin) << 7 | u8::from(self.opcode));
+        let mask_bit = if self.mask.is_some() { 0x80 } else { 0 };
+        match len {
+            0..126 => buf.push(mask_bit | len as u8),
+            126..=0xffff => {
+                buf.push(mask_bit | 126);
+                buf.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                buf.push(mask_bit | 127);
+                buf.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let start = buf.len();
+        if let Some(key) = self.mask {
+            buf.extend_from_slice(&key);
+        }
+        buf.extend_from_slice(&self.payload);
+        if let Some(key) = self.mask {
+            apply_mask(&mut buf[start + 4..], key);
+        }
+        writer.write_all(&buf)
+    }
+}
+
+/// Masks, or unmasks, a payload with the given key
+pub fn apply_mask(payload: &mut [u8], key: [u8; 4]) {
+    for (b, k) in payload.iter_mut().zip(key.iter().cycle()) {
+        *b ^= k;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::{Frame, FrameError, Opcode};
+
+    fn read(mut input: &[u8]) -> Result<Frame, FrameError> {
+        Frame::read(&mut input, 1 << 20)
+    }
+
+    #[test]
+    fn rfc_examples() {
+        /* RFC 6455 Section 5.7 */
+        let unmasked = [0x81, 0x05, b'H', b'e', b'l', b'l', b'o'];
+        let frame = read(&unmasked).unwrap();
+        assert_eq!(frame, Frame::new(Opcode::Text, "Hello"));
+
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = read(&masked).unwrap();
+        assert_eq!(frame.payload, b"Hello");
+        assert_eq!(frame.mask, Some([0x37, 0xfa, 0x21, 0x3d]));
+        let mut out = Vec::new();
+        frame.write_to(&mut out).unwrap();
+        assert_eq!(out, masked);
+
+        let first = read(&[0x01, 0x03, b'H', b'e', b'l']).unwrap();
+        assert!(!first.fin);
+        assert_eq!(first.opcode, Opcode::Text);
+        let last = read(&[0x80, 0x02, b'l', b'o']).unwrap();
+        assert!(last.fin);
+        assert_eq!(last.opcode, Opcode::Continuation);
+
+        let ping = read(&[0x89, 0x05, b'H', b'e', b'l', b'l', b'o']).unwrap();
+        assert_eq!(ping.opcode, Opcode::Ping);
+    }
+
+    #[test]
+    fn lengths() {
+        for (len, header) in [
+            (125, &[0x82, 125][..]),
+            (256, &[0x82, 126, 0x01, 0x00]),
+            (65536, &[0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0]),
+        ] {
+            let frame = Frame::new(Opcode::Binary, vec![7; len]);
+            let mut out = Vec::new();
+            frame.write_to(&mut out).unwrap();
+            assert_eq!(&out[..header.len()], header);
+            assert_eq!(out.len(), header.len() + len);
+            assert_eq!(read(&out).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(read(&[0xc1, 0x00]), Err(FrameError::Protocol(_))));
+        assert!(matches!(read(&[0x83, 0x00]), Err(FrameError::Protocol(_))));
+        /* Fragmented control frame */
+        assert!(matches!(read(&[0x09, 0x00]), Err(FrameError::Protocol(_))));
+        /* Control frame too long */
+        assert!(matches!(
+            read(&[0x89, 126, 0x00, 0x7e]),
+            Err(FrameError::Protocol(_))
+        ));
+        assert!(matches!(
+            Frame::read(&mut &[0x82, 0x05][..], 4),
+            Err(FrameError::TooBig)
+        ));
+        assert!(matches!(read(&[0x81, 0x05, b'H']), Err(FrameError::Io(_))));
+    }
+}
@@ -0,0 +1,449 @@
+//! WebSockets
+//!
+//! Server side of [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455).
+//!
+//! A [`WebSocket`] is opened by [accepting](WebSocket::accept) an
+//! HTTP/1.1 upgrade request. After that, the connection carries
+//! [messages](Message) in both directions, until one of the
+//! peers closes it.
+//!
+//! # Example
+//! ```no_run
+//! use http::{HttpRequest, websocket::{Message, WebSocket}};
+//!
+//! fn echo(req: &mut HttpRequest) -> http::Result<()> {
+//!     let mut ws = WebSocket::accept(req)?;
+//!     loop {
+//!         match ws.recv()? {
+//!             Message::Text(text) => ws.send_text(&text)?,
+//!             Message::Binary(data) => ws.send_binary(&data)?,
+//!             Message::Close(_) => return Ok(()),
+//!             Message::Ping(_) | Message::Pong(_) => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+use std::io::{BufReader, Write};
+
+use crate::{
+    HeaderMap, HttpError, HttpMethod, HttpRequest, HttpStream, HttpVersion, Result, Status, err,
+    error::ErrorKind,
+};
+
+pub mod frame;
+use frame::{Frame, FrameError, MAX_CONTROL_PAYLOAD, Opcode};
+mod sha1;
+
+/// Appended to the client's key to compute the *Sec-WebSocket-Accept* header
+pub const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only version of the protocol supported
+pub const VERSION: &str = "13";
+
+/// Default value of [`WebSocket::set_max_message_size`]
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Status code of a Close frame
+///
+/// See [RFC 6455 Section 7.4](https://www.rfc-editor.org/rfc/rfc6455#section-7.4)
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CloseCode(pub u16);
+
+impl CloseCode {
+    pub const NORMAL: Self = Self(1000);
+    pub const GOING_AWAY: Self = Self(1001);
+    pub const PROTOCOL_ERROR: Self = Self(1002);
+    pub const UNSUPPORTED_DATA: Self = Self(1003);
+    /// No code was present. Never sent in a Close frame
+    pub const NO_STATUS: Self = Self(1005);
+    /// The connection was closed without a Close frame. Never sent in a Close frame
+    pub const ABNORMAL: Self = Self(1006);
+    pub const INVALID_PAYLOAD: Self = Self(1007);
+    pub const POLICY_VIOLATION: Self = Self(1008);
+    pub const MESSAGE_TOO_BIG: Self = Self(1009);
+    pub const MANDATORY_EXTENSION: Self = Self(1010);
+    pub const INTERNAL_ERROR: Self = Self(1011);
+
+    /// Returns true if this code can be sent in a Close frame
+    #[must_use]
+    pub fn is_valid(self) -> bool {
+        matches!(self.0, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Payload of a Close frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+/// A message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// A ping from the peer. It's answered automatically
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The peer closed the connection. None if the Close frame had no code
+    Close(Option<CloseFrame>),
+}
+
+/// Returns the value of the *Sec-WebSocket-Accept* header
+/// for the given *Sec-WebSocket-Key*
+///
+/// # Example
+/// ```
+/// use http::websocket::accept_key;
+///
+/// assert_eq!(
+///     accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+///     "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+/// );
+/// ```
+#[must_use]
+pub fn accept_key(key: &str) -> String {
+    let key = format!("{}{GUID}", key.trim());
+    base64::encode(&sha1::sha1(key.as_bytes()))
+}
+
+/// Returns true if `req` asks to upgrade the connection to a WebSocket
+#[must_use]
+pub fn is_upgrade(req: &HttpRequest) -> bool {
+    let has_token = |header: &str, token: &str| {
+        req.headers()
+            .get_all(header)
+            .flat_map(|v| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    };
+    *req.method() == HttpMethod::GET
+        && req.version() == HttpVersion::Http11
+        && has_token("Upgrade", "websocket")
+        && has_token("Connection", "Upgrade")
+}
+
+/// A WebSocket connection
+///
+/// It borrows the stream of the [`HttpRequest`] that opened it.
+pub struct WebSocket<'a> {
+    stream: &'a mut BufReader<Box<dyn HttpStream>>,
+    max_message_size: usize,
+    fragment_size: usize,
+    /// Message being received: it's opcode, and the fragments received
+    partial: Option<(Opcode, Vec<u8>)>,
+    sent_close: bool,
+    received_close: bool,
+}
+
+impl<'a> WebSocket<'a> {
+    /// Accepts a WebSocket upgrade request, responding
+    /// with *101 Switching Protocols*
+    ///
+    /// The status of the request is set to 101, so the
+    /// connection is not reused for more HTTP requests.
+    ///
+    /// # Errors
+    /// - If the request is not a valid [upgrade request](is_upgrade)
+    /// - If the client asks for an unsupported version. The error has a
+    ///   426 status, and the response gets a *Sec-WebSocket-Version* header
+    /// - If the response can't be sent
+    pub fn accept(req: &'a mut HttpRequest) -> Result<Self> {
+        if !is_upgrade(req) {
+            return err!(kind = Parse, "Not a WebSocket upgrade request");
+        }
+        if req.header("Sec-WebSocket-Version").map(str::trim) != Some(VERSION) {
+            req.set_header("Sec-WebSocket-Version", VERSION);
+            return HttpError::with_kind(ErrorKind::Parse, "Unsupported WebSocket version")
+                .with_status(Status::UPGRADE_REQUIRED)
+                .err();
+        }
+        let key = req.header("Sec-WebSocket-Key").unwrap_or("").trim();
+        if !base64::decode(key).is_ok_and(|k| k.len() == 16) {
+            return err!(kind = Parse, "Invalid Sec-WebSocket-Key header");
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Upgrade", "websocket");
+        headers.insert("Connection", "Upgrade");
+        headers.insert("Sec-WebSocket-Accept", accept_key(key));
+        req.send_interim(Status::SWITCHING_PROTOCOLS, &headers)?;
+        req.set_status(Status::SWITCHING_PROTOCOLS);
+
+        Ok(Self {
+            stream: req.stream_mut(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            fragment_size: usize::MAX,
+            partial: None,
+            sent_close: false,
+            received_close: false,
+        })
+    }
+
+    /// Sets the max size of a received message
+    ///
+    /// Bigger messages close the connection with [`CloseCode::MESSAGE_TOO_BIG`].
+    pub fn set_max_message_size(&mut self, size: usize) {
+        self.max_message_size = size;
+    }
+
+    /// Sets the max size of the frames sent
+    ///
+    /// Bigger messages are split into fragments of this size.
+    pub fn set_fragment_size(&mut self, size: usize) {
+        self.fragment_size = size.max(1);
+    }
+
+    /// Returns true if the Close handshake started
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.sent_close || self.received_close
+    }
+
+    /// Receives the next message
+    ///
+    /// Pings are answered with a Pong before returning them.
+    /// When a Close is received, it's echoed back, and no more
+    /// messages can be received.
+    ///
+    /// # Errors
+    /// - If the connection was already closed
+    /// - If the peer violates the protocol. In that case, the
+    ///   connection is closed with the appropriate code.
+    /// - If an IO error happens
+    pub fn recv(&mut self) -> Result<Message> {
+        if self.received_close {
+            return err!("The WebSocket is closed");
+        }
+        loop {
+            let frame = match Frame::read(self.stream, self.max_message_size) {
+                Ok(frame) => frame,
+                Err(err) => return Err(self.fail(err)),
+            };
+            if frame.mask.is_none() {
+                return Err(self.fail(FrameError::Protocol("Unmasked client frame")));
+            }
+            let payload = frame.payload;
+            match frame.opcode {
+                Opcode::Text | Opcode::Binary => {
+                    if self.partial.is_some() {
+                        let err = FrameError::Protocol("Expected a continuation frame");
+                        return Err(self.fail(err));
+                    }
+                    if frame.fin {
+                        return self.message(frame.opcode, payload);
+                    }
+                    self.partial = Some((frame.opcode, payload));
+                }
+                Opcode::Continuation => {
+                    let Some((opcode, buf)) = &mut self.partial else {
+                        let err = FrameError::Protocol("Unexpected continuation frame");
+                        return Err(self.fail(err));
+                    };
+                    if buf.len() + payload.len() > self.max_message_size {
+                        return Err(self.fail(FrameError::TooBig));
+                    }
+                    buf.extend_from_slice(&payload);
+                    if frame.fin {
+                        let opcode = *opcode;
+                        let buf = core::mem::take(buf);
+                        self.partial = None;
+                        return self.message(opcode, buf);
+                    }
+                }
+                Opcode::Ping => {
+                    if !self.sent_close {
+                        self.write_frame(&Frame::new(Opcode::Pong, payload.clone()))?;
+                    }
+                    return Ok(Message::Ping(payload));
+                }
+                Opcode::Pong => return Ok(Message::Pong(payload)),
+                Opcode::Close => return self.on_close(&payload),
+                Opcode::Reserved(_) => unreachable!("Rejected by Frame::read"),
+            }
+        }
+    }
+
+    fn message(&mut self, opcode: Opcode, payload: Vec<u8>) -> Result<Message> {
+        if opcode == Opcode::Binary {
+            return Ok(Message::Binary(payload));
+        }
+        match String::from_utf8(payload) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => Err(self.fail_with(CloseCode::INVALID_PAYLOAD, "Invalid UTF-8 text")),
+        }
+    }
+
+    fn on_close(&mut self, payload: &[u8]) -> Result<Message> {
+        let close = match payload {
+            [] => None,
+            [_] => return Err(self.fail(FrameError::Protocol("Invalid Close payload"))),
+            [a, b, reason @ ..] => {
+                let code = CloseCode(u16::from_be_bytes([*a, *b]));
+                if !code.is_valid() {
+                    return Err(self.fail(FrameError::Protocol("Invalid close code")));
+                }
+                let Ok(reason) = String::from_utf8(reason.to_vec()) else {
+                    return Err(self.fail_with(CloseCode::INVALID_PAYLOAD, "Invalid UTF-8 reason"));
+                };
+                Some(CloseFrame { code, reason })
+            }
+        };
+        self.received_close = true;
+        if !self.sent_close {
+            let code = close.as_ref().map_or(CloseCode::NORMAL, |c| c.code);
+            self.send_close(code, "")?;
+        }
+        Ok(Message::Close(close))
+    }
+
+    /// Closes the connection after an error
+    fn fail(&mut self, err: FrameError) -> HttpError {
+        match err {
+            FrameError::Io(err) => {
+                self.received_close = true;
+                self.sent_close = true;
+                err.into()
+            }
+            _ => self.fail_with(err.close_code(), &err.to_string()),
+        }
+    }
+
+    fn fail_with(&mut self, code: CloseCode, msg: &str) -> HttpError {
+        if !self.sent_close {
+            let _ = self.send_close(code, "");
+        }
+        self.received_close = true;
+        HttpError::with_kind(ErrorKind::Parse, format!("WebSocket error ({code}): {msg}"))
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        let stream = self.stream.get_mut();
+        frame.write_to(stream)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn send_data(&mut self, opcode: Opcode, data: &[u8]) -> Result<()> {
+        if self.sent_close {
+            return err!("The WebSocket is closed");
+        }
+        let mut chunks = data.chunks(self.fragment_size).peekable();
+        let mut frame = Frame::new(opcode, Vec::new());
+        loop {
+            frame.payload.clear();
+            frame
+                .payload
+                .extend_from_slice(chunks.next().unwrap_or_default());
+            frame.fin = chunks.peek().is_none();
+            self.write_frame(&frame)?;
+            if frame.fin {
+                return Ok(());
+            }
+            frame.opcode = Opcode::Continuation;
+        }
+    }
+
+    /// Sends a text message
+    ///
+    /// # Errors
+    /// If the connection is closed, or an IO error happens
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send_data(Opcode::Text, text.as_bytes())
+    }
+
+    /// Sends a binary message
+    ///
+    /// # Errors
+    /// If the connection is closed, or an IO error happens
+    pub fn send_binary(&mut self, data: &[u8]) -> Result<()> {
+        self.send_data(Opcode::Binary, data)
+    }
+
+    /// Sends a message
+    ///
+    /// Sending a [`Message::Close`] is the same as calling [`close`](Self::close).
+    ///
+    /// # Errors
+    /// If the connection is closed, or an IO error happens
+    pub fn send(&mut self, msg: &Message) -> Result<()> {
+        match msg {
+            Message::Text(text) => self.send_text(text),
+            Message::Binary(data) => self.send_binary(data),
+            Message::Ping(data) => self.ping(data),
+            Message::Pong(data) => self.control(Opcode::Pong, data.clone()),
+            Message::Close(None) => self.close(CloseCode::NORMAL, ""),
+            Message::Close(Some(close)) => self.close(close.code, &close.reason),
+        }
+    }
+
+    /// Sends a Ping
+    ///
+    /// # Errors
+    /// - If the payload is bigger than 125 bytes
+    /// - If the connection is closed, or an IO error happens
+    pub fn ping(&mut self, data: &[u8]) -> Result<()> {
+        self.control(Opcode::Ping, data.to_vec())
+    }
+
+    fn control(&mut self, opcode: Opcode, payload: Vec<u8>) -> Result<()> {
+        if self.sent_close {
+            return err!("The WebSocket is closed");
+        }
+        if payload.len() > MAX_CONTROL_PAYLOAD {
+            return err!("Control frames can't be bigger than {MAX_CONTROL_PAYLOAD} bytes");
+        }
+        self.write_frame(&Frame::new(opcode, payload))
+    }
+
+    fn send_close(&mut self, code: CloseCode, reason: &str) -> Result<()> {
+        let mut payload = Vec::with_capacity(2 + reason.len());
+        payload.extend_from_slice(&code.0.to_be_bytes());
+        payload.extend_from_slice(reason.as_bytes());
+        self.control(Opcode::Close, payload)?;
+        self.sent_close = true;
+        Ok(())
+    }
+
+    /// Starts the Close handshake, and waits for the peer's Close
+    ///
+    /// The messages received meanwhile are discarded.
+    /// If the handshake was already started, it does nothing.
+    ///
+    /// # Errors
+    /// - If the code can't be sent, or the reason is too long
+    /// - If an IO error happens
+    pub fn close(&mut self, code: CloseCode, reason: &str) -> Result<()> {
+        if self.sent_close {
+            return Ok(());
+        }
+        if !code.is_valid() {
+            return err!("Invalid close code: {code}");
+        }
+        if reason.len() > MAX_CONTROL_PAYLOAD - 2 {
+            return err!("Close reason too long");
+        }
+        self.send_close(code, reason)?;
+        while !self.received_close {
+            self.recv()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,182 @@
+#![allow(clippy::unwrap_used)]
+
+use super::{
+    CloseCode, CloseFrame, Message, WebSocket,
+    frame::{Frame, Opcode},
+    is_upgrade,
+};
+use crate::{
+    HttpRequest, Status,
+    stream::shared::{Output, SharedStream},
+};
+
+const HANDSHAKE: &str = "GET /chat HTTP/1.1\r\n\
+                         Host: server.example.com\r\n\
+                         Upgrade: websocket\r\n\
+                         Connection: keep-alive, Upgrade\r\n\
+                         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                         Sec-WebSocket-Version: 13\r\n\r\n";
+
+/// Parses the request, followed by the given client frames
+fn shared(req: &str, frames: &[Frame]) -> (HttpRequest, Output) {
+    let mut input = req.as_bytes().to_vec();
+    for frame in frames {
+        frame.write_to(&mut input).unwrap();
+    }
+    let (stream, output) = SharedStream::new(input);
+    (HttpRequest::parse(stream).unwrap(), output)
+}
+
+fn client(opcode: Opcode, fin: bool, payload: &[u8]) -> Frame {
+    Frame {
+        fin,
+        opcode,
+        mask: Some([1, 2, 3, 4]),
+        payload: payload.to_vec(),
+    }
+}
+
+fn close(code: CloseCode, reason: &str) -> Frame {
+    let mut payload = code.0.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason.as_bytes());
+    client(Opcode::Close, true, &payload)
+}
+
+/// Returns the head of the response, and the frames sent after it
+fn response(output: &Output) -> (String, Vec<Frame>) {
+    let output = output.lock().unwrap();
+    let end = output.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8(output[..end].to_vec()).unwrap();
+    let mut rest = &output[end..];
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        frames.push(Frame::read(&mut rest, usize::MAX).unwrap());
+    }
+    (head, frames)
+}
+
+#[test]
+fn handshake() {
+    let (mut req, output) = shared(HANDSHAKE, &[]);
+    assert!(is_upgrade(&req));
+    let ws = WebSocket::accept(&mut req).unwrap();
+    assert!(!ws.is_closed());
+    assert_eq!(req.status(), Status::SWITCHING_PROTOCOLS);
+
+    let (head, frames) = response(&output);
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(head.contains("Upgrade: websocket\r\n"));
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    assert!(frames.is_empty());
+}
+
+#[test]
+fn invalid_handshake() {
+    let (mut req, _) = shared(&HANDSHAKE.replace("Version: 13", "Version: 8"), &[]);
+    let err = WebSocket::accept(&mut req).err().unwrap();
+    assert_eq!(err.status(), Status::UPGRADE_REQUIRED);
+    assert_eq!(
+        req.response_headers().get("Sec-WebSocket-Version"),
+        Some("13")
+    );
+
+    let (mut req, _) = shared(
+        &HANDSHAKE.replace("dGhlIHNhbXBsZSBub25jZQ==", "c2hvcnQ="),
+        &[],
+    );
+    assert!(WebSocket::accept(&mut req).is_err());
+
+    let (req, _) = shared(&HANDSHAKE.replace("GET", "POST"), &[]);
+    assert!(!is_upgrade(&req));
+    let h2c = HANDSHAKE.replace("Upgrade: websocket", "Upgrade: h2c");
+    let (req, _) = shared(&h2c, &[]);
+    assert!(!is_upgrade(&req));
+}
+
+#[test]
+fn messages() {
+    let frames = [
+        client(Opcode::Text, true, b"Hello"),
+        client(Opcode::Binary, false, b"\x01\x02"),
+        client(Opcode::Ping, true, b"ping"),
+        client(Opcode::Continuation, true, b"\x03"),
+        close(CloseCode::GOING_AWAY, "bye"),
+    ];
+    let (mut req, output) = shared(HANDSHAKE, &frames);
+    let mut ws = WebSocket::accept(&mut req).unwrap();
+    assert_eq!(ws.recv().unwrap(), Message::Text("Hello".into()));
+    ws.send_text("Hi").unwrap();
+    assert_eq!(ws.recv().unwrap(), Message::Ping(b"ping".to_vec()));
+    assert_eq!(ws.recv().unwrap(), Message::Binary(vec![1, 2, 3]));
+    assert_eq!(
+        ws.recv().unwrap(),
+        Message::Close(Some(CloseFrame {
+            code: CloseCode::GOING_AWAY,
+            reason: "bye".into()
+        }))
+    );
+    assert!(ws.is_closed());
+    assert!(ws.recv().is_err());
+    assert!(ws.send_text("Too late").is_err());
+
+    let (_, frames) = response(&output);
+    assert_eq!(frames[0], Frame::new(Opcode::Text, "Hi"));
+    assert_eq!(frames[1], Frame::new(Opcode::Pong, "ping"));
+    assert_eq!(frames[2], Frame::new(Opcode::Close, [0x03, 0xe9]));
+    assert_eq!(frames.len(), 3);
+}
+
+#[test]
+fn fragmented_send() {
+    let (mut req, output) = shared(HANDSHAKE, &[close(CloseCode::NORMAL, "")]);
+    let mut ws = WebSocket::accept(&mut req).unwrap();
+    ws.set_fragment_size(4);
+    ws.send_binary(b"0123456789").unwrap();
+    ws.close(CloseCode::NORMAL, "done").unwrap();
+
+    let (_, frames) = response(&output);
+    let fragments: Vec<(bool, Opcode, &[u8])> = frames
+        .iter()
+        .map(|f| (f.fin, f.opcode, &f.payload[..]))
+        .collect();
+    assert_eq!(fragments, [
+        (false, Opcode::Binary, &b"0123"[..]),
+        (false, Opcode::Continuation, b"4567"),
+        (true, Opcode::Continuation, b"89"),
+        (true, Opcode::Close, b"\x03\xe8done"),
+    ]);
+}
+
+#[test]
+fn protocol_errors() {
+    let cases = [
+        (
+            Frame::new(Opcode::Text, "unmasked"),
+            CloseCode::PROTOCOL_ERROR,
+        ),
+        (
+            client(Opcode::Text, true, b"\xff"),
+            CloseCode::INVALID_PAYLOAD,
+        ),
+        (
+            client(Opcode::Continuation, true, b"x"),
+            CloseCode::PROTOCOL_ERROR,
+        ),
+        (close(CloseCode::NO_STATUS, ""), CloseCode::PROTOCOL_ERROR),
+        (
+            client(Opcode::Binary, true, &[0; 11]),
+            CloseCode::MESSAGE_TOO_BIG,
+        ),
+    ];
+    for (frame, code) in cases {
+        let (mut req, output) = shared(HANDSHAKE, &[frame]);
+        let mut ws = WebSocket::accept(&mut req).unwrap();
+        ws.set_max_message_size(10);
+        assert!(ws.recv().is_err());
+        assert!(ws.is_closed());
+
+        let (_, frames) = response(&output);
+        assert_eq!(frames[0].opcode, Opcode::Close);
+        assert_eq!(frames[0].payload, code.0.to_be_bytes());
+    }
+}
@@ -1,6 +1,6 @@
 extern crate server;
 
-use http::HttpRequest;
+use http::{HttpRequest, Status};
 use server::{handler::Handler, http};
 
 #[unsafe(no_mangle)]
@@ -15,13 +15,13 @@ pub extern "C" fn init_handler(handler: Option<&mut Handler>) {
                         Ok(num) => num,
                         Err(err) => {
                             return req
-                                .set_status(400)
+                                .set_status(Status::BAD_REQUEST)
                                 .respond_str(&format!("Error parsing '{}': {err}", $n));
                         }
                     },
                     None => {
                         return req
-                            .set_status(400)
+                            .set_status(Status::BAD_REQUEST)
                             .respond_str(concat!("Missing numeric argument ", $n));
                     }
                 }
@@ -10,11 +10,17 @@ use std::{
     time::Duration,
 };
 
+use http::ParserLimits;
 use jsonrs::Json;
 use pool::PoolConfig;
 
 use crate::{
-    Result,
+    HttpError, Result,
+    handler::{
+        FileConfig,
+        cache::{CacheMatcher, CachePreset, CacheRule, MAX_EXPIRES},
+        conditional::ETagMode,
+    },
     log::{self},
     log_info, log_warn,
 };
@@ -25,8 +31,15 @@ pub struct ServerConfig {
     pub pool_conf: PoolConfig,
     pub keep_alive_timeout: Duration,
     pub keep_alive_requests: u16,
+    pub parser_limits: ParserLimits,
     pub log_file: Option<String>,
     pub setup_lib: Option<String>,
+    /// Serve HTTP/2 connections
+    pub http2: bool,
+    /// Compress the responses, with the default [`Compression`](crate::handler::Compression) settings
+    pub compression: bool,
+    /// Configuration of the static file handlers
+    pub files: FileConfig,
 
     #[cfg(feature = "tls")]
     pub tls_config: Option<Arc<rustls::ServerConfig>>,
@@ -39,7 +52,11 @@ impl fmt::Debug for ServerConfig {
             .field("pool_conf", &self.pool_conf)
             .field("keep_alive_timeout", &self.keep_alive_timeout)
             .field("keep_alive_requests", &self.keep_alive_requests)
+            .field("parser_limits", &self.parser_limits)
             .field("setup_lib", &self.setup_lib)
+            .field("http2", &self.http2)
+            .field("compression", &self.compression)
+            .field("files", &self.files)
             .field("log_file", &self.log_file);
 
         #[cfg(feature = "tls")]
@@ -91,10 +108,12 @@ fn get_tls_config(cert: Option<String>, pkey: Option<String>) -> Result<Arc<rust
         .map(|cert| cert.unwrap())
         .collect();
     let private_key = PrivateKeyDer::from_pem_file(pkey).unwrap();
-    let config = rustls::ServerConfig::builder()
+    let mut config = rustls::ServerConfig::builder()
         .with_no_client_auth()
         .with_single_cert(certs, private_key)
         .map_err(|err| format!("rustls: {err}"))?;
+    /* h2 is removed by the server if HTTP/2 is disabled */
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
     Ok(Arc::new(config))
 }
@@ -191,6 +210,9 @@ impl ServerConfig {
                 }
 
                 "--setup-lib" => conf.setup_lib = Some(parse_next!()),
+                "--no-http2" => conf.http2 = false,
+                "--compression" => conf.compression = true,
+                "--etag" => conf.files.etag_mode = parse_next!(as ETagMode),
 
                 #[cfg(feature = "tls")]
                 "--tls" => tls = true,
@@ -313,6 +335,21 @@ impl ServerConfig {
                 "keep_alive_timeout" => self.keep_alive_timeout = Duration::from_secs_f64(num!()),
                 "keep_alive_requests" => self.keep_alive_requests = num!() as u16,
                 "log_file" => self.log_file = Some(string!()),
+                "http2" => self.http2 = bool!(v),
+                "compression" => self.compression = bool!(v),
+                "etag" => self.files.etag_mode = string!().parse()?,
+                "cache" => {
+                    let Json::Array(rules) = v else {
+                        return Err(format!(
+                            "Parsing config file ({conf_str}): Expected array for \"{k}\""
+                        )
+                        .into());
+                    };
+                    self.files.cache_rules = rules
+                        .iter()
+                        .map(|rule| parse_cache_rule(rule, conf_str))
+                        .collect::<Result<_>>()?;
+                }
                 "log_level" => {
                     let n = num!(v as u8);
                     log::set_level(n.try_into()?);
@@ -330,6 +367,21 @@ impl ServerConfig {
                         }
                     }
                 }
+                "parser_limits" => {
+                    for (k, v) in obj!() {
+                        let limits = &mut self.parser_limits;
+                        match &**k {
+                            "max_request_line" => limits.max_request_line = num!(v as usize),
+                            "max_header_line" => limits.max_header_line = num!(v as usize),
+                            "max_headers" => limits.max_headers = num!(v as usize),
+                            "max_header_bytes" => limits.max_header_bytes = num!(v as usize),
+                            "max_unread_body" => limits.max_unread_body = num!(v as usize),
+                            _ => log_warn!(
+                                "Parsing config file ({conf_str}): Unexpected key: \"{k}\""
+                            ),
+                        }
+                    }
+                }
                 "pool_config" => {
                     for (k, v) in obj!() {
                         match &**k {
@@ -379,6 +431,24 @@ impl ServerConfig {
         self.keep_alive_requests = n;
         self
     }
+    #[inline]
+    #[must_use]
+    pub fn parser_limits(mut self, limits: ParserLimits) -> Self {
+        self.parser_limits = limits;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
 }
 
 fn help() -> ! {
@@ -404,6 +474,9 @@ PARAMETERS:
     --log-level <n> Set log level
     --setup-lib <file> Load the given file to setup the server
     --conf <file>   Use the given config file instead of the default one
+    --no-http2      Serve only HTTP/1.x connections
+    --compression   Compress responses for clients that accept it
+    --etag <mode>   How ETags are computed: metadata (default) or hash
     --license       Output the license of this program
 
     --tls           Enable TLS
@@ -422,6 +495,66 @@ fn license() -> ! {
     process::exit(0);
 }
 
+/// Parses an element of the "cache" array of the config file
+fn parse_cache_rule(rule: &Json, conf_str: &str) -> Result<CacheRule> {
+    let error =
+        |msg: String| -> HttpError { format!("Parsing config file ({conf_str}): {msg}").into() };
+    let obj = rule
+        .object()
+        .ok_or_else(|| error("Expected object for cache rule".into()))?;
+
+    let mut matcher = None;
+    let mut preset = None;
+    let mut cache_control = None;
+    let mut expires = None;
+    let mut vary = None;
+    for (k, v) in obj {
+        let string = || {
+            v.string()
+                .map(Box::<str>::from)
+                .ok_or_else(|| error(format!("Expected string for \"{k}\"")))
+        };
+        match &**k {
+            "prefix" => matcher = Some(CacheMatcher::Prefix(string()?)),
+            "glob" => matcher = Some(CacheMatcher::Glob(string()?)),
+            "mime" => matcher = Some(CacheMatcher::Mime(string()?)),
+            "preset" => preset = Some(string()?.parse::<CachePreset>()?),
+            "cache_control" => cache_control = Some(string()?),
+            "expires" => {
+                let secs = v
+                    .number()
+                    .filter(|secs| (0.0..=MAX_EXPIRES.as_secs_f64()).contains(secs))
+                    .ok_or_else(|| {
+                        error(format!(
+                            "Expected a number of seconds between 0 and {} for \"{k}\"",
+                            MAX_EXPIRES.as_secs()
+                        ))
+                    })?;
+                expires = Some(Duration::from_secs_f64(secs));
+            }
+            "vary" => vary = Some(string()?),
+            _ => log_warn!("Parsing config file ({conf_str}): Unexpected key: \"{k}\""),
+        }
+    }
+
+    let matcher = matcher
+        .ok_or_else(|| error("Cache rule needs a \"prefix\", \"glob\" or \"mime\"".into()))?;
+    let mut rule = CacheRule::new(matcher);
+    if let Some(preset) = preset {
+        rule = rule.preset(preset);
+    }
+    if let Some(directives) = cache_control {
+        rule = rule.cache_control(directives);
+    }
+    if let Some(offset) = expires {
+        rule = rule.expires(offset);
+    }
+    if let Some(fields) = vary {
+        rule = rule.vary(&fields);
+    }
+    Ok(rule)
+}
+
 trait ParseIterator {
     fn next_parse<T: FromStr>(&mut self) -> Option<T>;
 }
@@ -442,6 +575,10 @@ impl Default for ServerConfig {
     /// - Nº Workers: 1024
     /// - Keep Alive Timeout: 0s (Disabled)
     /// - Keep Alove Requests: 10000
+    /// - Parser Limits: [`ParserLimits::default`]
+    /// - HTTP/2: Enabled
+    /// - Compression: Disabled
+    /// - Static files: [`FileConfig::default`]
     #[inline]
     fn default() -> Self {
         Self {
@@ -449,8 +586,12 @@ impl Default for ServerConfig {
             pool_conf: PoolConfig::default(),
             keep_alive_timeout: Duration::from_secs(0),
             keep_alive_requests: 10000,
+            parser_limits: ParserLimits::default(),
             log_file: None,
             setup_lib: None,
+            http2: true,
+            compression: false,
+            files: FileConfig::default(),
             #[cfg(feature = "tls")]
             tls_config: None,
         }
@@ -478,6 +619,13 @@ mod test {
         };
     }
 
+    #[test]
+    fn compression() {
+        assert!(!ServerConfig::default().compression);
+        let conf = ServerConfig::parse(&["--compression"]).unwrap();
+        assert!(conf.compression);
+    }
+
     #[test]
     fn unknown() {
         let conf = vec!["?"];
@@ -0,0 +1,326 @@
+//! Caching headers
+//!
+//! [Rules](CacheRule) that add *Cache-Control*, *Expires* and *Vary*
+//! headers to the responses of the static file handlers.
+//!
+//! They're set in the [`cache_rules`](super::FileConfig::cache_rules)
+//! of the handlers' configuration.
+
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use http::date::format_http_date;
+
+use super::compression::add_vary;
+use crate::{HttpError, HttpRequest};
+
+/// Max offset of an *Expires* header, of one year.
+///
+/// Caches don't need dates further in the future than that.
+pub const MAX_EXPIRES: Duration = Duration::from_hours(365 * 24);
+
+/// Selects the responses a [`CacheRule`] applies to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheMatcher {
+    /// URLs that start with the given prefix
+    Prefix(Box<str>),
+    /// URLs that match a glob pattern. See [`glob_match`]
+    Glob(Box<str>),
+    /// Responses with the given *Content-Type*. The subtype can be `*`, like `image/*`
+    Mime(Box<str>),
+}
+
+impl CacheMatcher {
+    fn matches(&self, url: &str, content_type: Option<&str>) -> bool {
+        match self {
+            CacheMatcher::Prefix(prefix) => url.starts_with(&**prefix),
+            CacheMatcher::Glob(glob) => {
+                if glob.starts_with('/') {
+                    glob_match(glob, url)
+                } else {
+                    /* Patterns without a leading '/' match the file name */
+                    let name = url.rsplit('/').next().unwrap_or(url);
+                    glob_match(glob, name)
+                }
+            }
+            CacheMatcher::Mime(mime) => content_type.is_some_and(|ct| {
+                let ct = ct.split(';').next().unwrap_or("").trim();
+                let (major, minor) = mime.split_once('/').unwrap_or((mime, "*"));
+                ct.split_once('/').is_some_and(|(ct_major, ct_minor)| {
+                    major.eq_ignore_ascii_case(ct_major)
+                        && (minor == "*" || minor.eq_ignore_ascii_case(ct_minor))
+                })
+            }),
+        }
+    }
+}
+
+/// Predefined sets of *Cache-Control* directives
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePreset {
+    /// For fingerprinted assets, whose URL changes when their content does.
+    /// They can be cached for a year, without revalidating them.
+    Immutable,
+}
+
+impl CachePreset {
+    #[must_use]
+    pub fn directives(self) -> &'static str {
+        match self {
+            CachePreset::Immutable => "public, max-age=31536000, immutable",
+        }
+    }
+}
+
+impl FromStr for CachePreset {
+    type Err = HttpError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "immutable" => Ok(CachePreset::Immutable),
+            _ => Err(format!("Unknown cache preset: {s}").into()),
+        }
+    }
+}
+
+/// A caching rule
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use http_srv::handler::{
+///     FileConfig,
+///     cache::{CacheMatcher, CachePreset, CacheRule},
+/// };
+///
+/// let files = FileConfig {
+///     cache_rules: vec![
+///         CacheRule::new(CacheMatcher::Prefix("/assets/".into())).preset(CachePreset::Immutable),
+///         CacheRule::new(CacheMatcher::Glob("*.html".into())).cache_control("no-cache"),
+///         CacheRule::new(CacheMatcher::Mime("image/*".into()))
+///             .cache_control("public, max-age=86400")
+///             .expires(Duration::from_secs(86400))
+///             .vary("Accept"),
+///     ],
+///     ..FileConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheRule {
+    matcher: CacheMatcher,
+    cache_control: Option<Box<str>>,
+    expires: Option<Duration>,
+    vary: Vec<Box<str>>,
+}
+
+impl CacheRule {
+    #[must_use]
+    pub fn new(matcher: CacheMatcher) -> Self {
+        Self {
+            matcher,
+            cache_control: None,
+            expires: None,
+            vary: Vec::new(),
+        }
+    }
+    /// Sets the directives of the *Cache-Control* header
+    #[must_use]
+    pub fn cache_control(mut self, directives: impl Into<Box<str>>) -> Self {
+        self.cache_control = Some(directives.into());
+        self
+    }
+    /// Sets the *Cache-Control* directives of a [preset](CachePreset)
+    #[must_use]
+    pub fn preset(self, preset: CachePreset) -> Self {
+        self.cache_control(preset.directives())
+    }
+    /// Sends an *Expires* header, with the time of the response plus `offset`
+    ///
+    /// The offset is capped to [`MAX_EXPIRES`]
+    #[must_use]
+    pub fn expires(mut self, offset: Duration) -> Self {
+        self.expires = Some(offset.min(MAX_EXPIRES));
+        self
+    }
+    /// Adds the given fields to the *Vary* header
+    #[must_use]
+    pub fn vary(mut self, fields: &str) -> Self {
+        self.vary.extend(
+            fields
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(Box::from),
+        );
+        self
+    }
+
+    fn apply(&self, req: &mut HttpRequest) {
+        if let Some(directives) = &self.cache_control {
+            req.set_header("Cache-Control", &**directives);
+        }
+        if let Some(expires) = self
+            .expires
+            .and_then(|offset| SystemTime::now().checked_add(offset))
+        {
+            req.set_header("Expires", format_http_date(expires));
+        }
+        for field in &self.vary {
+            add_vary(req, field);
+        }
+    }
+}
+
+/// Applies the first rule that matches the request's
+/// URL, or the *Content-Type* of its response
+pub fn apply_rules(rules: &[CacheRule], req: &mut HttpRequest) {
+    let content_type = req.response_headers().get("Content-Type");
+    if let Some(rule) = rules
+        .iter()
+        .find(|r| r.matcher.matches(req.url(), content_type))
+    {
+        rule.apply(req);
+    }
+}
+
+/// Matches a glob pattern
+///
+/// - `*` matches any sequence of characters, except `/`
+/// - `**` matches any sequence of characters
+/// - `?` matches a single character, except `/`
+#[must_use]
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+    let (mut p, mut t) = (0, 0);
+    /* Backtracking points (pattern, text) of the last `*` and the last `**`.
+     * A `*` can't cross a '/', so when it gets stuck, the `**` before it
+     * consumes one more character instead. */
+    let mut star: Option<(usize, usize)> = None;
+    let mut double_star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') if pattern.get(p + 1) == Some(&b'*') => {
+                p += 2;
+                double_star = Some((p, t));
+                star = None;
+            }
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, t));
+            }
+            Some(b'?') if text[t] != b'/' => {
+                p += 1;
+                t += 1;
+            }
+            Some(&c) if c != b'?' && c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => {
+                if let Some((sp, st)) = star
+                    && text[st] != b'/'
+                {
+                    star = Some((sp, st + 1));
+                    p = sp;
+                    t = st + 1;
+                } else if let Some((sp, st)) = double_star {
+                    double_star = Some((sp, st + 1));
+                    star = None;
+                    p = sp;
+                    t = st + 1;
+                } else {
+                    return false;
+                }
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use std::time::Duration;
+
+    use http::HttpMethod;
+
+    use super::{CacheMatcher, CachePreset, CacheRule, glob_match};
+    use crate::HttpRequest;
+
+    #[test]
+    fn globs() {
+        let cases = [
+            ("*.js", "app.js", true),
+            ("*.js", "app.css", false),
+            ("app.*.js", "app.3f2a.js", true),
+            ("/static/*.js", "/static/app.js", true),
+            ("/static/*.js", "/static/lib/app.js", false),
+            ("/static/**.js", "/static/lib/app.js", true),
+            ("/static/**", "/static/", true),
+            ("/img/?.png", "/img/a.png", true),
+            ("/img/?.png", "/img/ab.png", false),
+            ("*", "", true),
+            ("a*b*c", "abxbc", true),
+            ("a*b*c", "abxb", false),
+            ("/**/*.css", "/a/b/c.css", true),
+            ("/**/*.css", "/a/b/c.js", false),
+            ("/static/**/*.js", "/static/lib/x/app.js", true),
+            ("/static/**/*.js", "/static/app.js", false),
+            ("**a*b", "xa/ab", true),
+            ("**a*b", "xa/a/b", false),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(glob_match(pattern, text), expected, "{pattern} {text}");
+        }
+    }
+
+    #[test]
+    fn matchers() {
+        let prefix = CacheMatcher::Prefix("/assets/".into());
+        assert!(prefix.matches("/assets/app.js", None));
+        assert!(!prefix.matches("/index.html", None));
+
+        let glob = CacheMatcher::Glob("*.html".into());
+        assert!(glob.matches("/docs/index.html", None));
+        assert!(!glob.matches("/docs/index.htm", None));
+
+        let mime = CacheMatcher::Mime("image/*".into());
+        assert!(mime.matches("/a", Some("image/png")));
+        assert!(mime.matches("/a", Some("IMAGE/svg+xml; charset=utf-8")));
+        assert!(!mime.matches("/a", Some("text/html")));
+        assert!(!mime.matches("/a", None));
+    }
+
+    #[test]
+    fn apply() {
+        let mut req = HttpRequest::builder()
+            .method(HttpMethod::GET)
+            .url("/a")
+            .build();
+        req.set_header("Vary", "Accept-Encoding");
+        CacheRule::new(CacheMatcher::Prefix("/".into()))
+            .preset(CachePreset::Immutable)
+            .expires(Duration::from_mins(1))
+            .vary("Accept-Language, accept-encoding")
+            .apply(&mut req);
+        let headers = req.response_headers();
+        assert_eq!(
+            headers.get("Cache-Control"),
+            Some("public, max-age=31536000, immutable")
+        );
+        assert!(headers.get("Expires").is_some_and(|e| e.ends_with(" GMT")));
+        assert_eq!(
+            headers.get("Vary"),
+            Some("Accept-Encoding, Accept-Language")
+        );
+
+        let mut req = HttpRequest::builder().url("/a").build();
+        CacheRule::new(CacheMatcher::Prefix("/".into()))
+            .expires(Duration::MAX)
+            .apply(&mut req);
+        assert!(req.response_headers().get("Expires").is_some());
+    }
+}
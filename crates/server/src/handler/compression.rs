@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use http::{encoding::ContentCoding, negotiation, request::EncodingSelector};
+use mime::Mime;
+
+use super::Interceptor;
+use crate::HttpRequest;
+
+/// Response compression
+///
+/// Compresses the body of the responses, if the client
+/// supports it (see *Accept-Encoding*), the *Content-Type*
+/// of the response is compressible and the body is big enough.
+///
+/// Responses that could be compressed get a `Vary: Accept-Encoding` header,
+/// even if the client doesn't support compression, so that caches don't
+/// serve them to the wrong clients.
+///
+/// # Example
+/// ```
+/// use http::encoding::ContentCoding;
+/// use http_srv::handler::*;
+///
+/// let mut handler = Handler::new();
+/// let compression = Compression::new()
+///     .min_size(512)
+///     .codings(&[ContentCoding::Gzip]);
+/// handler.pre_interceptor(compression.interceptor());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Compression {
+    min_size: u64,
+    mime_types: Vec<Box<str>>,
+    codings: Vec<ContentCoding>,
+}
+
+impl Compression {
+    /// Default minimum size of a compressed body, in bytes
+    pub const DEFAULT_MIN_SIZE: u64 = 1024;
+
+    /// Default compressible media types
+    pub const DEFAULT_MIME_TYPES: &[&str] = &[
+        "text/*",
+        "application/json",
+        "application/javascript",
+        "application/xml",
+        "application/wasm",
+        "image/svg+xml",
+    ];
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            min_size: Self::DEFAULT_MIN_SIZE,
+            mime_types: Self::DEFAULT_MIME_TYPES.iter().map(|&t| t.into()).collect(),
+            codings: ContentCoding::ALL.to_vec(),
+        }
+    }
+
+    /// Minimum size of a body to be compressed
+    ///
+    /// Bodies of unknown size (without a *Content-Length*) are always compressed.
+    #[must_use]
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Media types to compress. They can be ranges, like `text/*`
+    #[must_use]
+    pub fn mime_types(mut self, mime_types: &[&str]) -> Self {
+        self.mime_types = mime_types.iter().map(|&t| t.into()).collect();
+        self
+    }
+
+    /// Codings to use, in order of preference
+    #[must_use]
+    pub fn codings(mut self, codings: &[ContentCoding]) -> Self {
+        self.codings = codings.to_vec();
+        self
+    }
+
+    fn is_compressible(&self, content_type: &str) -> bool {
+        let content_type = content_type.split(';').next().unwrap_or("").trim();
+        let Ok(mime) = Mime::new(content_type) else {
+            return false;
+        };
+        self.mime_types.iter().any(|t| {
+            let (major, minor) = t.split_once('/').unwrap_or((t, ""));
+            major.eq_ignore_ascii_case(mime.major())
+                && (minor == "*" || minor.eq_ignore_ascii_case(mime.minor()))
+        })
+    }
+
+    /// Chooses the coding of a response
+    fn select(&self, req: &mut HttpRequest) -> Option<ContentCoding> {
+        let content_type = req.response_headers().get("Content-Type")?;
+        if !self.is_compressible(content_type) {
+            return None;
+        }
+        add_vary(req, "Accept-Encoding");
+
+        let len = req.response_headers().get("Content-Length");
+        if len
+            .and_then(|len| len.parse::<u64>().ok())
+            .is_some_and(|len| len < self.min_size)
+        {
+            return None;
+        }
+        /* Without Accept-Encoding any coding is acceptable, but
+         * in practice, only clients that send it support them */
+        let accept = req.header("Accept-Encoding")?;
+        let offered: Vec<_> = self
+            .codings
+            .iter()
+            .map(|c| c.as_str())
+            .chain(["identity"])
+            .collect();
+        negotiation::encoding(Some(accept), &offered)
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Returns an [Interceptor] that enables compression for the requests
+    ///
+    /// It should be added as a [pre interceptor](super::Handler::pre_interceptor)
+    #[must_use]
+    pub fn interceptor(self) -> impl Interceptor {
+        let this = Arc::new(self);
+        let selector: EncodingSelector = Arc::new(move |req| this.select(req));
+        move |req: &mut HttpRequest| req.set_encoding_selector(Some(Arc::clone(&selector)))
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds a field to the *Vary* header of the response
+pub(super) fn add_vary(req: &mut HttpRequest, field: &str) {
+    match req.response_headers().get("Vary") {
+        Some(vary)
+            if vary
+                .split(',')
+                .map(str::trim)
+                .any(|f| f == "*" || f.eq_ignore_ascii_case(field)) => {}
+        Some(vary) => {
+            let vary = format!("{vary}, {field}");
+            req.set_header("Vary", vary);
+        }
+        None => req.set_header("Vary", field),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use http::encoding::ContentCoding;
+
+    use super::Compression;
+    use crate::HttpRequest;
+
+    fn request(accept_encoding: Option<&str>, content_type: &str, len: usize) -> HttpRequest {
+        let mut req = HttpRequest::builder().url("/");
+        if let Some(accept) = accept_encoding {
+            req = req.header("Accept-Encoding", accept);
+        }
+        let mut req = req.build();
+        req.set_header("Content-Type", content_type);
+        req.set_header("Content-Length", len.to_string());
+        req
+    }
+
+    #[test]
+    fn select() {
+        let comp = Compression::new();
+        let mut req = request(Some("gzip, br;q=0.5"), "text/html; charset=utf-8", 2048);
+        assert_eq!(comp.select(&mut req), Some(ContentCoding::Gzip));
+        assert_eq!(req.response_headers().get("Vary"), Some("Accept-Encoding"));
+
+        let mut req = request(Some("gzip"), "application/json", 100);
+        assert_eq!(comp.select(&mut req), None);
+        assert_eq!(req.response_headers().get("Vary"), Some("Accept-Encoding"));
+
+        let mut req = request(Some("gzip"), "image/png", 2048);
+        assert_eq!(comp.select(&mut req), None);
+        assert_eq!(req.response_headers().get("Vary"), None);
+
+        let mut req = request(None, "text/plain", 2048);
+        assert_eq!(comp.select(&mut req), None);
+
+        let mut req = request(Some("deflate, identity;q=0.5"), "text/css", 2048);
+        let comp = comp.codings(&[ContentCoding::Brotli]);
+        assert_eq!(comp.select(&mut req), None);
+    }
+
+    #[test]
+    fn vary() {
+        let comp = Compression::new();
+        let mut req = request(Some("br"), "text/plain", 2048);
+        req.set_header("Vary", "Origin");
+        comp.select(&mut req);
+        comp.select(&mut req);
+        assert_eq!(
+            req.response_headers().get("Vary"),
+            Some("Origin, Accept-Encoding")
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Conditional requests
+//!
+//! See [RFC 9110 Section 13](https://www.rfc-editor.org/rfc/rfc9110#section-13)
+
+use std::{
+    fs::{File, Metadata},
+    io::{self, BufReader, Read},
+    iter,
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http::{HttpMethod, date::parse_http_date, encoding::ContentCoding};
+
+use crate::{HttpError, HttpRequest};
+
+/// How the `ETag` of a file is computed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ETagMode {
+    /// From the inode, size and modification time of the file.
+    #[default]
+    Metadata,
+    /// From a hash of the file's content. The file is read on
+    /// every request, but the tag doesn't change if the file is
+    /// touched, or copied to another server.
+    ContentHash,
+}
+
+impl FromStr for ETagMode {
+    type Err = HttpError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "metadata" => Ok(ETagMode::Metadata),
+            "hash" => Ok(ETagMode::ContentHash),
+            _ => Err(format!("Invalid ETag mode: {s}").into()),
+        }
+    }
+}
+
+/// Validators of the current representation of a resource
+#[derive(Clone, Debug, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// Gets the validators of a file, with an `ETag` computed as `mode` says
+    ///
+    /// # Errors
+    /// If the file's content needs to be hashed, and it can't be read
+    pub fn of_file(path: &Path, metadata: &Metadata, mode: ETagMode) -> io::Result<Self> {
+        let last_modified = metadata.modified().ok();
+        let etag = match mode {
+            ETagMode::Metadata => metadata_etag(metadata, last_modified),
+            ETagMode::ContentHash => content_etag(path)?,
+        };
+        Ok(Self {
+            etag: Some(etag),
+            last_modified,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_: &Metadata) -> u64 {
+    0
+}
+
+fn metadata_etag(metadata: &Metadata, modified: Option<SystemTime>) -> String {
+    let mtime = modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    format!("\"{:x}-{:x}-{mtime:x}\"", inode(metadata), metadata.len())
+}
+
+/// Hashes the file with 64 bit FNV-1a
+fn content_etag(path: &Path) -> io::Result<String> {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0; 8192];
+    let mut hash = OFFSET;
+    let mut len: u64 = 0;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &b in &buf[..n] {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(PRIME);
+        }
+        len += n as u64;
+    }
+    Ok(format!("\"{len:x}-{hash:016x}\""))
+}
+
+/// Parses a list of entity tags, returning if they're weak, and their opaque tag.
+/// It stops at the first invalid tag.
+fn entity_tags(list: &str) -> impl Iterator<Item = (bool, &str)> {
+    let mut rest = list;
+    iter::from_fn(move || {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        let (weak, tag) = match rest.strip_prefix("W/") {
+            Some(tag) => (true, tag),
+            None => (false, rest),
+        };
+        let end = tag.strip_prefix('"')?.find('"')? + 2;
+        let (tag, next) = tag.split_at(end);
+        rest = next;
+        Some((weak, tag))
+    })
+}
+
+/// Returns the [coding](ContentCoding) of `tag`, if it's the tag of
+/// a compressed representation of `current`
+fn coding_of(tag: &str, current: &str) -> Option<ContentCoding> {
+    ContentCoding::ALL
+        .into_iter()
+        .find(|coding| coding.etag(current) == tag)
+}
+
+/// Returns true if any of the entity tags in the `name` headers matches
+/// `current`. `*` matches any current representation.
+///
+/// The weak comparison also matches the tags of the compressed
+/// representations, which the [`Compression`](super::Compression)
+/// interceptor sends.
+///
+/// Returns None if the request doesn't have the header
+fn matches_any(
+    req: &HttpRequest,
+    name: &str,
+    current: Option<&Validators>,
+    strong: bool,
+) -> Option<bool> {
+    let values: Vec<&str> = req.headers().get_all(name).map(str::trim).collect();
+    if values.is_empty() {
+        return None;
+    }
+    let Some(current) = current else {
+        return Some(false);
+    };
+    let current_tag = current
+        .etag
+        .as_deref()
+        .and_then(|etag| entity_tags(etag).next());
+    Some(values.iter().any(|&v| {
+        v == "*"
+            || current_tag.is_some_and(|(current_weak, current)| {
+                entity_tags(v).any(|(weak, tag)| {
+                    if strong {
+                        !weak && !current_weak && tag == current
+                    } else {
+                        tag == current || coding_of(tag, current).is_some()
+                    }
+                })
+            })
+    }))
+}
+
+/// Seconds since the UNIX epoch. HTTP dates don't have a finer resolution
+fn secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Returns the date of a header, ignoring it if it's not a valid HTTP date
+fn date_header(req: &HttpRequest, name: &str) -> Option<u64> {
+    req.header(name).and_then(parse_http_date).map(secs)
+}
+
+/// Result of evaluating the preconditions of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precondition {
+    /// The request can be performed
+    Passed,
+    /// The client's copy is up to date. Respond with *304 Not Modified*
+    NotModified,
+    /// Respond with *412 Precondition Failed*
+    Failed,
+}
+
+/// Evaluates the preconditions of a request, in the order of
+/// [RFC 9110 Section 13.2.2](https://www.rfc-editor.org/rfc/rfc9110#section-13.2.2)
+///
+/// `current` is None if the resource doesn't exist.
+/// *If-Range* is evaluated separately, with [`if_range`].
+#[must_use]
+pub fn evaluate(req: &HttpRequest, current: Option<&Validators>) -> Precondition {
+    let last_modified = current.and_then(|c| c.last_modified).map(secs);
+    let safe = matches!(req.method(), HttpMethod::GET | HttpMethod::HEAD);
+
+    match matches_any(req, "If-Match", current, true) {
+        Some(false) => return Precondition::Failed,
+        Some(true) => {}
+        None => {
+            if let Some(since) = date_header(req, "If-Unmodified-Since")
+                && last_modified.is_some_and(|m| m > since)
+            {
+                return Precondition::Failed;
+            }
+        }
+    }
+
+    match matches_any(req, "If-None-Match", current, false) {
+        Some(true) if safe => return Precondition::NotModified,
+        Some(true) => return Precondition::Failed,
+        Some(false) => {}
+        None => {
+            if safe
+                && let Some(since) = date_header(req, "If-Modified-Since")
+                && last_modified.is_some_and(|m| m <= since)
+            {
+                return Precondition::NotModified;
+            }
+        }
+    }
+    Precondition::Passed
+}
+
+/// Returns the `ETag` of a *304 Not Modified* response
+///
+/// If the client's copy is a compressed representation, that's the
+/// tag it has, so that caches don't store the tag of the identity
+/// representation along with the compressed one.
+#[must_use]
+pub fn not_modified_etag(req: &HttpRequest, current: &Validators) -> Option<String> {
+    let etag = current.etag.as_deref()?;
+    let (_, current_tag) = entity_tags(etag).next()?;
+    let coding = req
+        .headers()
+        .get_all("If-None-Match")
+        .flat_map(entity_tags)
+        .find_map(|(_, tag)| coding_of(tag, current_tag));
+    Some(match coding {
+        Some(coding) => coding.etag(etag),
+        None => etag.to_owned(),
+    })
+}
+
+/// Returns true if the *Range* header of the request should be honored
+///
+/// That's the case if there's no *If-Range* header, or if the validator
+/// it contains matches the current one. Otherwise, the whole
+/// representation should be sent.
+#[must_use]
+pub fn if_range(req: &HttpRequest, current: &Validators) -> bool {
+    let Some(value) = req.header("If-Range").map(str::trim) else {
+        return true;
+    };
+    if value.starts_with('"') || value.starts_with("W/") {
+        let current = current
+            .etag
+            .as_deref()
+            .and_then(|etag| entity_tags(etag).next());
+        let tag = entity_tags(value).next();
+        matches!((tag, current), (Some((false, tag)), Some((false, current))) if tag == current)
+    } else {
+        let last_modified = current.last_modified.map(secs);
+        parse_http_date(value).is_some_and(|date| Some(secs(date)) == last_modified)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use http::HttpMethod;
+
+    use super::{Precondition, Validators, entity_tags, evaluate, if_range, not_modified_etag};
+    use crate::HttpRequest;
+
+    fn request(method: HttpMethod, headers: &[(&str, &str)]) -> HttpRequest {
+        let mut builder = HttpRequest::builder().method(method).url("/");
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        builder.build()
+    }
+
+    fn current() -> Validators {
+        Validators {
+            etag: Some("\"abc\"".into()),
+            /* Sun, 06 Nov 1994 08:49:37 GMT */
+            last_modified: Some(UNIX_EPOCH + Duration::from_millis(784_111_777_500)),
+        }
+    }
+
+    const BEFORE: &str = "Sun, 06 Nov 1994 08:49:36 GMT";
+    const SAME: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+    #[test]
+    fn tags() {
+        let tags: Vec<_> = entity_tags(" \"a\", W/\"b,c\" ,\"\"").collect();
+        assert_eq!(tags, [(false, "\"a\""), (true, "\"b,c\""), (false, "\"\"")]);
+        assert_eq!(entity_tags("\"a\", b").count(), 1);
+    }
+
+    #[test]
+    fn get() {
+        let cases: &[(&[(&str, &str)], Precondition)] = &[
+            (&[], Precondition::Passed),
+            (
+                &[("If-None-Match", "\"x\", W/\"abc\"")],
+                Precondition::NotModified,
+            ),
+            (&[("If-None-Match", "*")], Precondition::NotModified),
+            (
+                &[("If-None-Match", "\"abc-gzip\"")],
+                Precondition::NotModified,
+            ),
+            (&[("If-None-Match", "\"abc-zstd\"")], Precondition::Passed),
+            (&[("If-None-Match", "\"x\"")], Precondition::Passed),
+            (&[("If-Modified-Since", SAME)], Precondition::NotModified),
+            (&[("If-Modified-Since", BEFORE)], Precondition::Passed),
+            (&[("If-Modified-Since", "invalid")], Precondition::Passed),
+            /* If-None-Match takes precedence over If-Modified-Since */
+            (
+                &[("If-None-Match", "\"x\""), ("If-Modified-Since", SAME)],
+                Precondition::Passed,
+            ),
+            (&[("If-Match", "\"abc\"")], Precondition::Passed),
+            (&[("If-Match", "W/\"abc\"")], Precondition::Failed),
+            (&[("If-Match", "\"abc-br\"")], Precondition::Failed),
+            (&[("If-Unmodified-Since", BEFORE)], Precondition::Failed),
+            (&[("If-Unmodified-Since", SAME)], Precondition::Passed),
+            /* If-Match takes precedence over If-Unmodified-Since */
+            (
+                &[("If-Match", "*"), ("If-Unmodified-Since", BEFORE)],
+                Precondition::Passed,
+            ),
+            (
+                &[("If-Match", "\"x\""), ("If-None-Match", "\"abc\"")],
+                Precondition::Failed,
+            ),
+        ];
+        for (headers, expected) in cases {
+            let req = request(HttpMethod::GET, headers);
+            assert_eq!(evaluate(&req, Some(&current())), *expected, "{headers:?}");
+        }
+    }
+
+    #[test]
+    fn unsafe_methods() {
+        let req = request(HttpMethod::DELETE, &[("If-None-Match", "\"abc\"")]);
+        assert_eq!(evaluate(&req, Some(&current())), Precondition::Failed);
+        let req = request(HttpMethod::POST, &[("If-Modified-Since", SAME)]);
+        assert_eq!(evaluate(&req, Some(&current())), Precondition::Passed);
+
+        /* Create only if it doesn't exist */
+        let req = request(HttpMethod::POST, &[("If-None-Match", "*")]);
+        assert_eq!(evaluate(&req, None), Precondition::Passed);
+        assert_eq!(evaluate(&req, Some(&current())), Precondition::Failed);
+        let req = request(HttpMethod::POST, &[("If-Match", "*")]);
+        assert_eq!(evaluate(&req, None), Precondition::Failed);
+    }
+
+    #[test]
+    fn compressed_tags() {
+        let req = request(HttpMethod::GET, &[("If-None-Match", "\"x\", \"abc-br\"")]);
+        assert_eq!(
+            not_modified_etag(&req, &current()).as_deref(),
+            Some("\"abc-br\"")
+        );
+        let req = request(HttpMethod::GET, &[("If-None-Match", "*")]);
+        assert_eq!(
+            not_modified_etag(&req, &current()).as_deref(),
+            Some("\"abc\"")
+        );
+    }
+
+    #[test]
+    fn range() {
+        let current = current();
+        assert!(if_range(&request(HttpMethod::GET, &[]), &current));
+        let cases = [
+            ("\"abc\"", true),
+            ("W/\"abc\"", false),
+            ("\"abc-gzip\"", false),
+            ("\"x\"", false),
+            (SAME, true),
+            (BEFORE, false),
+            ("invalid", false),
+        ];
+        for (value, expected) in cases {
+            let req = request(HttpMethod::GET, &[("If-Range", value)]);
+            assert_eq!(if_range(&req, &current), expected, "{value}");
+        }
+    }
+}
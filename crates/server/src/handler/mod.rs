@@ -1,24 +1,37 @@
 mod auth;
+pub mod cache;
+mod compression;
+pub mod conditional;
 mod indexing;
 mod ranges;
 use std::{
     borrow::Cow,
     collections::HashMap,
-    fs::{self, File, OpenOptions},
+    fs::{self, File, Metadata, OpenOptions},
     io::{self, BufReader, Read, Seek, SeekFrom, Write, stdout},
     ops::Range,
-    path::Path,
-    sync::Mutex,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 pub use auth::AuthConfig;
-use http::HttpMethod;
+pub use compression::Compression;
+use http::{
+    HttpMethod, Status,
+    date::format_http_date,
+    websocket::{CloseCode, WebSocket},
+};
 use mime::Mime;
 
-use self::{indexing::index_of, ranges::get_range_for};
+use self::{
+    cache::CacheRule,
+    conditional::{ETagMode, Precondition, Validators, if_range},
+    indexing::index_of,
+    ranges::{ByteRanges, content_range, get_ranges_for},
+};
 use crate::{
     Result,
-    log::{self, LogLevel},
+    log::{self, LogLevel, prelude::*},
     request::HttpRequest,
 };
 
@@ -144,6 +157,46 @@ impl Handler {
     pub fn head(&mut self, url: impl Into<UrlMatcher>, f: impl RequestHandler) {
         self.add(HttpMethod::HEAD, url, f);
     }
+    /// Adds a [`WebSocket`] endpoint
+    ///
+    /// [GET](HttpMethod::GET) requests to `url` are [accepted](WebSocket::accept)
+    /// as [`WebSocket`]s, and passed to `f`. When `f` returns, the socket is closed,
+    /// with [`CloseCode::INTERNAL_ERROR`] if it failed.
+    ///
+    /// # Example
+    /// ```
+    /// use http_srv::handler::Handler;
+    /// use http::websocket::Message;
+    ///
+    /// let mut handler = Handler::new();
+    /// handler.websocket("/echo", |ws| {
+    ///     while let Message::Text(text) = ws.recv()? {
+    ///         ws.send_text(&text)?;
+    ///     }
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn websocket<F>(&mut self, url: impl Into<UrlMatcher>, f: F)
+    where
+        F: Fn(&mut WebSocket<'_>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.get(url, move |req: &mut HttpRequest| {
+            let mut ws = WebSocket::accept(req)?;
+            /* The connection isn't HTTP anymore, so errors can't be
+             * returned, or they would be answered with an error page */
+            let code = match f(&mut ws) {
+                Ok(()) => CloseCode::NORMAL,
+                Err(err) => {
+                    log_error!("WebSocket: {err}");
+                    CloseCode::INTERNAL_ERROR
+                }
+            };
+            if let Err(err) = ws.close(code, "") {
+                log_warn!("Closing WebSocket: {err}");
+            }
+            Ok(())
+        });
+    }
     /// Adds a handler for a request type
     ///
     /// - method: HTTP [method](HttpMethod) to match
@@ -201,12 +254,15 @@ impl Handler {
     }
     /// Handles a request if it finds a [`RequestHandler`] for it.
     /// Else, it returns a 403 FORBIDDEN response
+    ///
+    /// If the handler fails, the response's status is
+    /// taken from the [error](http::HttpError::status)
     pub fn handle(&self, req: &mut HttpRequest) -> Result<()> {
         self.pre_interceptors.iter().for_each(|f| f(req));
         let result = match self.get_handler(req.method(), req.url()) {
             Some(handler) => handler.handle(req).or_else(|err| {
                 eprintln!("ERROR: {err}");
-                req.server_error()
+                req.set_status(err.status()).respond_error_page()
             }),
             None => req.forbidden(),
         };
@@ -215,6 +271,32 @@ impl Handler {
     }
 }
 
+impl Handler {
+    /// Like the [default](Handler::default) handler, but its
+    /// static file handlers use the given [configuration](FileConfig)
+    #[must_use]
+    pub fn with_files(files: &FileConfig) -> Self {
+        let mut handler = Self::new();
+        handler.pre_interceptor(suffix_html);
+        handler.pre_interceptor(|req| {
+            req.set_header("Accept-Ranges", "bytes");
+        });
+
+        handler.add_default(HttpMethod::GET, files.cat_handler());
+        handler.add_default(HttpMethod::POST, files.post_handler());
+        handler.add_default(HttpMethod::DELETE, files.delete_handler());
+        handler.add_default(HttpMethod::HEAD, files.head_handler());
+
+        handler.get("/", files.root_handler());
+        handler.head("/", files.root_handler());
+
+        if log::get_level() >= LogLevel::Info {
+            handler.post_interceptor(log_stdout);
+        }
+        handler
+    }
+}
+
 impl Default for Handler {
     /// Default Handler
     ///
@@ -235,31 +317,94 @@ impl Default for Handler {
     ///  - [`log_stdout`]
     ///
     fn default() -> Self {
-        let mut handler = Self::new();
-        handler.pre_interceptor(suffix_html);
-        handler.pre_interceptor(|req| {
-            req.set_header("Accept-Ranges", "bytes");
-        });
-
-        handler.add_default(HttpMethod::GET, cat_handler);
-        handler.add_default(HttpMethod::POST, post_handler);
-        handler.add_default(HttpMethod::DELETE, delete_handler);
-        handler.add_default(HttpMethod::HEAD, head_handler);
+        Self::with_files(&FileConfig::default())
+    }
+}
 
-        handler.get("/", root_handler);
-        handler.head("/", root_handler);
+/// Configuration of the static file handlers
+///
+/// The [`cat_handler`], [`head_handler`], [`post_handler`], [`delete_handler`]
+/// and [`root_handler`] functions use the default configuration. The methods
+/// of this struct return the same handlers, using this configuration.
+///
+/// # Example
+/// ```
+/// use http::HttpMethod;
+/// use http_srv::handler::{FileConfig, Handler, conditional::ETagMode};
+///
+/// let files = FileConfig {
+///     etag_mode: ETagMode::ContentHash,
+///     ..FileConfig::default()
+/// };
+/// let mut handler = Handler::new();
+/// handler.add_default(HttpMethod::GET, files.cat_handler());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FileConfig {
+    /// How the `ETag`s of the files are computed
+    pub etag_mode: ETagMode,
+    /// Rules that add caching headers to the responses.
+    /// Only the first one that matches a file is applied
+    pub cache_rules: Vec<CacheRule>,
+}
 
-        if log::get_level() >= LogLevel::Info {
-            handler.post_interceptor(log_stdout);
+impl FileConfig {
+    /// Returns a [`cat_handler`] that uses this configuration
+    #[must_use]
+    pub fn cat_handler(&self) -> FileHandler {
+        self.handler(cat_file)
+    }
+    /// Returns a [`head_handler`] that uses this configuration
+    #[must_use]
+    pub fn head_handler(&self) -> FileHandler {
+        self.handler(head_file)
+    }
+    /// Returns a [`post_handler`] that uses this configuration
+    #[must_use]
+    pub fn post_handler(&self) -> FileHandler {
+        self.handler(post_file)
+    }
+    /// Returns a [`delete_handler`] that uses this configuration
+    #[must_use]
+    pub fn delete_handler(&self) -> FileHandler {
+        self.handler(delete_file)
+    }
+    /// Returns a [`root_handler`] that uses this configuration
+    #[must_use]
+    pub fn root_handler(&self) -> FileHandler {
+        self.handler(root_file)
+    }
+    fn handler(&self, f: fn(&mut HttpRequest, &FileConfig) -> Result<()>) -> FileHandler {
+        FileHandler {
+            f,
+            config: Arc::new(self.clone()),
         }
-        handler
     }
 }
 
-fn head_headers(req: &mut HttpRequest) -> Result<Option<Range<u64>>> {
+/// A static file handler, with its [configuration](FileConfig)
+pub struct FileHandler {
+    f: fn(&mut HttpRequest, &FileConfig) -> Result<()>,
+    config: Arc<FileConfig>,
+}
+
+impl RequestHandler for FileHandler {
+    fn handle(&self, req: &mut HttpRequest) -> Result<()> {
+        (self.f)(req, &self.config)
+    }
+}
+
+/// The ranges of a file sent in a *206 Partial Content* response
+enum Partial {
+    Single(Range<u64>),
+    Multiple(ByteRanges),
+}
+
+fn head_headers(req: &mut HttpRequest, config: &FileConfig) -> Result<Option<Partial>> {
     let filename = req.filename()?;
     if dir_exists(&filename) {
         req.set_header("Content-Type", "text/html");
+        cache::apply_rules(&config.cache_rules, req);
         return Ok(None);
     }
     match File::open(&*filename) {
@@ -267,31 +412,39 @@ fn head_headers(req: &mut HttpRequest) -> Result<Option<Range<u64>>> {
             if let Ok(mime) = Mime::from_filename(&filename) {
                 req.set_header("Content-Type", mime.to_string());
             }
+            cache::apply_rules(&config.cache_rules, req);
             let metadata = file.metadata()?;
             let len = metadata.len();
+            let mut validators = Validators::default();
             if metadata.is_file() {
                 req.set_header("Content-Length", len.to_string());
+                validators =
+                    Validators::of_file(Path::new(&*filename), &metadata, config.etag_mode)?;
+                set_validator_headers(req, &validators);
             }
-            let Some(range) = req.header("Range") else {
+            match conditional::evaluate(req, Some(&validators)) {
+                Precondition::Passed => {}
+                Precondition::NotModified => {
+                    if let Some(etag) = conditional::not_modified_etag(req, &validators) {
+                        req.set_header("ETag", etag);
+                    }
+                    req.set_status(Status::NOT_MODIFIED);
+                    return Ok(None);
+                }
+                Precondition::Failed => {
+                    req.set_status(Status::PRECONDITION_FAILED);
+                    return Ok(None);
+                }
+            }
+            if !metadata.is_file() || *req.method() != HttpMethod::GET {
                 return Ok(None);
-            };
-            let range = get_range_for(range, len)?;
-            if range.end > len || range.end <= range.start {
-                req.set_status(416);
-            } else {
-                req.set_status(206);
-                req.set_header("Content-Length", (range.end - range.start).to_string());
-                req.set_header(
-                    "Content-Range",
-                    format!("bytes {}-{}/{}", range.start, range.end - 1, len),
-                );
             }
-            return Ok(Some(range));
+            return Ok(select_ranges(req, &validators, len));
         }
         Err(err) => {
             let status = match err.kind() {
-                io::ErrorKind::PermissionDenied => 403,
-                _ => 404,
+                io::ErrorKind::PermissionDenied => Status::FORBIDDEN,
+                _ => Status::NOT_FOUND,
             };
             req.set_status(status);
         }
@@ -299,6 +452,59 @@ fn head_headers(req: &mut HttpRequest) -> Result<Option<Range<u64>>> {
     Ok(None)
 }
 
+/// Evaluates the *Range* header of a request for a file of `len` bytes
+///
+/// An invalid header is ignored, and the whole file is sent.
+fn select_ranges(req: &mut HttpRequest, validators: &Validators, len: u64) -> Option<Partial> {
+    let header = req.header("Range")?;
+    let ranges = get_ranges_for(header, len).ok()?;
+    if !if_range(req, validators) {
+        return None;
+    }
+    match &ranges[..] {
+        [] => {
+            req.set_status(Status::RANGE_NOT_SATISFIABLE);
+            req.set_header("Content-Range", format!("bytes */{len}"));
+            None
+        }
+        [range] => {
+            req.set_status(Status::PARTIAL_CONTENT);
+            req.set_header("Content-Length", (range.end - range.start).to_string());
+            req.set_header("Content-Range", content_range(range, len));
+            Some(Partial::Single(range.clone()))
+        }
+        _ => {
+            let content_type = req.response_headers().get("Content-Type");
+            let body = ByteRanges::new(&ranges, len, content_type);
+            req.set_status(Status::PARTIAL_CONTENT);
+            req.set_header("Content-Type", body.content_type());
+            req.set_header("Content-Length", body.content_length().to_string());
+            Some(Partial::Multiple(body))
+        }
+    }
+}
+
+fn set_validator_headers(req: &mut HttpRequest, validators: &Validators) {
+    if let Some(etag) = &validators.etag {
+        req.set_header("ETag", etag.as_str());
+    }
+    if let Some(modified) = validators.last_modified {
+        req.set_header("Last-Modified", format_http_date(modified));
+    }
+}
+
+/// Returns the validators of the file, or None if it isn't a regular file
+fn file_validators(filename: &str, mode: ETagMode) -> Result<Option<Validators>> {
+    let Some(metadata) = fs::metadata(filename).ok().filter(Metadata::is_file) else {
+        return Ok(None);
+    };
+    Ok(Some(Validators::of_file(
+        Path::new(filename),
+        &metadata,
+        mode,
+    )?))
+}
+
 #[inline]
 fn show_hidden(req: &HttpRequest) -> bool {
     match req.param("hidden") {
@@ -310,9 +516,15 @@ fn show_hidden(req: &HttpRequest) -> bool {
 /// Returns the headers that would be sent by a [GET](HttpMethod::GET)
 /// [request](HttpRequest), with an empty body.
 pub fn head_handler(req: &mut HttpRequest) -> Result<()> {
-    head_headers(req)?;
+    head_file(req, &FileConfig::default())
+}
+
+fn head_file(req: &mut HttpRequest, config: &FileConfig) -> Result<()> {
+    head_headers(req, config)?;
     let filename = req.filename()?;
-    let len = if req.is_http_err() {
+    let len = if req.status() == Status::NOT_MODIFIED {
+        0
+    } else if req.is_http_err() {
         req.error_page().len()
     } else if dir_exists(&filename) {
         index_of(&filename, show_hidden(req))?.len()
@@ -328,10 +540,27 @@ pub fn head_handler(req: &mut HttpRequest) -> Result<()> {
 
 /// Returns the file, or an index of the directory.
 ///
+/// Files are sent with `ETag` and *Last-Modified* validators, and
+/// the request's [preconditions](conditional::evaluate) are evaluated
+/// against them, answering *304 Not Modified* or *412 Precondition Failed*.
+///
+/// A *Range* header selects parts of the file. Multiple ranges
+/// are sent as a `multipart/byteranges` body.
+///
+/// The first [cache rule](FileConfig::cache_rules) that matches
+/// the file adds its caching headers to the response.
+///
 /// # Errors
 /// If the request returns an Error variant on send
 pub fn cat_handler(req: &mut HttpRequest) -> Result<()> {
-    let range = head_headers(req)?;
+    cat_file(req, &FileConfig::default())
+}
+
+fn cat_file(req: &mut HttpRequest, config: &FileConfig) -> Result<()> {
+    let partial = head_headers(req, config)?;
+    if req.status() == Status::NOT_MODIFIED {
+        return req.respond();
+    }
     if req.is_http_err() {
         return req.respond_error_page();
     }
@@ -341,25 +570,59 @@ pub fn cat_handler(req: &mut HttpRequest) -> Result<()> {
         return req.respond_str(&page);
     }
     let mut file = File::open(&*req.filename()?)?;
-    if let Some(range) = range {
-        file.seek(SeekFrom::Start(range.start))?;
-        let mut reader = BufReader::new(file).take(range.end - range.start);
-        req.respond_reader(&mut reader)
-    } else {
-        let mut reader = BufReader::new(file);
-        req.respond_reader(&mut reader)
+    match partial {
+        Some(Partial::Single(range)) => {
+            file.seek(SeekFrom::Start(range.start))?;
+            let mut reader = BufReader::new(file).take(range.end - range.start);
+            req.respond_reader(&mut reader)
+        }
+        Some(Partial::Multiple(body)) => {
+            let mut reader = body.reader(BufReader::new(file));
+            req.respond_reader(&mut reader)
+        }
+        None => {
+            let mut reader = BufReader::new(file);
+            req.respond_reader(&mut reader)
+        }
     }
 }
 
 /// Save the data of the request to the url
 ///
+/// If the body is `multipart/form-data`, it's handled by [`upload_handler`]
+///
+/// *If-Match* and *If-Unmodified-Since* can be used to avoid overwriting
+/// someone else's changes, and `If-None-Match: *` to avoid overwriting an
+/// existing file. The response contains the validators of the new file.
+///
 /// # Errors
 /// If the request returns an Error variant on send
 pub fn post_handler(req: &mut HttpRequest) -> Result<()> {
+    post_file(req, &FileConfig::default())
+}
+
+fn post_file(req: &mut HttpRequest, config: &FileConfig) -> Result<()> {
+    let multipart = req.header("Content-Type").is_some_and(|ct| {
+        ct.get(..19)
+            .is_some_and(|mime| mime.eq_ignore_ascii_case("multipart/form-data"))
+    });
+    if multipart {
+        return upload_handler(req);
+    }
     let filename = req.filename()?;
+    let current = file_validators(&filename, config.etag_mode)?;
+    if conditional::evaluate(req, current.as_ref()) != Precondition::Passed {
+        return req
+            .set_status(Status::PRECONDITION_FAILED)
+            .respond_error_page();
+    }
     match File::create(&*filename) {
         Ok(mut file) => {
             req.read_body(&mut file)?;
+            drop(file);
+            if let Some(validators) = file_validators(&filename, config.etag_mode)? {
+                set_validator_headers(req, &validators);
+            }
             req.ok()
         }
         Err(err) => {
@@ -372,12 +635,64 @@ pub fn post_handler(req: &mut HttpRequest) -> Result<()> {
     }
 }
 
+/// Save the files of a `multipart/form-data` body into the directory of the url
+///
+/// Only the last component of each part's filename is used, so files can't
+/// be written outside of the directory. Parts without a filename are ignored.
+///
+/// Responds with a 201 status, and the names of the saved files, one per line.
+///
+/// # Errors
+/// - If the body is not multipart
+/// - If some file can't be written
+/// - If the request returns an Error variant on send
+pub fn upload_handler(req: &mut HttpRequest) -> Result<()> {
+    let dir = PathBuf::from(&*req.filename()?);
+    if !dir.is_dir() {
+        return req.not_found();
+    }
+    let mut saved = String::new();
+    let mut multipart = req.multipart()?;
+    while let Some(mut part) = multipart.next_part()? {
+        let Some(name) = part.filename().and_then(upload_filename).map(str::to_owned) else {
+            continue;
+        };
+        let mut file = File::create(dir.join(&name))?;
+        io::copy(&mut part, &mut file)?;
+        saved.push_str(&name);
+        saved.push('\n');
+    }
+    req.set_status(Status::CREATED).respond_str(&saved)
+}
+
+/// Returns the last component of an uploaded filename,
+/// or None if it's not a valid filename
+fn upload_filename(filename: &str) -> Option<&str> {
+    let name = filename.rsplit(['/', '\\']).next()?;
+    (!matches!(name, "" | "." | "..")).then_some(name)
+}
+
 /// Delete the filename
 ///
+/// Like [`post_handler`], it evaluates the request's preconditions first.
+///
 /// # Errors
 /// If the request returns an Error variant on send
 pub fn delete_handler(req: &mut HttpRequest) -> Result<()> {
-    match fs::remove_file(&*req.filename()?) {
+    delete_file(req, &FileConfig::default())
+}
+
+fn delete_file(req: &mut HttpRequest, config: &FileConfig) -> Result<()> {
+    let filename = req.filename()?;
+    /* Preconditions are ignored if the file doesn't exist, so it's a 404 */
+    if let Some(current) = file_validators(&filename, config.etag_mode)?
+        && conditional::evaluate(req, Some(&current)) != Precondition::Passed
+    {
+        return req
+            .set_status(Status::PRECONDITION_FAILED)
+            .respond_error_page();
+    }
+    match fs::remove_file(&*filename) {
         Ok(()) => req.ok(),
         Err(err) => match err.kind() {
             io::ErrorKind::PermissionDenied => req.forbidden(),
@@ -460,10 +775,14 @@ pub fn log_file(filename: &str) -> crate::Result<Box<dyn Interceptor>> {
 /// # Errors
 /// If the request returns an Error variant on send
 pub fn root_handler(req: &mut HttpRequest) -> Result<()> {
+    root_file(req, &FileConfig::default())
+}
+
+fn root_file(req: &mut HttpRequest, config: &FileConfig) -> Result<()> {
     if file_exists("index.html") {
         req.set_url("/index.html");
     }
-    cat_handler(req)
+    cat_file(req, config)
 }
 
 pub fn redirect(uri: impl Into<Box<str>>) -> impl RequestHandler {
@@ -471,6 +790,108 @@ pub fn redirect(uri: impl Into<Box<str>>) -> impl RequestHandler {
     move |req: &mut HttpRequest| {
         req.set_header("Location", &*uri);
         req.set_header("Content-Length", "0");
-        req.set_status(308).respond()
+        req.set_status(Status::PERMANENT_REDIRECT).respond()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use http::{HttpMethod, HttpVersion, Status};
+
+    use super::{
+        Compression, FileConfig, RequestHandler,
+        cache::{CacheMatcher, CachePreset, CacheRule},
+        cat_handler,
+        conditional::ETagMode,
+    };
+    use crate::HttpRequest;
+
+    /// Gets a text file of the crate, that is compressed
+    fn get(headers: &[(&str, &str)]) -> HttpRequest {
+        let mut builder = HttpRequest::builder()
+            .method(HttpMethod::GET)
+            .url("/COPYING")
+            .version(HttpVersion::Http11)
+            .header("Accept-Encoding", "gzip");
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        let mut req = builder.build();
+        (Compression::default().interceptor())(&mut req);
+        /* The request has a dummy stream, that can't be written.
+         * Sending the body fails, but the headers are set by then */
+        let _ = cat_handler(&mut req);
+        req
+    }
+
+    #[test]
+    fn compressed_etag() {
+        let req = get(&[]);
+        let headers = req.response_headers();
+        assert_eq!(headers.get("Content-Encoding"), Some("gzip"));
+        let etag = headers.get("ETag").unwrap().to_owned();
+        assert!(etag.ends_with("-gzip\""), "{etag}");
+        let identity = etag.replace("-gzip", "");
+
+        /* The compressed tag can't be used to request identity ranges */
+        let req = get(&[("Range", "bytes=0-9"), ("If-Range", &etag)]);
+        assert_eq!(req.status(), Status::OK);
+        assert_eq!(req.response_headers().get("Content-Encoding"), Some("gzip"));
+
+        let req = get(&[("Range", "bytes=0-9"), ("If-Range", &identity)]);
+        assert_eq!(req.status(), Status::PARTIAL_CONTENT);
+        assert_eq!(req.response_headers().get("Content-Encoding"), None);
+        assert_eq!(req.response_headers().get("ETag"), Some(&*identity));
+
+        /* Both tags validate the client's copy */
+        let req = get(&[("If-None-Match", &etag)]);
+        assert_eq!(req.status(), Status::NOT_MODIFIED);
+        assert_eq!(req.response_headers().get("ETag"), Some(&*etag));
+        let req = get(&[("If-None-Match", &identity)]);
+        assert_eq!(req.status(), Status::NOT_MODIFIED);
+        assert_eq!(req.response_headers().get("ETag"), Some(&*identity));
+    }
+
+    #[test]
+    fn etag_modes() {
+        let etag = |files: &FileConfig| {
+            let mut req = HttpRequest::builder().url("/COPYING").build();
+            let _ = files.head_handler().handle(&mut req);
+            req.response_headers().get("ETag").unwrap().to_owned()
+        };
+        let metadata = etag(&FileConfig::default());
+        let hash = etag(&FileConfig {
+            etag_mode: ETagMode::ContentHash,
+            ..FileConfig::default()
+        });
+        assert_ne!(metadata, hash);
+        /* The length, and a 64 bit hash */
+        let (_, hash) = hash.trim_matches('"').split_once('-').unwrap();
+        assert_eq!(hash.len(), 16);
+    }
+
+    #[test]
+    fn cache_rules() {
+        let cache_control = |files: &FileConfig| {
+            let mut req = HttpRequest::builder().url("/COPYING").build();
+            let _ = files.head_handler().handle(&mut req);
+            req.response_headers()
+                .get("Cache-Control")
+                .map(str::to_owned)
+        };
+        assert_eq!(cache_control(&FileConfig::default()), None);
+        let files = FileConfig {
+            cache_rules: vec![
+                CacheRule::new(CacheMatcher::Prefix("/assets/".into())).cache_control("no-store"),
+                CacheRule::new(CacheMatcher::Mime("text/*".into())).preset(CachePreset::Immutable),
+                CacheRule::new(CacheMatcher::Glob("*".into())).cache_control("no-cache"),
+            ],
+            ..FileConfig::default()
+        };
+        assert_eq!(
+            cache_control(&files).as_deref(),
+            Some(CachePreset::Immutable.directives())
+        );
     }
 }
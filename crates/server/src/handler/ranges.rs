@@ -1,56 +1,312 @@
-use std::ops::Range;
+//! Range requests
+//!
+//! See [RFC 9110 Section 14](https://www.rfc-editor.org/rfc/rfc9110#section-14)
+
+use std::{
+    collections::VecDeque,
+    hash::{BuildHasher, RandomState},
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    ops::Range,
+};
 
 use crate::{Result, err};
 
-pub fn get_range_for(range: &str, len: u64) -> Result<Range<u64>> {
-    let mut range = range.split('=');
-    let unit = range.next().ok_or("Missing unit")?;
-    if unit != "bytes" {
-        /* TODO */
+/// Max number of ranges, after coalescing them.
+///
+/// Requests with more ranges than this are served the whole
+/// representation, since so many small parts would be mostly overhead.
+const MAX_RANGES: usize = 64;
+
+/// Parses a *Range* header, for a representation of `len` bytes.
+///
+/// The ranges are sorted, and the ones that overlap or are
+/// adjacent are coalesced. They're returned as `start..end`,
+/// with the `end` exclusive.
+///
+/// An empty list means that none of the ranges can be satisfied,
+/// so the response should be a *416 Range Not Satisfiable*.
+///
+/// # Errors
+/// If the header is invalid, or uses an unknown unit.
+/// In that case, the header should be ignored.
+pub fn get_ranges_for(header: &str, len: u64) -> Result<Vec<Range<u64>>> {
+    let (unit, set) = header.split_once('=').ok_or("Missing unit")?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
         return err!("Unknown unit");
     }
-    /* TODO: Implement more ranges
-     * -1024 (get the last 1024) */
-    let range = range.next().ok_or("Missing range")?;
-    let mut range = range.split('-');
-    let start = range.next().unwrap_or("").parse()?;
-    let end = range.next().unwrap_or("").parse().unwrap_or(len);
-    Ok(start..end)
+    let mut ranges = Vec::new();
+    let mut empty = true;
+    for spec in set.split(',').map(|s| s.trim_matches([' ', '\t'])) {
+        if spec.is_empty() {
+            continue;
+        }
+        empty = false;
+        let (first, last) = spec.split_once('-').ok_or("Missing '-' in range")?;
+        let range = if first.is_empty() {
+            /* Suffix range: the last N bytes */
+            let suffix: u64 = parse_pos(last)?;
+            (suffix > 0 && len > 0).then(|| len.saturating_sub(suffix)..len)
+        } else {
+            let first = parse_pos(first)?;
+            let last = if last.is_empty() {
+                u64::MAX
+            } else {
+                parse_pos(last)?
+            };
+            if last < first {
+                return err!("Invalid range: {spec}");
+            }
+            (first < len).then(|| first..last.saturating_add(1).min(len))
+        };
+        ranges.extend(range);
+    }
+    if empty {
+        return err!("Missing range");
+    }
+    let ranges = coalesce(ranges);
+    if ranges.len() > MAX_RANGES {
+        return err!("Too many ranges");
+    }
+    Ok(ranges)
+}
+
+fn parse_pos(pos: &str) -> Result<u64> {
+    if pos.is_empty() || !pos.bytes().all(|b| b.is_ascii_digit()) {
+        return err!("Invalid range position: \"{pos}\"");
+    }
+    /* Positions too big to represent are beyond the end of any file */
+    Ok(pos.parse().unwrap_or(u64::MAX))
+}
+
+fn coalesce(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut result: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match result.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => result.push(range),
+        }
+    }
+    result
+}
+
+/// Formats the value of a *Content-Range* header
+#[must_use]
+pub fn content_range(range: &Range<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{len}", range.start, range.end - 1)
+}
+
+/// A `multipart/byteranges` body
+pub struct ByteRanges {
+    boundary: String,
+    /// Headers of each part, and the range they contain
+    parts: Vec<(String, Range<u64>)>,
+}
+
+impl ByteRanges {
+    /// Creates a body with the given ranges of a representation of
+    /// `len` bytes. Each part has the given *Content-Type*.
+    #[must_use]
+    pub fn new(ranges: &[Range<u64>], len: u64, content_type: Option<&str>) -> Self {
+        let boundary = format!("{:016x}", RandomState::new().hash_one(ranges));
+        let parts = ranges
+            .iter()
+            .map(|range| {
+                let content_type = content_type
+                    .map(|ct| format!("Content-Type: {ct}\r\n"))
+                    .unwrap_or_default();
+                let head = format!(
+                    "--{boundary}\r\n{content_type}Content-Range: {}\r\n\r\n",
+                    content_range(range, len)
+                );
+                (head, range.clone())
+            })
+            .collect();
+        Self { boundary, parts }
+    }
+
+    /// Returns the *Content-Type* of the body
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        format!("multipart/byteranges; boundary={}", self.boundary)
+    }
+
+    fn end(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+
+    /// Returns the length of the body
+    #[must_use]
+    pub fn content_length(&self) -> u64 {
+        let parts: u64 = self
+            .parts
+            .iter()
+            .map(|(head, range)| head.len() as u64 + range.end - range.start + 2)
+            .sum();
+        parts + self.end().len() as u64
+    }
+
+    /// Returns a reader for the body, taking the ranges from `source`
+    pub fn reader<R: Read + Seek>(self, source: R) -> impl Read {
+        let end = self.end();
+        let mut segments: VecDeque<_> = self
+            .parts
+            .into_iter()
+            .map(|(head, range)| (head, Some(range)))
+            .collect();
+        segments.push_back((end, None));
+        ByteRangesReader {
+            source,
+            segments,
+            text: Cursor::default(),
+            remaining: 0,
+        }
+    }
+}
+
+struct ByteRangesReader<R: Read + Seek> {
+    source: R,
+    /// Text to send before each range
+    segments: VecDeque<(String, Option<Range<u64>>)>,
+    text: Cursor<Vec<u8>>,
+    /// Bytes left to read from the current range
+    remaining: u64,
+}
+
+impl<R: Read + Seek> Read for ByteRangesReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.text.read(buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+            if self.remaining > 0 {
+                let max = buf
+                    .len()
+                    .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+                let n = self.source.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                self.remaining -= n as u64;
+                if self.remaining == 0 {
+                    self.text = Cursor::new(b"\r\n".to_vec());
+                }
+                return Ok(n);
+            }
+            let Some((text, range)) = self.segments.pop_front() else {
+                return Ok(0);
+            };
+            self.text = Cursor::new(text.into_bytes());
+            if let Some(range) = range {
+                self.source.seek(SeekFrom::Start(range.start))?;
+                self.remaining = range.end - range.start;
+            }
+        }
+    }
 }
 
 #[cfg(test)]
-#[allow(clippy::unwrap_used)]
-#[allow(clippy::panic)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::single_range_in_vec_init,
+    clippy::cast_possible_truncation
+)]
 mod test {
-    use super::get_range_for;
+    use std::io::{Cursor, Read};
+
+    use http::multipart::Multipart;
+
+    use super::{ByteRanges, get_ranges_for};
+
+    #[test]
+    fn single() {
+        assert_eq!(get_ranges_for("bytes=0-", 1024).unwrap(), [0..1024]);
+        assert_eq!(get_ranges_for("bytes=40-70", 1024).unwrap(), [40..71]);
+        assert_eq!(get_ranges_for("bytes=1000-2000", 1024).unwrap(), [
+            1000..1024
+        ]);
+        assert_eq!(get_ranges_for("Bytes = 0-0", 1024).unwrap(), [0..1]);
+    }
 
     #[test]
-    fn unbounded() {
-        let range = get_range_for("bytes=0-", 1024).unwrap();
-        assert_eq!(range, 0..1024);
-        let range = get_range_for("bytes=12", 1024).unwrap();
-        assert_eq!(range, 12..1024);
+    fn suffix() {
+        assert_eq!(get_ranges_for("bytes=-24", 1024).unwrap(), [1000..1024]);
+        assert_eq!(get_ranges_for("bytes=-2000", 1024).unwrap(), [0..1024]);
     }
 
     #[test]
-    fn bounded() {
-        let range = get_range_for("bytes=40-70", 1024).unwrap();
-        assert_eq!(range, 40..70);
+    fn lists() {
+        let ranges = get_ranges_for("bytes=500-599, -100,0-99", 1000).unwrap();
+        assert_eq!(ranges, [0..100, 500..600, 900..1000]);
+        /* Overlapping and adjacent ranges are coalesced */
+        let ranges = get_ranges_for("bytes=0-99,50-149,150-199,300-", 1000).unwrap();
+        assert_eq!(ranges, [0..200, 300..1000]);
+        let ranges = get_ranges_for("bytes=, 0-9 ,,", 1000).unwrap();
+        assert_eq!(ranges, [0..10]);
     }
 
     #[test]
-    fn no_unit() {
-        match get_range_for("=0-", 1024) {
-            Ok(_) => panic!(),
-            Err(err) => assert_eq!("Unknown unit", err.get_message()),
+    fn unsatisfiable() {
+        for header in ["bytes=1024-", "bytes=-0", "bytes=2000-3000, 1500-"] {
+            assert!(get_ranges_for(header, 1024).unwrap().is_empty(), "{header}");
         }
+        assert!(get_ranges_for("bytes=-10", 0).unwrap().is_empty());
+        /* Unsatisfiable ranges are dropped from the list */
+        assert_eq!(get_ranges_for("bytes=2000-,0-1", 1024).unwrap(), [0..2]);
     }
 
     #[test]
-    fn no_range() {
-        match get_range_for("bytes=", 1024) {
-            Ok(_) => panic!(),
-            Err(err) => assert_eq!("cannot parse integer from empty string", err.get_message()),
+    fn invalid() {
+        let cases = [
+            ("=0-", "Unknown unit"),
+            ("items=0-1", "Unknown unit"),
+            ("bytes", "Missing unit"),
+            ("bytes=", "Missing range"),
+            ("bytes=12", "Missing '-' in range"),
+            ("bytes=70-40", "Invalid range: 70-40"),
+            ("bytes=-", "Invalid range position: \"\""),
+            ("bytes=1-a", "Invalid range position: \"a\""),
+            ("bytes=+1-2", "Invalid range position: \"+1\""),
+        ];
+        for (header, msg) in cases {
+            let err = get_ranges_for(header, 1024).unwrap_err();
+            assert_eq!(err.get_message(), msg, "{header}");
+        }
+        let many: Vec<_> = (0..100).map(|i| format!("{}-{}", i * 10, i * 10)).collect();
+        let header = format!("bytes={}", many.join(","));
+        assert!(get_ranges_for(&header, 1024).is_err());
+    }
+
+    #[test]
+    fn multipart() {
+        let data: Vec<u8> = (0..100).collect();
+        let ranges = [2..5, 90..100];
+        let body = ByteRanges::new(&ranges, 100, Some("application/octet-stream"));
+        let content_type = body.content_type();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_owned();
+        let len = body.content_length();
+
+        let mut out = Vec::new();
+        body.reader(Cursor::new(&data))
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out.len() as u64, len);
+        assert!(out.ends_with(format!("\r\n--{boundary}--\r\n").as_bytes()));
+
+        let mut multipart = Multipart::new(&out[..], &boundary);
+        for range in ranges {
+            let mut part = multipart.next_part().unwrap().unwrap();
+            let expected = format!("bytes {}-{}/100", range.start, range.end - 1);
+            assert_eq!(part.headers().get("Content-Range"), Some(&*expected));
+            assert_eq!(part.content_type(), Some("application/octet-stream"));
+            let mut content = Vec::new();
+            part.read_to_end(&mut content).unwrap();
+            assert_eq!(content, data[range.start as usize..range.end as usize]);
         }
+        assert!(multipart.next_part().unwrap().is_none());
     }
 }
@@ -47,7 +47,7 @@ pub mod prelude {
     pub use crate::{
         HttpServer,
         config::*,
-        handler::{self, AuthConfig, Handler},
+        handler::{self, AuthConfig, Compression, FileConfig, Handler},
     };
 }
 use prelude::*;
@@ -62,11 +62,11 @@ use std::{
     net::{TcpListener, TcpStream},
     sync::Arc,
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 pub use config::ServerConfig;
-use http::HttpStream;
+use http::{HttpStream, date::format_http_date, h2};
 use pool::ThreadPool;
 
 mod log;
@@ -115,39 +115,123 @@ fn peek_stream(
     Ok(result)
 }
 
+/// Tells the client whether the connection will be kept open after the response
+fn set_connection_header(req: &mut HttpRequest, persistent: bool) {
+    if !persistent {
+        req.set_header("Connection", "close");
+    } else if !req.version().is_persistent_by_default() {
+        req.set_header("Connection", "keep-alive");
+    }
+}
+
+/// Returns true if the connection was taken over by another
+/// protocol, like a [`WebSocket`](http::websocket::WebSocket)
+fn switched_protocols(req: &HttpRequest) -> bool {
+    req.status() == Status::SWITCHING_PROTOCOLS
+}
+
+/// Returns true if the response told the client that the connection will
+/// be closed. For example, an HTTP/1.0 [event stream](HttpRequest::respond_sse)
+/// ends when the connection does.
+fn closes_connection(req: &HttpRequest) -> bool {
+    req.response_headers()
+        .get("Connection")
+        .is_some_and(|c| c.eq_ignore_ascii_case("close"))
+}
+
+/// Handles the request, after adding the headers that every response has
+fn handle(handlers: &Handler, req: &mut HttpRequest) -> Result<()> {
+    req.set_header("Date", format_http_date(SystemTime::now()));
+    handlers.handle(req)
+}
+
+/// Idle timeout of HTTP/2 connections when keep-alive is disabled
+///
+/// HTTP/2 connections are always persistent, so they
+/// can't be closed right after the first response.
+const H2_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn serve_h2(conn: h2::Connection, handlers: &Handler, keep_alive_timeout: Duration) -> Result<()> {
+    let idle_timeout = if keep_alive_timeout.is_zero() {
+        H2_IDLE_TIMEOUT
+    } else {
+        keep_alive_timeout
+    };
+    log_info!("[{:?}] Start HTTP/2 connection", thread::current().id());
+    conn.idle_timeout(idle_timeout)
+        .serve(|req| handle(handlers, req).inspect_err(|err| log_error!("{err}")))?;
+    log_info!("[{:?}] End HTTP/2 connection", thread::current().id());
+    Ok(())
+}
+
 fn handle_connection(
     stream: TcpStream,
     handlers: &Handler,
     keep_alive_timeout: Duration,
     keep_alive_requests: u16,
+    limits: ParserLimits,
+    http2: bool,
 
     #[cfg(feature = "tls")] tls_config: Option<&Arc<rustls::ServerConfig>>,
 ) -> Result<()> {
+    /* Whether h2 was negotiated with ALPN, and if the connection is in cleartext */
     #[cfg(feature = "tls")]
-    let mut req = match tls_config {
+    let (stream, alpn_h2, cleartext): (Box<dyn HttpStream>, bool, bool) = match tls_config {
         Some(config) => {
-            let conn = rustls::ServerConnection::new(Arc::clone(config))
+            let mut stream = stream;
+            let mut conn = rustls::ServerConnection::new(Arc::clone(config))
                 .map_err(|err| format!("TLS error: {err}"))?;
+            while conn.is_handshaking() {
+                conn.complete_io(&mut stream)?;
+            }
+            let alpn_h2 = conn.alpn_protocol() == Some(&b"h2"[..]);
             let tls_stream = rustls::StreamOwned::new(conn, stream);
-            HttpRequest::parse(tls_stream)?
+            (Box::new(tls_stream), alpn_h2, false)
         }
-        None => HttpRequest::parse(stream)?,
+        None => (Box::new(stream), false, true),
     };
 
     #[cfg(not(feature = "tls"))]
-    let mut req = HttpRequest::parse(stream)?;
+    let (stream, alpn_h2, cleartext): (Box<dyn HttpStream>, bool, bool) =
+        (Box::new(stream), false, true);
+
+    let mut stream = BufReader::new(stream);
+    let h2c = http2 && cleartext;
+    if alpn_h2 || (h2c && h2::starts_with_preface(&mut stream)?) {
+        let conn = h2::Connection::new(stream, limits);
+        return serve_h2(conn, handlers, keep_alive_timeout);
+    }
 
-    handlers.handle(&mut req)?;
+    let mut req = HttpRequest::parse_buffered(stream, limits)?;
+    if h2c && h2::is_upgrade(&req) {
+        let conn = h2::Connection::upgrade(req)?;
+        return serve_h2(conn, handlers, keep_alive_timeout);
+    }
 
-    let connection = req.header("Connection");
     let keep_alive = keep_alive_timeout.as_millis() > 0;
-    if connection.is_some_and(|conn| conn == "keep-alive") && keep_alive {
+    let persistent = keep_alive && keep_alive_requests > 1 && req.is_persistent();
+    set_connection_header(&mut req, persistent);
+    handle(handlers, &mut req)?;
+
+    if persistent && !switched_protocols(&req) && !closes_connection(&req) {
         let start = Instant::now();
         let mut n = 1;
         log_info!("[{:?}] Start keep alive", thread::current().id());
         while start.elapsed() < keep_alive_timeout && n < keep_alive_requests {
             let offset = keep_alive_timeout - start.elapsed();
 
+            /* The handler declined the body of an `Expect: 100-continue`
+             * request. The response already had a `Connection: close` */
+            if req.expects_continue() && req.has_body()? {
+                break;
+            }
+            /* Skip the body the handler didn't read, so that
+             * it isn't parsed as the start of the next request */
+            if let Err(err) = req.discard_body() {
+                log_warn!("Closing connection: {err}");
+                break;
+            }
+
             match peek_stream(req.stream_mut(), offset) {
                 Ok(false) => break,
                 Err(err) => {
@@ -158,11 +242,13 @@ fn handle_connection(
             }
 
             req = req.keep_alive()?;
-            handlers.handle(&mut req)?;
             n += 1;
 
-            let connection = req.header("Connection");
-            if connection.is_some_and(|conn| conn == "close") {
+            let persistent = n < keep_alive_requests && req.is_persistent();
+            set_connection_header(&mut req, persistent);
+            handle(handlers, &mut req)?;
+
+            if !persistent || switched_protocols(&req) || closes_connection(&req) {
                 break;
             }
         }
@@ -208,9 +294,20 @@ impl HttpServer {
         let handler = handler.unwrap();
         let timeout = config.keep_alive_timeout;
         let req = config.keep_alive_requests;
+        let limits = config.parser_limits;
+        let http2 = config.http2;
 
         #[cfg(feature = "tls")]
-        let tls_config = config.tls_config.as_ref();
+        let tls_config = config.tls_config.map(|mut tls| {
+            if !http2 {
+                Arc::make_mut(&mut tls)
+                    .alpn_protocols
+                    .retain(|p| p != b"h2");
+            }
+            tls
+        });
+        #[cfg(feature = "tls")]
+        let tls_config = tls_config.as_ref();
 
         println!("Sever listening on port {}", config.port);
 
@@ -222,6 +319,8 @@ impl HttpServer {
                         &handler,
                         timeout,
                         req,
+                        limits,
+                        http2,
                         #[cfg(feature = "tls")]
                         tls_config,
                     )
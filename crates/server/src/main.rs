@@ -19,7 +19,10 @@ fn load_lib(handler: &mut Handler, name: &str) -> Result<Library> {
 }
 
 fn get_handler(config: &ServerConfig) -> Result<(Option<Library>, Handler)> {
-    let mut handler = Handler::default();
+    let mut handler = Handler::with_files(&config.files);
+    if config.compression {
+        handler.pre_interceptor(Compression::default().interceptor());
+    }
     let mut _lib = None;
 
     if let Some(path) = &config.setup_lib {
@@ -37,8 +40,10 @@ fn get_handler(config: &ServerConfig) -> Result<(Option<Library>, Handler)> {
         let mut s = "".to_string();
         for (k, v) in req.params() {
             s.push_str(k);
-            s.push_str(" = ");
-            s.push_str(v);
+            if let Some(v) = v {
+                s.push_str(" = ");
+                s.push_str(v);
+            }
             s.push('\n');
         }
         req.respond_str(&s)
@@ -77,7 +82,7 @@ fn get_handler(config: &ServerConfig) -> Result<(Option<Library>, Handler)> {
 
             let fname = &*req.filename().unwrap();
             if !Path::new(fname).exists() {
-                return req.set_status(404).respond_error_page();
+                return req.set_status(Status::NOT_FOUND).respond_error_page();
             }
             let output = Command::new("php")
                 .arg(fname)